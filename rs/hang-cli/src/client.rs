@@ -11,19 +11,34 @@ pub async fn run_client(client: moq_native::Client, url: Url, name: String, publ
 	tracing::info!(%url, %name, "connecting");
 
 	// Establish the connection, not providing a subscriber.
-	let session = client.connect_with_fallback(url, origin.consumer, None).await?;
+	// The session reconnects whenever the connection is lost, or migrates if the relay sends a GOAWAY.
+	let mut session = client.connect_reconnecting(url, origin.consumer, None);
 
-	#[cfg(unix)]
-	// Notify systemd that we're ready.
-	let _ = sd_notify::notify(true, &[sd_notify::NotifyState::Ready]);
+	let run = publish.run();
+	tokio::pin!(run);
 
-	tokio::select! {
-		res = publish.run() => res,
-		res = session.closed() => res.map_err(Into::into),
-		_ = tokio::signal::ctrl_c() => {
-			session.close(moq_lite::Error::Cancel);
-			tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-			Ok(())
-		},
+	loop {
+		tokio::select! {
+			res = &mut run => return res,
+			state = session.changed() => match state {
+				moq_native::ConnectionState::Connected(url) => {
+					tracing::info!(%url, "connected");
+
+					#[cfg(unix)]
+					// Notify systemd that we're ready.
+					let _ = sd_notify::notify(true, &[sd_notify::NotifyState::Ready]);
+				}
+				moq_native::ConnectionState::Reconnecting { attempt, delay } => {
+					tracing::warn!(%attempt, ?delay, "disconnected, reconnecting");
+				}
+				moq_native::ConnectionState::Failed(err) => anyhow::bail!("failed to connect: {err}"),
+				_ => {}
+			},
+			_ = tokio::signal::ctrl_c() => {
+				session.close();
+				tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+				return Ok(());
+			},
+		}
 	}
 }
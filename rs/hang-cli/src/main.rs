@@ -27,6 +27,7 @@ pub struct Cli {
 }
 
 #[derive(Subcommand, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum Command {
 	Serve {
 		#[command(flatten)]
@@ -52,7 +53,7 @@ pub enum Command {
 		/// The URL of the MoQ server.
 		///
 		/// The URL must start with `https://` or `http://`.
-		/// - If `http` is used, a HTTP fetch to "/certificate.sha256" is first made to get the TLS certificiate fingerprint (insecure).
+		/// - If `http` is used, a HTTP fetch to "/certificate.json" is first made to get the TLS certificiate fingerprints (insecure).
 		/// - If `https` is used, then A WebTransport connection is made via QUIC to the provided host/port.
 		///
 		/// The `?jwt=` query parameter is used to provide a JWT token from moq-token-cli.
@@ -2,7 +2,7 @@ use anyhow::Context;
 use axum::handler::HandlerWithoutStateExt;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use axum::{Router, http::Method, routing::get};
+use axum::{Json, Router, http::Method, routing::get};
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
@@ -25,20 +25,28 @@ pub async fn run_web(
 		(StatusCode::NOT_FOUND, "Not found")
 	}
 
-	let fingerprint_handler = move || async move {
-		// Get the first certificate's fingerprint.
-		// TODO serve all of them so we can support multiple signature algorithms.
-		tls_info
-			.read()
-			.expect("tls_info read lock poisoned")
-			.fingerprints
-			.first()
-			.expect("missing certificate")
-			.clone()
+	let fingerprint_handler = {
+		let tls_info = tls_info.clone();
+		move || async move {
+			// Get the preferred certificate's fingerprint, for clients that only support one.
+			tls_info
+				.read()
+				.expect("tls_info read lock poisoned")
+				.fingerprints
+				.first()
+				.expect("missing certificate")
+				.clone()
+		}
+	};
+
+	let fingerprints_handler = move || async move {
+		let fingerprints = tls_info.read().expect("tls_info read lock poisoned").hashes.clone();
+		Json(fingerprints)
 	};
 
 	let mut app = Router::new()
 		.route("/certificate.sha256", get(fingerprint_handler))
+		.route("/certificate.json", get(fingerprints_handler))
 		.layer(CorsLayer::new().allow_origin(Any).allow_methods([Method::GET]));
 
 	// If a public directory is provided, serve it.
@@ -24,6 +24,9 @@ pub struct GroupConsumer {
 	// The current frame index
 	index: usize,
 
+	// The frame being read, saved so `read` is cancel safe.
+	active: Option<moq_lite::FrameConsumer>,
+
 	// The any buffered frames in the group.
 	buffered: VecDeque<Frame>,
 
@@ -37,6 +40,7 @@ impl GroupConsumer {
 		Self {
 			group,
 			index: 0,
+			active: None,
 			buffered: VecDeque::new(),
 			max_timestamp: None,
 		}
@@ -60,10 +64,16 @@ impl GroupConsumer {
 	}
 
 	async fn read_unbuffered(&mut self) -> Result<Option<Frame>> {
-		let Some(mut frame) = self.group.next_frame().await? else {
+		// Save the frame in case we're cancelled while waiting for the rest of it, otherwise it would be skipped.
+		if self.active.is_none() {
+			self.active = self.group.next_frame().await?;
+		}
+
+		let Some(frame) = self.active.as_mut() else {
 			return Ok(None);
 		};
 		let payload = frame.read_chunks().await?;
+		self.active = None;
 
 		let mut payload = BufList::from_iter(payload);
 
@@ -91,7 +101,8 @@ impl GroupConsumer {
 				_ => (),
 			}
 
-			match self.read().await {
+			// Skip the buffer, otherwise we'd spin forever popping and pushing the same frames.
+			match self.read_unbuffered().await {
 				Ok(Some(frame)) => self.buffered.push_back(frame),
 				// Otherwise block forever so we don't return from FuturesUnordered
 				_ => std::future::pending().await,
@@ -114,3 +125,76 @@ impl std::ops::Deref for GroupConsumer {
 		&self.group
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use bytes::BytesMut;
+	use futures::FutureExt;
+	use moq_lite::{coding::Encode, lite};
+
+	fn write(group: &mut moq_lite::GroupProducer, millis: u64) {
+		let mut frame = BytesMut::new();
+		Timestamp::from_millis(millis)
+			.unwrap()
+			.encode(&mut frame, lite::Version::Draft02);
+		group.write_frame(frame.freeze());
+	}
+
+	#[test]
+	fn buffer_until() {
+		let mut group = moq_lite::Group { sequence: 0 }.produce();
+		write(&mut group.producer, 0);
+		write(&mut group.producer, 40);
+
+		let mut consumer = GroupConsumer::new(group.consumer);
+		let cutoff = Timestamp::from_millis(100).unwrap();
+
+		// The cutoff hasn't been reached, so this should block once the available frames are buffered.
+		// It used to spin forever, re-reading the frames it had just buffered.
+		assert!(consumer.buffer_until(cutoff).now_or_never().is_none());
+		assert_eq!(consumer.max_timestamp(), Some(Timestamp::from_millis(40).unwrap()));
+
+		write(&mut group.producer, 120);
+		let timestamp = consumer.buffer_until(cutoff).now_or_never();
+		assert_eq!(timestamp, Some(Timestamp::from_millis(120).unwrap()));
+
+		// The buffered frames are returned in order.
+		for (millis, keyframe) in [(0, true), (40, false), (120, false)] {
+			let frame = consumer.read().now_or_never().unwrap().unwrap().unwrap();
+			assert_eq!(frame.timestamp, Timestamp::from_millis(millis).unwrap());
+			assert_eq!(frame.keyframe, keyframe);
+		}
+	}
+
+	#[test]
+	fn read_cancelled() {
+		let mut group = moq_lite::Group { sequence: 0 }.produce();
+		let mut consumer = GroupConsumer::new(group.consumer);
+
+		// Start a frame but don't finish it yet.
+		let mut frame = group.producer.create_frame(moq_lite::Frame::streaming());
+		let mut timestamp = BytesMut::new();
+		Timestamp::from_millis(0)
+			.unwrap()
+			.encode(&mut timestamp, lite::Version::Draft02);
+		frame.write_chunk(timestamp.freeze());
+
+		// Cancel the read while it's waiting for the rest of the frame.
+		assert!(consumer.read().now_or_never().is_none());
+
+		frame.write_chunk(bytes::Bytes::from_static(b"payload"));
+		frame.close();
+		write(&mut group.producer, 40);
+
+		// The partial frame used to be dropped, skipping straight to the next frame.
+		let frame = consumer.read().now_or_never().unwrap().unwrap().unwrap();
+		assert_eq!(frame.timestamp, Timestamp::from_millis(0).unwrap());
+		assert!(frame.keyframe);
+
+		let frame = consumer.read().now_or_never().unwrap().unwrap().unwrap();
+		assert_eq!(frame.timestamp, Timestamp::from_millis(40).unwrap());
+		assert!(!frame.keyframe);
+	}
+}
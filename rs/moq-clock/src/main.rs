@@ -68,17 +68,14 @@ async fn main() -> anyhow::Result<()> {
 
 			origin.producer.publish_broadcast(&config.broadcast, broadcast.consumer);
 
-			let session = client
-				.connect_with_fallback(config.url, Some(origin.consumer), None)
-				.await?;
+			// Reconnect whenever the connection is lost, republishing the broadcast.
+			let _session = client.connect_reconnecting(config.url, origin.consumer, None);
 
-			tokio::select! {
-				res = session.closed() => res.context("session closed"),
-				_ = clock.run() => Ok(()),
-			}
+			clock.run().await
 		}
 		Command::Subscribe => {
-			let session = client.connect_with_fallback(config.url, None, origin.producer).await?;
+			// Reconnect whenever the connection is lost; the broadcast is reannounced once it's back.
+			let _session = client.connect_reconnecting(config.url, None, origin.producer);
 
 			// NOTE: We could just call `session.consume_broadcast(&config.broadcast)` instead,
 			// However that won't work with IETF MoQ and the current OriginConsumer API the moment.
@@ -107,7 +104,6 @@ async fn main() -> anyhow::Result<()> {
 							tracing::warn!(broadcast = %path, "broadcast is offline, waiting...");
 						}
 					},
-					res = session.closed() => return res.context("session closed"),
 					// NOTE: This drops clock when a new announce arrives, canceling it.
 					Some(res) = async { Some(clock.take()?.run().await) } => res.context("clock error")?,
 				}
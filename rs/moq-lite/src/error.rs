@@ -80,6 +80,10 @@ pub enum Error {
 
 	#[error("invalid role")]
 	InvalidRole,
+
+	/// The broadcast was closed by the producer.
+	#[error("closed")]
+	Closed,
 }
 
 impl Error {
@@ -105,6 +109,7 @@ impl Error {
 			Self::TooLarge => 18,
 			Self::TooManyParameters => 19,
 			Self::InvalidRole => 20,
+			Self::Closed => 21,
 			Self::App(app) => *app + 64,
 		}
 	}
@@ -15,6 +15,13 @@ pub enum ParameterVarInt {
 	Unknown(u64),
 }
 
+impl ParameterVarInt {
+	/// The DELIVERY_TIMEOUT message parameter in milliseconds.
+	///
+	/// Message parameters are a separate namespace from setup parameters, so it shares an ID with MAX_REQUEST_ID.
+	pub const DELIVERY_TIMEOUT: Self = Self::MaxRequestId;
+}
+
 #[derive(Debug, Copy, Clone, FromPrimitive, IntoPrimitive, Eq, Hash, PartialEq)]
 #[repr(u64)]
 pub enum ParameterBytes {
@@ -22,6 +29,8 @@ pub enum ParameterBytes {
 	AuthorizationToken = 3,
 	Authority = 5,
 	Implementation = 7,
+	// A moq-lite extension containing the broadcast metadata.
+	Metadata = 0xff0dad,
 	#[num_enum(catch_all)]
 	Unknown(u64),
 }
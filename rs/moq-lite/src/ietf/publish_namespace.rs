@@ -3,9 +3,9 @@
 use std::borrow::Cow;
 
 use crate::{
-	Path,
+	Metadata, Path,
 	coding::*,
-	ietf::{Message, Parameters, RequestId, Version},
+	ietf::{Message, ParameterBytes, Parameters, RequestId, Version},
 };
 
 use super::namespace::{decode_namespace, encode_namespace};
@@ -16,6 +16,8 @@ use super::namespace::{decode_namespace, encode_namespace};
 pub struct PublishNamespace<'a> {
 	pub request_id: RequestId,
 	pub track_namespace: Path<'a>,
+	/// Encoded as a parameter, only when not empty.
+	pub metadata: Metadata,
 }
 
 impl Message for PublishNamespace<'_> {
@@ -24,19 +26,31 @@ impl Message for PublishNamespace<'_> {
 	fn encode_msg<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
 		self.request_id.encode(w, version);
 		encode_namespace(w, &self.track_namespace, version);
-		0u8.encode(w, version); // number of parameters
+
+		let mut params = Parameters::default();
+		if !self.metadata.is_empty() {
+			let mut metadata = Vec::new();
+			self.metadata.encode(&mut metadata, version);
+			params.set_bytes(ParameterBytes::Metadata, metadata);
+		}
+		params.encode(w, version);
 	}
 
 	fn decode_msg<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
 		let request_id = RequestId::decode(r, version)?;
 		let track_namespace = decode_namespace(r, version)?;
 
-		// Ignore parameters, who cares.
-		let _params = Parameters::decode(r, version)?;
+		// Ignore any other parameters, who cares.
+		let params = Parameters::decode(r, version)?;
+		let metadata = match params.get_bytes(ParameterBytes::Metadata) {
+			Some(mut metadata) => Metadata::decode(&mut metadata, version)?,
+			None => Metadata::default(),
+		};
 
 		Ok(Self {
 			request_id,
 			track_namespace,
+			metadata,
 		})
 	}
 }
@@ -158,12 +172,28 @@ mod tests {
 		let msg = PublishNamespace {
 			request_id: RequestId(1),
 			track_namespace: Path::new("test/broadcast"),
+			metadata: Metadata::default(),
 		};
 
 		let encoded = encode_message(&msg);
 		let decoded: PublishNamespace = decode_message(&encoded).unwrap();
 
 		assert_eq!(decoded.track_namespace.as_str(), "test/broadcast");
+		assert!(decoded.metadata.is_empty());
+	}
+
+	#[test]
+	fn test_announce_metadata() {
+		let msg = PublishNamespace {
+			request_id: RequestId(1),
+			track_namespace: Path::new("test/broadcast"),
+			metadata: Metadata::new().with("title", "test").unwrap(),
+		};
+
+		let encoded = encode_message(&msg);
+		let decoded: PublishNamespace = decode_message(&encoded).unwrap();
+
+		assert_eq!(decoded.metadata, msg.metadata);
 	}
 
 	#[test]
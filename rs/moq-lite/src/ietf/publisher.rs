@@ -1,6 +1,12 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+	collections::HashMap,
+	sync::{
+		Arc,
+		atomic::{AtomicU64, Ordering},
+	},
+};
 
-use tokio::sync::oneshot;
+use tokio::{sync::oneshot, time::Instant};
 use web_async::{FuturesExt, Lock};
 use web_transport_trait::SendStream;
 
@@ -18,7 +24,8 @@ pub(super) struct Publisher<S: web_transport_trait::Session> {
 	control: Control,
 
 	// Drop in order to cancel the subscribe.
-	subscribes: Lock<HashMap<RequestId, oneshot::Sender<()>>>,
+	// The track is kept so a joining FETCH can report whether it has ended.
+	subscribes: Lock<HashMap<RequestId, (oneshot::Sender<()>, TrackConsumer)>>,
 
 	version: Version,
 }
@@ -40,7 +47,7 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		while let Some((path, active)) = self.origin.announced().await {
 			let suffix = path.to_owned();
 
-			if active.is_some() {
+			if let Some(active) = active {
 				tracing::debug!(broadcast = %self.origin.absolute(&path), "announce");
 
 				let request_id = self.control.next_request_id().await?;
@@ -48,6 +55,7 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 				self.control.send(ietf::PublishNamespace {
 					request_id,
 					track_namespace: suffix,
+					metadata: active.metadata().clone(),
 				})?;
 			} else {
 				tracing::debug!(broadcast = %self.origin.absolute(&path), "unannounce");
@@ -57,6 +65,9 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 			}
 		}
 
+		// Nothing more will be announced, but keep serving subscriptions until the session closes.
+		std::future::pending::<()>().await;
+
 		Ok(())
 	}
 
@@ -93,11 +104,11 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 			priority: msg.subscriber_priority,
 		};
 
-		let track = broadcast.subscribe_track(&track);
+		let track = broadcast.subscribe_track_with_timeout(&track, msg.delivery_timeout);
 
 		let (tx, rx) = oneshot::channel();
 		let mut subscribes = self.subscribes.lock();
-		subscribes.insert(request_id, tx);
+		subscribes.insert(request_id, (tx, track.clone()));
 
 		self.control.send(ietf::SubscribeOk {
 			request_id,
@@ -111,24 +122,28 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		let version = self.version;
 
 		web_async::spawn(async move {
-			if let Err(err) = Self::run_track(session, track, request_id, rx, version).await {
-				control
-					.send(ietf::PublishDone {
-						request_id,
-						status_code: 500,
-						stream_count: 0, // TODO send the correct value if we want the peer to block.
-						reason_phrase: err.to_string().into(),
-					})
-					.ok();
-			} else {
-				control
-					.send(ietf::PublishDone {
-						request_id,
-						status_code: 200,
-						stream_count: 0, // TODO send the correct value if we want the peer to block.
-						reason_phrase: "OK".into(),
-					})
-					.ok();
+			match Self::run_track(session, track, request_id, rx, version).await {
+				Err(err) => {
+					control
+						.send(ietf::PublishDone {
+							request_id,
+							status_code: 500,
+							stream_count: 0, // The track is aborted, so the peer shouldn't wait for streams.
+							reason_phrase: err.to_string().into(),
+						})
+						.ok();
+				}
+				Ok(stream_count) => {
+					// The peer waits for this many streams before closing the track, so it won't miss the final group.
+					control
+						.send(ietf::PublishDone {
+							request_id,
+							status_code: 200,
+							stream_count,
+							reason_phrase: "OK".into(),
+						})
+						.ok();
+				}
 			}
 
 			subscribes.lock().remove(&request_id);
@@ -151,7 +166,11 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		request_id: RequestId,
 		mut cancel: oneshot::Receiver<()>,
 		version: Version,
-	) -> Result<(), Error> {
+	) -> Result<u64, Error> {
+		// The number of group streams opened, reported in PUBLISH_DONE.
+		// Declared first because the group futures borrow it.
+		let streams = AtomicU64::new(0);
+
 		// TODO use a BTreeMap serve the latest N groups by sequence.
 		// Until then, we'll implement N=2 manually.
 		// Also, this is more complicated because we can't use tokio because of WASM.
@@ -168,7 +187,7 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		loop {
 			let group = tokio::select! {
 				biased;
-				_ = &mut cancel => return Ok(()),
+				_ = &mut cancel => return Ok(streams.load(Ordering::Relaxed)),
 				Some(group) = track.next_group().transpose() => group,
 				Some(_) = async { Some(old_group.as_mut()?.await) } => {
 					old_group = None;
@@ -182,7 +201,7 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 					old_sequence = None;
 					continue;
 				},
-				else => return Ok(()),
+				else => return Ok(streams.load(Ordering::Relaxed)),
 			}?;
 
 			let sequence = group.info.sequence;
@@ -205,6 +224,8 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 				flags: Default::default(),
 			};
 
+			let deadline = track.deadline(&group);
+
 			// Spawn a task to serve this group, ignoring any errors because they don't really matter.
 			// TODO add some logging at least.
 			let handle = Box::pin(Self::run_group(
@@ -212,7 +233,9 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 				msg,
 				track.info.priority,
 				group,
+				deadline,
 				version,
+				&streams,
 			));
 
 			// Terminate the old group if it's still running.
@@ -238,22 +261,61 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 
 	async fn run_group(
 		session: S,
-		msg: ietf::GroupHeader,
+		mut msg: ietf::GroupHeader,
 		priority: u8,
 		mut group: GroupConsumer,
+		deadline: Option<Instant>,
 		version: Version,
+		streams: &AtomicU64,
 	) -> Result<(), Error> {
 		// TODO add a way to open in priority order.
 		let mut stream = session
 			.open_uni()
 			.await
 			.map_err(|err| Error::Transport(Arc::new(err)))?;
+
+		// Only count streams that were actually opened, otherwise the peer would wait for them.
+		streams.fetch_add(1, Ordering::Relaxed);
 		stream.set_priority(priority);
 
 		let mut stream = Writer::new(stream, version);
 
+		// Reset the stream if the group isn't delivered before the DELIVERY_TIMEOUT.
+		let res = tokio::select! {
+			res = Self::run_frames(&mut stream, &mut msg, &mut group) => res,
+			Some(()) = async { tokio::time::sleep_until(deadline?).await; Some(()) } => Err(Error::Timeout),
+		};
+
+		if let Err(err) = res {
+			tracing::debug!(sequence = %msg.group_id, %err, "group error");
+			stream.abort(&err);
+			return Err(err);
+		}
+
+		tracing::debug!(sequence = %msg.group_id, "finished group");
+
+		Ok(())
+	}
+
+	async fn run_frames(
+		stream: &mut Writer<S::SendStream, Version>,
+		msg: &mut ietf::GroupHeader,
+		group: &mut GroupConsumer,
+	) -> Result<(), Error> {
+		// Peek at the first frame, so every object doesn't pay for extensions when there are none.
+		// The clone is dropped right away so it doesn't hold on to frames for the budget.
+		let first = {
+			let mut peek = group.clone();
+			tokio::select! {
+				biased;
+				_ = stream.closed() => return Err(Error::Cancel),
+				frame = peek.next_frame() => frame?,
+			}
+		};
+		msg.flags.has_extensions = first.is_some_and(|frame| !frame.info.extensions.is_empty());
+
 		// Encode the GroupHeader
-		stream.encode(&msg).await?;
+		stream.encode(&*msg).await?;
 
 		tracing::trace!(?msg, "sending group header");
 
@@ -272,18 +334,38 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 			// object id delta is always 0.
 			stream.encode(&0u64).await?;
 
-			// not using extensions.
+			// The flag is per group, so later frames can't add extensions if the first frame had none.
 			if msg.flags.has_extensions {
-				stream.encode(&0u64).await?;
+				stream.encode(&frame.info.extensions).await?;
+			} else if !frame.info.extensions.is_empty() {
+				tracing::debug!(sequence = %msg.group_id, "dropping extensions, the group header doesn't include them");
 			}
 
+			// IETF requires an upfront size, so we have to buffer streaming frames.
+			let mut buffered = Vec::new();
+			let size = match frame.info.size {
+				Some(size) => size,
+				None => {
+					buffered = tokio::select! {
+						biased;
+						_ = stream.closed() => return Err(Error::Cancel),
+						chunks = frame.read_chunks() => chunks?,
+					};
+					buffered.iter().map(|chunk| chunk.len() as u64).sum()
+				}
+			};
+
 			// Write the size of the frame.
-			stream.encode(&frame.info.size).await?;
+			stream.encode(&size).await?;
 
-			if frame.info.size == 0 {
+			if size == 0 {
 				// Have to write the object status too.
 				stream.encode(&0u8).await?;
 			} else {
+				for mut chunk in buffered {
+					stream.write_all(&mut chunk).await?;
+				}
+
 				// Stream each chunk of the frame.
 				loop {
 					let chunk = tokio::select! {
@@ -303,16 +385,12 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		stream.finish()?;
 
 		// Wait until everything is acknowledged by the peer so we can still cancel the stream.
-		stream.closed().await?;
-
-		tracing::debug!(sequence = %msg.group_id, "finished group");
-
-		Ok(())
+		stream.closed().await
 	}
 
 	pub fn recv_unsubscribe(&mut self, msg: ietf::Unsubscribe) -> Result<(), Error> {
 		let mut subscribes = self.subscribes.lock();
-		if let Some(tx) = subscribes.remove(&msg.request_id) {
+		if let Some((tx, _)) = subscribes.remove(&msg.request_id) {
 			let _ = tx.send(());
 		}
 		Ok(())
@@ -380,20 +458,25 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		};
 
 		let subscribes = self.subscribes.lock();
-		if !subscribes.contains_key(&subscribe_id) {
+		let Some((_, track)) = subscribes.get(&subscribe_id) else {
 			return self.control.send(ietf::FetchError {
 				request_id: msg.request_id,
 				error_code: 404,
 				reason_phrase: "Subscribe not found".into(),
 			});
-		}
+		};
+
+		let final_group = track.final_group();
 
 		self.control.send(ietf::FetchOk {
 			request_id: msg.request_id,
 			group_order: GroupOrder::Descending,
-			end_of_track: false,
-			// TODO get the proper group_id
-			end_location: Location { group: 0, object: 0 },
+			end_of_track: final_group.is_some(),
+			// TODO get the proper group_id while the track is still active
+			end_location: Location {
+				group: final_group.unwrap_or(0),
+				object: 0,
+			},
 		})?;
 
 		let session = self.session.clone();
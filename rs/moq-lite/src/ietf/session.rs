@@ -1,11 +1,18 @@
+use std::collections::HashSet;
+
+use tokio::sync::watch;
+use web_async::Lock;
+
 use crate::{
-	Error, OriginConsumer, OriginProducer,
+	Error, OriginConsumer, OriginProducer, PathOwned,
 	coding::{Reader, Stream},
 	ietf::{self, Control, Message, RequestId, Version},
+	limits::Budgets,
 };
 
 use super::{Publisher, Subscriber};
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn start<S: web_transport_trait::Session>(
 	session: S,
 	setup: Stream<S, Version>,
@@ -14,6 +21,10 @@ pub(crate) async fn start<S: web_transport_trait::Session>(
 	publish: Option<OriginConsumer>,
 	subscribe: Option<OriginProducer>,
 	version: Version,
+	goaway: watch::Receiver<Option<String>>,
+	going_away: watch::Sender<Option<String>>,
+	budgets: Budgets,
+	announced: Lock<HashSet<PathOwned>>,
 ) -> Result<(), Error> {
 	web_async::spawn(async move {
 		match run(
@@ -24,6 +35,10 @@ pub(crate) async fn start<S: web_transport_trait::Session>(
 			publish,
 			subscribe,
 			version,
+			goaway,
+			going_away,
+			budgets,
+			announced,
 		)
 		.await
 		{
@@ -45,6 +60,7 @@ pub(crate) async fn start<S: web_transport_trait::Session>(
 	Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run<S: web_transport_trait::Session>(
 	session: S,
 	setup: Stream<S, Version>,
@@ -53,25 +69,47 @@ async fn run<S: web_transport_trait::Session>(
 	publish: Option<OriginConsumer>,
 	subscribe: Option<OriginProducer>,
 	version: Version,
+	goaway: watch::Receiver<Option<String>>,
+	going_away: watch::Sender<Option<String>>,
+	budgets: Budgets,
+	announced: Lock<HashSet<PathOwned>>,
 ) -> Result<(), Error> {
 	let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
 	let control = Control::new(tx, request_id_max, client, version);
 	let publisher = Publisher::new(session.clone(), publish, control.clone(), version);
-	let subscriber = Subscriber::new(session.clone(), subscribe, control.clone(), version);
+	let subscriber = Subscriber::new(session.clone(), subscribe, control.clone(), version, budgets, announced);
 
 	tokio::select! {
 		res = subscriber.clone().run() => res,
 		res = publisher.clone().run() => res,
-		res = run_control_read(setup.reader, control, publisher, subscriber) => res,
+		res = run_control_read(setup.reader, control.clone(), publisher, subscriber, going_away) => res,
 		res = Control::run::<S>(setup.writer, rx) => res,
+		res = run_goaway(control, goaway) => res,
 	}
 }
 
+// Send a GOAWAY when requested, leaving the session open for the peer to migrate.
+async fn run_goaway(control: Control, mut goaway: watch::Receiver<Option<String>>) -> Result<(), Error> {
+	let uri = goaway.wait_for(Option::is_some).await.map(|uri| uri.clone());
+	let Ok(Some(uri)) = uri else {
+		// The Session was dropped, so nobody can ask us to go away.
+		return std::future::pending().await;
+	};
+
+	control.send(ietf::GoAway {
+		new_session_uri: uri.into(),
+	})?;
+
+	// It's up to the peer (or a timeout) to close the session.
+	std::future::pending().await
+}
+
 async fn run_control_read<S: web_transport_trait::Session>(
 	mut reader: Reader<S::RecvStream, Version>,
 	control: Control,
 	mut publisher: Publisher<S>,
 	mut subscriber: Subscriber<S>,
+	going_away: watch::Sender<Option<String>>,
 ) -> Result<(), Error> {
 	loop {
 		let id: u64 = match reader.decode_maybe().await? {
@@ -149,7 +187,8 @@ async fn run_control_read<S: web_transport_trait::Session>(
 			ietf::GoAway::ID => {
 				let msg = ietf::GoAway::decode_msg(&mut data, ietf::Version::Draft14)?;
 				tracing::debug!(message = ?msg, "received control message");
-				return Err(Error::Unsupported);
+				tracing::info!(uri = %msg.new_session_uri, "received GOAWAY");
+				going_away.send_replace(Some(msg.new_session_uri.into_owned()));
 			}
 			ietf::SubscribeNamespace::ID => {
 				let msg = ietf::SubscribeNamespace::decode_msg(&mut data, ietf::Version::Draft14)?;
@@ -1,13 +1,13 @@
 //! IETF moq-transport-14 subscribe messages
 
-use std::borrow::Cow;
+use std::{borrow::Cow, time::Duration};
 
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
 use crate::{
 	Path,
 	coding::*,
-	ietf::{GroupOrder, Location, Message, Parameters, RequestId, Version},
+	ietf::{GroupOrder, Location, Message, ParameterVarInt, Parameters, RequestId, Version},
 };
 
 use super::namespace::{decode_namespace, encode_namespace};
@@ -43,6 +43,7 @@ pub struct Subscribe<'a> {
 	pub subscriber_priority: u8,
 	pub group_order: GroupOrder,
 	pub filter_type: FilterType,
+	pub delivery_timeout: Option<Duration>,
 }
 
 impl Message for Subscribe<'_> {
@@ -76,8 +77,10 @@ impl Message for Subscribe<'_> {
 			FilterType::NextGroup | FilterType::LargestObject => {}
 		};
 
-		// Ignore parameters, who cares.
-		let _params = Parameters::decode(r, version)?;
+		let params = Parameters::decode(r, version)?;
+		let delivery_timeout = params
+			.get_varint(ParameterVarInt::DELIVERY_TIMEOUT)
+			.map(Duration::from_millis);
 
 		Ok(Self {
 			request_id,
@@ -86,6 +89,7 @@ impl Message for Subscribe<'_> {
 			subscriber_priority,
 			group_order,
 			filter_type,
+			delivery_timeout,
 		})
 	}
 
@@ -103,7 +107,12 @@ impl Message for Subscribe<'_> {
 		);
 
 		self.filter_type.encode(w, version);
-		0u8.encode(w, version); // no parameters
+
+		let mut params = Parameters::default();
+		if let Some(timeout) = self.delivery_timeout {
+			params.set_varint(ParameterVarInt::DELIVERY_TIMEOUT, timeout.as_millis() as u64);
+		}
+		params.encode(w, version);
 	}
 }
 
@@ -283,6 +292,7 @@ mod tests {
 			subscriber_priority: 128,
 			group_order: GroupOrder::Descending,
 			filter_type: FilterType::LargestObject,
+			delivery_timeout: None,
 		};
 
 		let encoded = encode_message(&msg);
@@ -294,6 +304,24 @@ mod tests {
 		assert_eq!(decoded.subscriber_priority, 128);
 	}
 
+	#[test]
+	fn test_subscribe_delivery_timeout() {
+		let msg = Subscribe {
+			request_id: RequestId(1),
+			track_namespace: Path::new("test"),
+			track_name: "video".into(),
+			subscriber_priority: 128,
+			group_order: GroupOrder::Descending,
+			filter_type: FilterType::LargestObject,
+			delivery_timeout: Some(Duration::from_millis(1500)),
+		};
+
+		let encoded = encode_message(&msg);
+		let decoded: Subscribe = decode_message(&encoded).unwrap();
+
+		assert_eq!(decoded.delivery_timeout, Some(Duration::from_millis(1500)));
+	}
+
 	#[test]
 	fn test_subscribe_nested_namespace() {
 		let msg = Subscribe {
@@ -303,6 +331,7 @@ mod tests {
 			subscriber_priority: 255,
 			group_order: GroupOrder::Descending,
 			filter_type: FilterType::LargestObject,
+			delivery_timeout: None,
 		};
 
 		let encoded = encode_message(&msg);
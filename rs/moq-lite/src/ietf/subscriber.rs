@@ -1,14 +1,16 @@
 use std::{
-	collections::{HashMap, hash_map::Entry},
+	collections::{HashMap, HashSet, hash_map::Entry},
 	sync::Arc,
 };
 
 use crate::{
-	Broadcast, Error, Frame, FrameProducer, Group, GroupProducer, OriginProducer, Path, PathOwned, Track,
-	TrackProducer,
+	Broadcast, Budget, Error, Extensions, Frame, FrameProducer, Group, GroupProducer, Metadata, OriginProducer, Path,
+	PathOwned, Track, TrackProducer,
 	coding::Reader,
 	ietf::{self, Control, FetchHeader, FilterType, GroupFlags, GroupOrder, RequestId, Version},
+	limits::Budgets,
 	model::BroadcastProducer,
+	session::FINISH_TIMEOUT,
 };
 
 use web_async::Lock;
@@ -31,6 +33,27 @@ struct State {
 struct TrackState {
 	producer: TrackProducer,
 	alias: Option<u64>,
+
+	// A child of the session budget, limiting the bytes buffered for this track.
+	budget: Budget,
+
+	// The number of group streams received.
+	streams: u64,
+
+	// Set to the number of streams reported by PUBLISH_DONE, closing the track once they have all arrived.
+	done: Option<u64>,
+}
+
+impl TrackState {
+	fn new(producer: TrackProducer, alias: Option<u64>, budget: Budget) -> Self {
+		Self {
+			producer,
+			alias,
+			budget,
+			streams: 0,
+			done: None,
+		}
+	}
 }
 
 struct BroadcastState {
@@ -49,23 +72,36 @@ pub(super) struct Subscriber<S: web_transport_trait::Session> {
 	control: Control,
 
 	version: Version,
+	budgets: Budgets,
+
+	// The paths of the broadcasts in State, shared with the Session.
+	announced: Lock<HashSet<PathOwned>>,
 }
 
 impl<S: web_transport_trait::Session> Subscriber<S> {
-	pub fn new(session: S, origin: Option<OriginProducer>, control: Control, version: Version) -> Self {
+	pub fn new(
+		session: S,
+		origin: Option<OriginProducer>,
+		control: Control,
+		version: Version,
+		budgets: Budgets,
+		announced: Lock<HashSet<PathOwned>>,
+	) -> Self {
 		Self {
 			session,
 			origin,
 			state: Default::default(),
 			control,
 			version,
+			budgets,
+			announced,
 		}
 	}
 
 	pub fn recv_publish_namespace(&mut self, msg: ietf::PublishNamespace) -> Result<(), Error> {
 		let request_id = msg.request_id;
 
-		match self.start_announce(msg.track_namespace.to_owned()) {
+		match self.start_announce(msg.track_namespace.to_owned(), msg.metadata) {
 			Ok(_) => self.control.send(ietf::PublishNamespaceOk { request_id }),
 			Err(err) => self.control.send(ietf::PublishNamespaceError {
 				request_id,
@@ -75,7 +111,7 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 		}
 	}
 
-	fn start_announce(&mut self, path: PathOwned) -> Result<BroadcastProducer, Error> {
+	fn start_announce(&mut self, path: PathOwned, metadata: Metadata) -> Result<BroadcastProducer, Error> {
 		let Some(origin) = &self.origin else {
 			return Err(Error::InvalidRole);
 		};
@@ -87,12 +123,13 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 				return Ok(entry.get().producer.clone());
 			}
 			Entry::Vacant(entry) => {
-				let broadcast = Broadcast::produce();
+				let broadcast = Broadcast::produce_with_metadata(metadata);
 				origin.publish_broadcast(path.clone(), broadcast.consumer);
 				entry.insert(BroadcastState {
 					producer: broadcast.producer.clone(),
 					count: 1,
 				});
+				self.announced.lock().insert(path.clone());
 				broadcast.producer
 			}
 		};
@@ -107,6 +144,7 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 				tracing::debug!(%err, "error running broadcast");
 			}
 			this.state.lock().broadcasts.remove(&path);
+			this.announced.lock().remove(&path);
 		});
 
 		Ok(broadcast)
@@ -125,7 +163,8 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 				entry.get_mut().count -= 1;
 				if entry.get().count == 0 {
 					tracing::debug!(broadcast = %origin.absolute(&path), "unannounced");
-					entry.remove();
+					self.announced.lock().remove(&path);
+					entry.remove().producer.close();
 				}
 			}
 			Entry::Vacant(_) => return Err(Error::NotFound),
@@ -165,10 +204,23 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 	pub fn recv_publish_done(&mut self, msg: ietf::PublishDone<'_>) -> Result<(), Error> {
 		let mut state = self.state.lock();
 
-		if let Some(track) = state.subscribes.remove(&msg.request_id) {
-			track.producer.close();
-			if let Some(alias) = track.alias {
-				state.aliases.remove(&alias);
+		if let Some(track) = state.subscribes.get_mut(&msg.request_id) {
+			// Some group streams may still be in flight, so wait for them before closing the track.
+			track.done = Some(msg.stream_count);
+			if track.streams >= msg.stream_count {
+				Self::finish_track(&mut state, msg.request_id);
+			} else {
+				// Don't wait forever in case a stream was reset or never opened.
+				let state = self.state.clone();
+				let request_id = msg.request_id;
+				web_async::spawn(async move {
+					tokio::time::sleep(FINISH_TIMEOUT).await;
+					let mut state = state.lock();
+					if state.subscribes.contains_key(&request_id) {
+						tracing::warn!(%request_id, "timed out waiting for the final group streams");
+						Self::finish_track(&mut state, request_id);
+					}
+				});
 			}
 		}
 
@@ -180,6 +232,16 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 		Ok(())
 	}
 
+	// Close the track after PUBLISH_DONE, once all of its group streams have arrived.
+	fn finish_track(state: &mut State, request_id: RequestId) {
+		if let Some(track) = state.subscribes.remove(&request_id) {
+			track.producer.close();
+			if let Some(alias) = track.alias {
+				state.aliases.remove(&alias);
+			}
+		}
+	}
+
 	pub async fn run(self) -> Result<(), Error> {
 		loop {
 			let stream = self
@@ -233,13 +295,9 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 			let mut this = self.clone();
 
 			let mut state = self.state.lock();
-			state.subscribes.insert(
-				request_id,
-				TrackState {
-					producer: track.clone(),
-					alias: None,
-				},
-			);
+			state
+				.subscribes
+				.insert(request_id, TrackState::new(track.clone(), None, self.budgets.track()));
 
 			let path = path.to_owned();
 			web_async::spawn(async move {
@@ -267,6 +325,7 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 			group_order: GroupOrder::Descending,
 			// we want largest group
 			filter_type: FilterType::LargestObject,
+			delivery_timeout: track.timeout(),
 		})?;
 
 		// TODO we should send a joining fetch, but it's annoying to implement.
@@ -300,11 +359,23 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 				}
 			};
 			let track = state.subscribes.get_mut(&request_id).ok_or(Error::NotFound)?;
+			track.streams += 1;
 
 			let group = Group {
 				sequence: group.group_id,
 			};
-			track.producer.create_group(group).ok_or(Error::Old)?
+			let group = track.producer.create_group(group);
+			let group = group.map(|group| match self.budgets.group(&track.budget) {
+				Some(budget) => group.with_budget(budget),
+				None => group,
+			});
+
+			// This was the last stream reported by PUBLISH_DONE.
+			if track.done.is_some_and(|done| track.streams >= done) {
+				Self::finish_track(&mut state, request_id);
+			}
+
+			group.ok_or(Error::Old)?
 		};
 
 		let res = tokio::select! {
@@ -341,9 +412,9 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 				tracing::warn!(id_delta = %id_delta, "object ID gaps not supported, ignoring");
 			}
 
+			let mut extensions = Extensions::default();
 			if group.flags.has_extensions {
-				let size: usize = stream.decode().await?;
-				stream.skip(size).await?;
+				extensions = stream.decode().await?;
 			}
 
 			let size: u64 = stream.decode().await?;
@@ -352,7 +423,10 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 				let status: u64 = stream.decode().await?;
 				if status == 0 {
 					// Empty frame
-					let frame = producer.create_frame(Frame { size: 0 });
+					let frame = producer.create_frame(Frame {
+						size: Some(0),
+						extensions,
+					});
 					frame.close();
 				} else if status == 3 && !group.flags.has_end {
 					// End of group
@@ -361,11 +435,14 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 					return Err(Error::Unsupported);
 				}
 			} else {
-				let frame = producer.create_frame(Frame { size });
+				let frame = producer.create_frame(Frame {
+					size: Some(size),
+					extensions,
+				});
 
 				let res = tokio::select! {
 					_ = frame.unused() => Err(Error::Cancel),
-					res = self.run_frame(stream, frame.clone()) => res,
+					res = self.run_frame(stream, frame.clone(), size) => res,
 				};
 
 				if let Err(err) = res {
@@ -384,18 +461,19 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 		&mut self,
 		stream: &mut Reader<S::RecvStream, Version>,
 		mut frame: FrameProducer,
+		size: u64,
 	) -> Result<(), Error> {
-		let mut remain = frame.info.size;
+		let mut remain = size;
 
-		tracing::trace!(size = %frame.info.size, "reading frame");
+		tracing::trace!(%size, "reading frame");
 
 		while remain > 0 {
 			let chunk = stream.read(remain as usize).await?.ok_or(Error::WrongSize)?;
 			remain = remain.checked_sub(chunk.len() as u64).ok_or(Error::WrongSize)?;
-			frame.write_chunk(chunk);
+			frame.try_write_chunk(chunk)?;
 		}
 
-		tracing::trace!(size = %frame.info.size, "read frame");
+		tracing::trace!(%size, "read frame");
 
 		frame.close();
 
@@ -450,10 +528,11 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 		let mut state = self.state.lock();
 		match state.subscribes.entry(request_id) {
 			Entry::Vacant(entry) => {
-				entry.insert(TrackState {
-					producer: track.producer,
-					alias: Some(msg.track_alias),
-				});
+				entry.insert(TrackState::new(
+					track.producer,
+					Some(msg.track_alias),
+					self.budgets.track(),
+				));
 			}
 			Entry::Occupied(_) => return Err(Error::Duplicate),
 		};
@@ -464,7 +543,7 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 
 		// Announce our namespace if we haven't already.
 		// NOTE: This is debated in the IETF draft, but is significantly easier to implement.
-		let mut broadcast = self.start_announce(msg.track_namespace.to_owned())?;
+		let mut broadcast = self.start_announce(msg.track_namespace.to_owned(), Metadata::default())?;
 
 		let exists = broadcast.insert_track(track.consumer);
 		if exists {
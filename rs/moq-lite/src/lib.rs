@@ -33,18 +33,26 @@
 //!
 //! - Use [FrameProducer] and [FrameConsumer] for chunked frame writes/reads without allocating entire frames (useful for relaying).
 //! - Use [TrackProducer::create_group] instead of [TrackProducer::append_group] to produce groups out-of-order.
+//! - Enable the `loopback` feature for an in-process transport with simulated network conditions, useful for tests.
 
 mod error;
+mod limits;
 mod model;
 mod path;
 mod session;
 mod setup;
+mod stats;
 
 pub mod coding;
 pub mod ietf;
 pub mod lite;
 
+#[cfg(any(test, feature = "loopback"))]
+pub mod loopback;
+
 pub use error::*;
+pub use limits::*;
 pub use model::*;
 pub use path::*;
 pub use session::*;
+pub use stats::*;
@@ -0,0 +1,70 @@
+use std::sync::{
+	Arc,
+	atomic::{AtomicU64, Ordering},
+};
+
+use crate::Budget;
+
+/// Limits on the number of bytes buffered for remote subscriptions, see [crate::Session::with_limits].
+///
+/// When a frame would exceed any of them, frames that every active consumer has read are evicted to make room.
+/// Otherwise the group is aborted with [crate::Error::TooLarge], so slow consumers count against the limits.
+/// A consumer that hasn't read an evicted frame yet also gets [crate::Error::TooLarge] rather than skipping it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+	/// The maximum number of bytes buffered across all subscriptions, or [None] for unlimited.
+	pub session: Option<u64>,
+
+	/// The maximum number of bytes buffered for each subscription, or [None] for unlimited.
+	pub track: Option<u64>,
+
+	/// The maximum number of bytes buffered for each group, or [None] for unlimited.
+	pub group: Option<u64>,
+}
+
+// Shared between the Session and the subscriber so the limits can be changed after the handshake.
+// The track and group limits are shared by every budget, so changes apply to existing subscriptions too.
+#[derive(Clone)]
+pub(crate) struct Budgets {
+	session: Budget,
+	track: Arc<AtomicU64>,
+	group: Arc<AtomicU64>,
+}
+
+impl Default for Budgets {
+	fn default() -> Self {
+		Self {
+			session: Budget::unlimited(),
+			track: Arc::new(AtomicU64::new(u64::MAX)),
+			group: Arc::new(AtomicU64::new(u64::MAX)),
+		}
+	}
+}
+
+impl Budgets {
+	pub fn set(&self, limits: Limits) {
+		self.session.set_limit(limits.session.unwrap_or(u64::MAX));
+		self.track.store(limits.track.unwrap_or(u64::MAX), Ordering::Relaxed);
+		self.group.store(limits.group.unwrap_or(u64::MAX), Ordering::Relaxed);
+	}
+
+	// The number of bytes currently buffered for the session.
+	pub fn used(&self) -> u64 {
+		self.session.used()
+	}
+
+	// Create a budget for a new subscription.
+	pub fn track(&self) -> Budget {
+		self.session.child_shared(self.track.clone())
+	}
+
+	// Create a budget for a new group within the subscription, or None if there are no limits.
+	// Unlimited groups skip the bookkeeping entirely, so nothing is ever evicted.
+	pub fn group(&self, track: &Budget) -> Option<Budget> {
+		let limited = self.session.limit() != u64::MAX
+			|| self.track.load(Ordering::Relaxed) != u64::MAX
+			|| self.group.load(Ordering::Relaxed) != u64::MAX;
+
+		limited.then(|| track.child_shared(self.group.clone()))
+	}
+}
@@ -1,7 +1,7 @@
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
 use crate::{
-	Path,
+	Metadata, Path,
 	coding::*,
 	lite::{Message, Version},
 };
@@ -14,6 +14,8 @@ pub enum Announce<'a> {
 	Active {
 		#[cfg_attr(feature = "serde", serde(borrow))]
 		suffix: Path<'a>,
+		/// Only encoded for Draft03 and later.
+		metadata: Metadata,
 	},
 	Ended {
 		#[cfg_attr(feature = "serde", serde(borrow))]
@@ -26,6 +28,7 @@ impl Message for Announce<'_> {
 		Ok(match AnnounceStatus::decode(r, version)? {
 			AnnounceStatus::Active => Self::Active {
 				suffix: Path::decode(r, version)?,
+				metadata: decode_metadata(r, version)?,
 			},
 			AnnounceStatus::Ended => Self::Ended {
 				suffix: Path::decode(r, version)?,
@@ -35,9 +38,10 @@ impl Message for Announce<'_> {
 
 	fn encode_msg<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
 		match self {
-			Self::Active { suffix } => {
+			Self::Active { suffix, metadata } => {
 				AnnounceStatus::Active.encode(w, version);
 				suffix.encode(w, version);
+				encode_metadata(w, metadata, version);
 			}
 			Self::Ended { suffix } => {
 				AnnounceStatus::Ended.encode(w, version);
@@ -68,7 +72,7 @@ impl Message for AnnouncePlease<'_> {
 /// Send by the publisher, used to determine the message that follows.
 #[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive)]
 #[repr(u8)]
-enum AnnounceStatus {
+pub(super) enum AnnounceStatus {
 	Ended = 0,
 	Active = 1,
 }
@@ -91,8 +95,10 @@ impl<V> Encode<V> for AnnounceStatus {
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnnounceInit<'a> {
 	/// List of currently active broadcasts, encoded as suffixes to be combined with the prefix.
+	///
+	/// The metadata is only encoded for Draft03 and later.
 	#[cfg_attr(feature = "serde", serde(borrow))]
-	pub suffixes: Vec<Path<'a>>,
+	pub suffixes: Vec<(Path<'a>, Metadata)>,
 }
 
 impl Message for AnnounceInit<'_> {
@@ -103,7 +109,9 @@ impl Message for AnnounceInit<'_> {
 		let mut paths = Vec::with_capacity(count.min(1024) as usize);
 
 		for _ in 0..count {
-			paths.push(Path::decode(r, version)?);
+			let path = Path::decode(r, version)?;
+			let metadata = decode_metadata(r, version)?;
+			paths.push((path, metadata));
 		}
 
 		Ok(Self { suffixes: paths })
@@ -111,8 +119,107 @@ impl Message for AnnounceInit<'_> {
 
 	fn encode_msg<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
 		(self.suffixes.len() as u64).encode(w, version);
-		for path in &self.suffixes {
+		for (path, metadata) in &self.suffixes {
 			path.encode(w, version);
+			encode_metadata(w, metadata, version);
 		}
 	}
 }
+
+// Metadata was added in Draft03; older versions silently drop it.
+fn encode_metadata<W: bytes::BufMut>(w: &mut W, metadata: &Metadata, version: Version) {
+	match version {
+		Version::Draft01 | Version::Draft02 => {}
+		_ => metadata.encode(w, version),
+	}
+}
+
+fn decode_metadata<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Metadata, DecodeError> {
+	match version {
+		Version::Draft01 | Version::Draft02 => Ok(Metadata::default()),
+		_ => Metadata::decode(r, version),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bytes::BytesMut;
+
+	fn encode_message<M: Message>(msg: &M, version: Version) -> Vec<u8> {
+		let mut buf = BytesMut::new();
+		msg.encode(&mut buf, version);
+		buf.to_vec()
+	}
+
+	fn decode_message<M: Message>(bytes: &[u8], version: Version) -> Result<M, DecodeError> {
+		let mut buf = bytes::Bytes::from(bytes.to_vec());
+		M::decode(&mut buf, version)
+	}
+
+	#[test]
+	fn test_announce_metadata() {
+		let metadata = Metadata::new()
+			.with("title", "demo")
+			.unwrap()
+			.with("type", "video")
+			.unwrap();
+
+		for msg in [
+			Announce::Active {
+				suffix: Path::new("alice"),
+				metadata: metadata.clone(),
+			},
+			Announce::Active {
+				suffix: Path::new("bob"),
+				metadata: Metadata::default(),
+			},
+			Announce::Ended {
+				suffix: Path::new("alice"),
+			},
+		] {
+			let encoded = encode_message(&msg, Version::Draft03);
+			let decoded: Announce = decode_message(&encoded, Version::Draft03).unwrap();
+			assert_eq!(decoded, msg);
+		}
+	}
+
+	#[test]
+	fn test_announce_init_metadata() {
+		let msg = AnnounceInit {
+			suffixes: vec![
+				(Path::new("alice"), Metadata::new().with("title", "demo").unwrap()),
+				(Path::new("bob"), Metadata::default()),
+			],
+		};
+
+		let encoded = encode_message(&msg, Version::Draft03);
+		let decoded: AnnounceInit = decode_message(&encoded, Version::Draft03).unwrap();
+		assert_eq!(decoded, msg);
+	}
+
+	#[test]
+	fn test_announce_metadata_dropped() {
+		let msg = Announce::Active {
+			suffix: Path::new("alice"),
+			metadata: Metadata::new().with("title", "demo").unwrap(),
+		};
+
+		// Draft02 doesn't have metadata, so it's dropped and the encoding is unchanged.
+		let encoded = encode_message(&msg, Version::Draft02);
+		let decoded: Announce = decode_message(&encoded, Version::Draft02).unwrap();
+		assert_eq!(
+			decoded,
+			Announce::Active {
+				suffix: Path::new("alice"),
+				metadata: Metadata::default(),
+			}
+		);
+
+		let empty = Announce::Active {
+			suffix: Path::new("alice"),
+			metadata: Metadata::default(),
+		};
+		assert_eq!(encoded, encode_message(&empty, Version::Draft02));
+	}
+}
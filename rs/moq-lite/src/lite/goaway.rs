@@ -0,0 +1,66 @@
+use std::borrow::Cow;
+
+use crate::{
+	coding::*,
+	lite::{Message, Version},
+};
+
+/// Sent on a dedicated control stream to ask the peer to migrate to a new session.
+///
+/// An empty URI means the peer should reconnect to the same URL.
+#[derive(Clone, Debug)]
+pub struct GoAway<'a> {
+	pub new_session_uri: Cow<'a, str>,
+}
+
+impl Message for GoAway<'_> {
+	fn decode_msg<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let new_session_uri = Cow::<str>::decode(r, version)?;
+		Ok(Self { new_session_uri })
+	}
+
+	fn encode_msg<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		self.new_session_uri.encode(w, version);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bytes::BytesMut;
+
+	fn encode_message<M: Message>(msg: &M) -> Vec<u8> {
+		let mut buf = BytesMut::new();
+		msg.encode(&mut buf, Version::Draft03);
+		buf.to_vec()
+	}
+
+	fn decode_message<M: Message>(bytes: &[u8]) -> Result<M, DecodeError> {
+		let mut buf = bytes::Bytes::from(bytes.to_vec());
+		M::decode(&mut buf, Version::Draft03)
+	}
+
+	#[test]
+	fn test_goaway_with_url() {
+		let msg = GoAway {
+			new_session_uri: "https://example.com/new".into(),
+		};
+
+		let encoded = encode_message(&msg);
+		let decoded: GoAway = decode_message(&encoded).unwrap();
+
+		assert_eq!(decoded.new_session_uri, "https://example.com/new");
+	}
+
+	#[test]
+	fn test_goaway_empty() {
+		let msg = GoAway {
+			new_session_uri: "".into(),
+		};
+
+		let encoded = encode_message(&msg);
+		let decoded: GoAway = decode_message(&encoded).unwrap();
+
+		assert_eq!(decoded.new_session_uri, "");
+	}
+}
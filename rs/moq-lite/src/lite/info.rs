@@ -3,8 +3,12 @@ use crate::{
 	lite::{Message, Version},
 };
 
+/// Periodically sent on the setup stream to report how fast data is arriving.
 #[derive(Clone, Debug)]
 pub struct SessionInfo {
+	/// The rate at which the sender is receiving data, in bits per second, or [None] if nothing was received.
+	///
+	/// This is the observed throughput, not the available bandwidth.
 	pub bitrate: Option<u64>,
 }
 
@@ -5,6 +5,7 @@
 //! Specification: [<https://github.com/moq-dev/drafts>]
 
 mod announce;
+mod goaway;
 mod group;
 mod info;
 mod message;
@@ -16,9 +17,11 @@ mod setup;
 mod stream;
 mod subscribe;
 mod subscriber;
+mod tracks;
 mod version;
 
 pub use announce::*;
+pub use goaway::*;
 pub use group::*;
 pub use info::*;
 pub use message::*;
@@ -29,4 +32,5 @@ pub use setup::*;
 pub use stream::*;
 pub use subscribe::*;
 use subscriber::*;
+pub use tracks::*;
 pub use version::*;
@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use tokio::{sync::watch, time::Instant};
 use web_async::FuturesExt;
 
 use crate::{
@@ -17,10 +18,16 @@ pub(super) struct Publisher<S: web_transport_trait::Session> {
 	origin: OriginConsumer,
 	priority: PriorityQueue,
 	version: Version,
+	going_away: watch::Sender<Option<String>>,
 }
 
 impl<S: web_transport_trait::Session> Publisher<S> {
-	pub fn new(session: S, origin: Option<OriginConsumer>, version: Version) -> Self {
+	pub fn new(
+		session: S,
+		origin: Option<OriginConsumer>,
+		version: Version,
+		going_away: watch::Sender<Option<String>>,
+	) -> Self {
 		// Default to a dummy origin that is immediately closed.
 		let origin = origin.unwrap_or_else(|| Origin::produce().consumer);
 		Self {
@@ -28,6 +35,7 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 			origin,
 			priority: Default::default(),
 			version,
+			going_away,
 		}
 	}
 
@@ -42,6 +50,8 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 			if let Err(err) = match kind {
 				lite::ControlType::Announce => self.recv_announce(stream).await,
 				lite::ControlType::Subscribe => self.recv_subscribe(stream).await,
+				lite::ControlType::GoAway => self.recv_goaway(stream).await,
+				lite::ControlType::Tracks => self.recv_tracks(stream).await,
 				_ => Err(Error::UnexpectedStream),
 			} {
 				tracing::warn!(%err, "control stream error");
@@ -97,13 +107,13 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		while let Some((path, active)) = origin.try_announced() {
 			let suffix = path.strip_prefix(&prefix).expect("origin returned invalid path");
 
-			if active.is_some() {
+			if let Some(active) = active {
 				tracing::debug!(broadcast = %origin.absolute(&path), "announce");
-				init.push(suffix.to_owned());
+				init.push((suffix.to_owned(), active.metadata().clone()));
 			} else {
 				// A potential race.
 				tracing::debug!(broadcast = %origin.absolute(&path), "unannounce");
-				init.retain(|path| path != &suffix);
+				init.retain(|(path, _)| path != &suffix);
 			}
 		}
 
@@ -120,9 +130,9 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 						Some((path, active)) => {
 							let suffix = path.strip_prefix(&prefix).expect("origin returned invalid path").to_owned();
 
-							if active.is_some() {
+							if let Some(active) = active {
 								tracing::debug!(broadcast = %origin.absolute(&path), "announce");
-								let msg = lite::Announce::Active { suffix };
+								let msg = lite::Announce::Active { suffix, metadata: active.metadata().clone() };
 								stream.writer.encode(&msg).await?;
 							} else {
 								tracing::debug!(broadcast = %origin.absolute(&path), "unannounce");
@@ -140,6 +150,83 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		}
 	}
 
+	pub async fn recv_tracks(&mut self, mut stream: Stream<S, Version>) -> Result<(), Error> {
+		let msg = stream.reader.decode::<lite::TracksPlease>().await?;
+		let absolute = self.origin.absolute(&msg.broadcast).to_owned();
+
+		tracing::debug!(broadcast = %absolute, "tracks started");
+
+		let broadcast = self.origin.consume_broadcast(&msg.broadcast);
+
+		web_async::spawn(async move {
+			let res = match broadcast {
+				Some(broadcast) => Self::run_tracks(&mut stream, broadcast).await,
+				None => Err(Error::NotFound),
+			};
+
+			if let Err(err) = res {
+				match &err {
+					Error::Cancel | Error::Transport(_) => {
+						tracing::debug!(broadcast = %absolute, "tracks cancelled");
+					}
+					err => {
+						tracing::warn!(%err, broadcast = %absolute, "tracks error");
+					}
+				}
+
+				stream.writer.abort(&err);
+			} else {
+				tracing::debug!(broadcast = %absolute, "tracks complete");
+			}
+		});
+
+		Ok(())
+	}
+
+	async fn run_tracks(stream: &mut Stream<S, Version>, broadcast: BroadcastConsumer) -> Result<(), Error> {
+		// NOTE: We hold the broadcast so it's not considered unused while discovering tracks.
+		let mut tracks = broadcast.tracks();
+		let mut init = Vec::new();
+
+		// Send TRACKS_INIT as the first message with all currently announced tracks.
+		while let Some((name, active)) = tracks.try_announced() {
+			if active {
+				init.push(name.into());
+			} else {
+				init.retain(|existing| existing != &name);
+			}
+		}
+
+		stream.writer.encode(&lite::TracksInit { names: init }).await?;
+
+		loop {
+			tokio::select! {
+				biased;
+				res = stream.reader.closed() => return res,
+				announced = tracks.announced() => {
+					let msg = match announced {
+						Some((name, true)) => lite::TrackAnnounce::Active { name: name.into() },
+						Some((name, false)) => lite::TrackAnnounce::Ended { name: name.into() },
+						None => {
+							stream.writer.finish()?;
+							return stream.writer.closed().await;
+						}
+					};
+
+					stream.writer.encode(&msg).await?;
+				}
+			}
+		}
+	}
+
+	pub async fn recv_goaway(&mut self, mut stream: Stream<S, Version>) -> Result<(), Error> {
+		let msg = stream.reader.decode::<lite::GoAway>().await?;
+		tracing::info!(uri = %msg.new_session_uri, "received GOAWAY");
+		self.going_away.send_replace(Some(msg.new_session_uri.into_owned()));
+
+		Ok(())
+	}
+
 	pub async fn recv_subscribe(&mut self, mut stream: Stream<S, Version>) -> Result<(), Error> {
 		let subscribe = stream.reader.decode::<lite::Subscribe>().await?;
 
@@ -189,7 +276,7 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		};
 
 		let broadcast = consumer.ok_or(Error::NotFound)?;
-		let track = broadcast.subscribe_track(&track);
+		let track = broadcast.subscribe_track_with_timeout(&track, subscribe.timeout);
 
 		// TODO wait until track.info() to get the *real* priority
 
@@ -199,9 +286,17 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 
 		stream.writer.encode(&info).await?;
 
-		tokio::select! {
-			res = Self::run_track(session, track, subscribe, priority, version) => res?,
-			res = stream.reader.closed() => res?,
+		let finished = tokio::select! {
+			res = Self::run_track(session, track.clone(), subscribe, priority, version) => res.map(|_| true)?,
+			res = stream.reader.closed() => res.map(|_| false)?,
+		};
+
+		// Tell the subscriber the final group, so it knows the track ended rather than the connection.
+		if finished && !matches!(version, Version::Draft01 | Version::Draft02) {
+			let done = lite::SubscribeDone {
+				final_group: track.final_group(),
+			};
+			stream.writer.encode(&done).await?;
 		}
 
 		stream.writer.finish()?;
@@ -265,10 +360,18 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 			};
 
 			let priority = priority.insert(track.info.priority, sequence);
+			let deadline = track.deadline(&group);
 
 			// Spawn a task to serve this group, ignoring any errors because they don't really matter.
 			// TODO add some logging at least.
-			let handle = Box::pin(Self::serve_group(session.clone(), msg, priority, group, version));
+			let handle = Box::pin(Self::serve_group(
+				session.clone(),
+				msg,
+				priority,
+				group,
+				deadline,
+				version,
+			));
 
 			// Terminate the old group if it's still running.
 			if let Some(old_sequence) = old_sequence.take() {
@@ -296,6 +399,7 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		msg: lite::Group,
 		mut priority: PriorityHandle,
 		mut group: GroupConsumer,
+		deadline: Option<Instant>,
 		version: Version,
 	) -> Result<(), Error> {
 		// TODO add a way to open in priority order.
@@ -309,6 +413,29 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		stream.encode(&lite::DataType::Group).await?;
 		stream.encode(&msg).await?;
 
+		// Reset the stream if the group isn't delivered before the track's timeout.
+		let res = tokio::select! {
+			res = Self::serve_frames(&mut stream, &mut priority, &mut group, version) => res,
+			Some(()) = async { tokio::time::sleep_until(deadline?).await; Some(()) } => Err(Error::Timeout),
+		};
+
+		if let Err(err) = res {
+			tracing::debug!(sequence = %msg.sequence, %err, "group error");
+			stream.abort(&err);
+			return Err(err);
+		}
+
+		tracing::debug!(sequence = %msg.sequence, "finished group");
+
+		Ok(())
+	}
+
+	async fn serve_frames(
+		stream: &mut Writer<S::SendStream, Version>,
+		priority: &mut PriorityHandle,
+		group: &mut GroupConsumer,
+		version: Version,
+	) -> Result<(), Error> {
 		loop {
 			let frame = tokio::select! {
 				biased;
@@ -326,9 +453,39 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 				None => break,
 			};
 
-			tracing::trace!(size = %frame.info.size, "writing frame");
+			tracing::trace!(size = ?frame.info.size, "writing frame");
+
+			// Streaming frames were added in Draft03, so older peers need the whole frame buffered.
+			let mut buffered = Vec::new();
+			let streaming = match (version, frame.info.size) {
+				(Version::Draft01 | Version::Draft02, size) => {
+					let size = match size {
+						Some(size) => size,
+						None => {
+							buffered = tokio::select! {
+								biased;
+								_ = stream.closed() => return Err(Error::Cancel),
+								chunks = frame.read_chunks() => chunks?,
+							};
+							buffered.iter().map(|chunk| chunk.len() as u64).sum()
+						}
+					};
 
-			stream.encode(&frame.info.size).await?;
+					// Extensions were also added in Draft03, so they're dropped for older peers.
+					stream.encode(&size).await?;
+					false
+				}
+				// A zero size indicates a streaming frame, otherwise it's the size plus one.
+				(_, size) => {
+					stream.encode(&size.map_or(0, |size| size + 1)).await?;
+					stream.encode(&frame.info.extensions).await?;
+					size.is_none()
+				}
+			};
+
+			for mut chunk in buffered {
+				stream.write_all(&mut chunk).await?;
+			}
 
 			loop {
 				let chunk = tokio::select! {
@@ -343,19 +500,26 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 				};
 
 				match chunk? {
+					// Streaming frames prefix each chunk with its size, skipping empty chunks.
+					Some(chunk) if streaming && chunk.is_empty() => {}
+					Some(mut chunk) if streaming => {
+						stream.encode(&(chunk.len() as u64)).await?;
+						stream.write_all(&mut chunk).await?;
+					}
 					Some(mut chunk) => stream.write_all(&mut chunk).await?,
 					None => break,
 				}
 			}
 
-			tracing::trace!(size = %frame.info.size, "wrote frame");
+			// An empty chunk marks the end of a streaming frame.
+			if streaming {
+				stream.encode(&0u64).await?;
+			}
+
+			tracing::trace!(size = ?frame.info.size, "wrote frame");
 		}
 
 		stream.finish()?;
-		stream.closed().await?;
-
-		tracing::debug!(sequence = %msg.sequence, "finished group");
-
-		Ok(())
+		stream.closed().await
 	}
 }
@@ -1,13 +1,25 @@
-use tokio::sync::oneshot;
+use std::{
+	collections::HashSet,
+	sync::{
+		Arc,
+		atomic::{AtomicU64, Ordering},
+	},
+	time::Duration,
+};
+
+use tokio::sync::{oneshot, watch};
+use web_async::Lock;
 
 use crate::{
-	Error, OriginConsumer, OriginProducer,
+	Error, OriginConsumer, OriginProducer, PathOwned,
 	coding::Stream,
-	lite::{SessionInfo, Version},
+	limits::Budgets,
+	lite::{self, SessionInfo, Version},
 };
 
 use super::{Publisher, Subscriber};
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn start<S: web_transport_trait::Session>(
 	session: S,
 	// The stream used to setup the session, after exchanging setup messages.
@@ -18,17 +30,32 @@ pub(crate) async fn start<S: web_transport_trait::Session>(
 	subscribe: Option<OriginProducer>,
 	// The version of the protocol to use.
 	version: Version,
+	// Send a GOAWAY with the given URI when this is set.
+	goaway: watch::Receiver<Option<String>>,
+	// Set to the new session URI when the peer sends a GOAWAY.
+	going_away: watch::Sender<Option<String>>,
+	// The prefixes we want the peer to announce.
+	interest: watch::Receiver<Vec<PathOwned>>,
+	// The number of bytes received, used to measure our throughput.
+	received: Arc<AtomicU64>,
+	// Set to the throughput reported by the peer.
+	throughput: watch::Sender<Option<u64>>,
+	// Limits the number of bytes buffered for our subscriptions.
+	budgets: Budgets,
+	// The broadcasts announced by the peer, reported by the Session.
+	announced: Lock<HashSet<PathOwned>>,
 ) -> Result<(), Error> {
-	let publisher = Publisher::new(session.clone(), publish, version);
-	let subscriber = Subscriber::new(session.clone(), subscribe, version);
+	let publisher = Publisher::new(session.clone(), publish, version, going_away);
+	let subscriber = Subscriber::new(session.clone(), subscribe, version, budgets, announced);
 
 	let init = oneshot::channel();
 
 	web_async::spawn(async move {
 		let res = tokio::select! {
-			res = run_session(setup) => res,
+			res = run_session(setup, received, throughput) => res,
 			res = publisher.run() => res,
-			res = subscriber.run(init.0) => res,
+			res = subscriber.run(interest, init.0) => res,
+			res = run_goaway(session.clone(), goaway, version) => res,
 		};
 
 		match res {
@@ -56,8 +83,80 @@ pub(crate) async fn start<S: web_transport_trait::Session>(
 	Ok(())
 }
 
-// TODO do something useful with this
-async fn run_session<S: web_transport_trait::Session>(mut stream: Stream<S, Version>) -> Result<(), Error> {
-	while let Some(_info) = stream.reader.decode_maybe::<SessionInfo>().await? {}
-	Err(Error::Cancel)
+// How often we report our throughput to the peer.
+const THROUGHPUT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Exchange SESSION_INFO messages on the setup stream.
+// We periodically report the rate that we're receiving data, and expose the rate reported by the peer.
+// This is only the observed throughput: an idle or application-limited sender reports less than the link could carry.
+async fn run_session<S: web_transport_trait::Session>(
+	mut stream: Stream<S, Version>,
+	received: Arc<AtomicU64>,
+	throughput: watch::Sender<Option<u64>>,
+) -> Result<(), Error> {
+	let mut interval = tokio::time::interval(THROUGHPUT_INTERVAL);
+	interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+	let mut last = (tokio::time::Instant::now(), received.load(Ordering::Relaxed));
+	let mut reported = None;
+
+	loop {
+		tokio::select! {
+			info = stream.reader.decode_maybe::<SessionInfo>() => match info? {
+				Some(info) => {
+					tracing::trace!(bitrate = ?info.bitrate, "received session info");
+					throughput.send_replace(info.bitrate);
+				}
+				None => return Err(Error::Cancel),
+			},
+			now = interval.tick() => {
+				let total = received.load(Ordering::Relaxed);
+				let elapsed = now.duration_since(last.0).as_secs_f64();
+				let rate = ((total - last.1) as f64 * 8.0 / elapsed.max(0.001)) as u64;
+				last = (now, total);
+
+				// Zero means unknown, which is the case when nothing was received.
+				let rate = Some(rate).filter(|rate| *rate > 0);
+				if rate != reported {
+					tracing::trace!(bitrate = ?rate, "sending session info");
+					stream.writer.encode(&SessionInfo { bitrate: rate }).await?;
+					reported = rate;
+				}
+			}
+		}
+	}
+}
+
+// Open a control stream to send a GOAWAY when requested, leaving the session open for the peer to migrate.
+async fn run_goaway<S: web_transport_trait::Session>(
+	session: S,
+	mut goaway: watch::Receiver<Option<String>>,
+	version: Version,
+) -> Result<(), Error> {
+	let uri = goaway.wait_for(Option::is_some).await.map(|uri| uri.clone());
+	let Ok(Some(uri)) = uri else {
+		// The Session was dropped, so nobody can ask us to go away.
+		return std::future::pending().await;
+	};
+
+	if matches!(version, Version::Draft01 | Version::Draft02) {
+		// The peer doesn't support GOAWAY, so it won't migrate until the session is closed.
+		tracing::debug!(%uri, ?version, "GOAWAY not supported");
+		return std::future::pending().await;
+	}
+
+	tracing::info!(%uri, "sending GOAWAY");
+
+	let mut stream = Stream::open(&session, version).await?;
+	stream.writer.encode(&lite::ControlType::GoAway).await?;
+
+	let msg = lite::GoAway {
+		new_session_uri: uri.into(),
+	};
+	stream.writer.encode(&msg).await?;
+	stream.writer.finish()?;
+	stream.writer.closed().await?;
+
+	// It's up to the peer (or a timeout) to close the session.
+	std::future::pending().await
 }
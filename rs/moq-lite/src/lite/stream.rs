@@ -8,6 +8,9 @@ pub enum ControlType {
 	Session = 0,
 	Announce = 1,
 	Subscribe = 2,
+	GoAway = 3,
+	// Added in Draft03.
+	Tracks = 4,
 }
 
 impl<V> Decode<V> for ControlType {
@@ -1,4 +1,4 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, time::Duration};
 
 use crate::{
 	Path,
@@ -15,6 +15,9 @@ pub struct Subscribe<'a> {
 	pub broadcast: Path<'a>,
 	pub track: Cow<'a, str>,
 	pub priority: u8,
+
+	/// The subscriber's delivery timeout, which can only narrow the publisher's.
+	pub timeout: Option<Duration>,
 }
 
 impl Message for Subscribe<'_> {
@@ -24,11 +27,20 @@ impl Message for Subscribe<'_> {
 		let track = Cow::<str>::decode(r, version)?;
 		let priority = u8::decode(r, version)?;
 
+		// The timeout was added in Draft03, in milliseconds with zero meaning none.
+		let timeout = match version {
+			Version::Draft01 | Version::Draft02 => None,
+			_ => Some(u64::decode(r, version)?)
+				.filter(|timeout| *timeout > 0)
+				.map(Duration::from_millis),
+		};
+
 		Ok(Self {
 			id,
 			broadcast,
 			track,
 			priority,
+			timeout,
 		})
 	}
 
@@ -37,6 +49,12 @@ impl Message for Subscribe<'_> {
 		self.broadcast.encode(w, version);
 		self.track.encode(w, version);
 		self.priority.encode(w, version);
+
+		if !matches!(version, Version::Draft01 | Version::Draft02) {
+			// Round up so a sub-millisecond timeout isn't confused with none.
+			let timeout = self.timeout.map_or(0, |timeout| timeout.as_millis().max(1) as u64);
+			timeout.encode(w, version);
+		}
 	}
 }
 
@@ -62,3 +80,55 @@ impl Message for SubscribeOk {
 		Ok(Self { priority })
 	}
 }
+
+/// Sent by the publisher after the final group has been delivered, before finishing the stream.
+///
+/// This lets the subscriber tell a finished track apart from a dropped connection.
+/// Added in Draft03; older versions just finish the stream.
+#[derive(Clone, Debug)]
+pub struct SubscribeDone {
+	/// The sequence number of the final group, or [None] if the track had no groups.
+	pub final_group: Option<u64>,
+}
+
+impl Message for SubscribeDone {
+	fn encode_msg<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		// Zero means no groups, otherwise it's the sequence plus one.
+		self.final_group.map_or(0, |group| group + 1).encode(w, version);
+	}
+
+	fn decode_msg<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let final_group = u64::decode(r, version)?.checked_sub(1);
+		Ok(Self { final_group })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bytes::BytesMut;
+
+	fn encode_message<M: Message>(msg: &M) -> Vec<u8> {
+		let mut buf = BytesMut::new();
+		msg.encode(&mut buf, Version::Draft03);
+		buf.to_vec()
+	}
+
+	fn decode_message<M: Message>(bytes: &[u8]) -> Result<M, DecodeError> {
+		let mut buf = bytes::Bytes::from(bytes.to_vec());
+		M::decode(&mut buf, Version::Draft03)
+	}
+
+	#[test]
+	fn test_subscribe_done() {
+		// Group zero must be distinguishable from no groups at all.
+		for final_group in [None, Some(0), Some(1), Some(u64::MAX >> 3)] {
+			let msg = SubscribeDone { final_group };
+
+			let encoded = encode_message(&msg);
+			let decoded: SubscribeDone = decode_message(&encoded).unwrap();
+
+			assert_eq!(decoded.final_group, final_group);
+		}
+	}
+}
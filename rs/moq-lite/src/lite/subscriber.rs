@@ -1,17 +1,19 @@
 use std::{
-	collections::{HashMap, hash_map::Entry},
+	collections::{BTreeSet, HashMap, HashSet, hash_map::Entry},
 	sync::{Arc, atomic},
 };
 
 use crate::{
-	AsPath, Broadcast, Error, Frame, FrameProducer, Group, GroupProducer, OriginProducer, Path, PathOwned,
-	TrackProducer,
+	AsPath, Broadcast, Budget, Error, Frame, FrameProducer, Group, GroupProducer, Metadata, OriginProducer, Path,
+	PathOwned, TrackProducer,
 	coding::{Reader, Stream},
+	limits::Budgets,
 	lite::{self, Version},
 	model::BroadcastProducer,
+	session::FINISH_TIMEOUT,
 };
 
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot, watch};
 use web_async::Lock;
 
 #[derive(Clone)]
@@ -19,26 +21,44 @@ pub(super) struct Subscriber<S: web_transport_trait::Session> {
 	session: S,
 
 	origin: Option<OriginProducer>,
-	subscribes: Lock<HashMap<u64, TrackProducer>>,
+	// Each subscription is limited by its own budget, a child of the session budget.
+	subscribes: Lock<HashMap<u64, (TrackProducer, Budget)>>,
 	next_id: Arc<atomic::AtomicU64>,
 	version: Version,
+	budgets: Budgets,
+
+	// Every broadcast announced by the peer, counting the announce streams that include it.
+	// Overlapping prefixes are briefly requested while the interest changes, so this avoids announcing twice.
+	broadcasts: Lock<HashMap<PathOwned, (BroadcastProducer, usize)>>,
+
+	// The paths of the above, shared with the Session.
+	announced: Lock<HashSet<PathOwned>>,
 }
 
 impl<S: web_transport_trait::Session> Subscriber<S> {
-	pub fn new(session: S, origin: Option<OriginProducer>, version: Version) -> Self {
+	pub fn new(
+		session: S,
+		origin: Option<OriginProducer>,
+		version: Version,
+		budgets: Budgets,
+		announced: Lock<HashSet<PathOwned>>,
+	) -> Self {
 		Self {
 			session,
 			origin,
 			subscribes: Default::default(),
 			next_id: Default::default(),
 			version,
+			budgets,
+			broadcasts: Default::default(),
+			announced,
 		}
 	}
 
 	/// Send a signal when the subscriber is initialized.
-	pub async fn run(self, init: oneshot::Sender<()>) -> Result<(), Error> {
+	pub async fn run(self, interest: watch::Receiver<Vec<PathOwned>>, init: oneshot::Sender<()>) -> Result<(), Error> {
 		tokio::select! {
-			Err(err) = self.clone().run_announce(init) => Err(err),
+			Err(err) = self.clone().run_announce(interest, init) => Err(err),
 			res = self.run_uni() => res,
 		}
 	}
@@ -76,67 +96,213 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 		Ok(())
 	}
 
-	async fn run_announce(mut self, init: oneshot::Sender<()>) -> Result<(), Error> {
+	async fn run_announce(
+		self,
+		mut interest: watch::Receiver<Vec<PathOwned>>,
+		init: oneshot::Sender<()>,
+	) -> Result<(), Error> {
 		if self.origin.is_none() {
 			// Don't do anything if there's no origin configured.
 			let _ = init.send(());
 			return Ok(());
 		}
 
+		// Each prefix gets its own announce stream, cancelled when the sender is dropped.
+		let mut running: HashMap<PathOwned, oneshot::Sender<()>> = HashMap::new();
+		let (errors_tx, mut errors) = mpsc::unbounded_channel();
+
+		let mut init = Some(init);
+		let mut open = true;
+
+		loop {
+			let prefixes = self.effective_interest(&interest.borrow_and_update());
+
+			let mut ready = Vec::new();
+
+			for prefix in prefixes.iter().cloned() {
+				if running.contains_key(&prefix) {
+					continue;
+				}
+
+				let cancel = oneshot::channel();
+				let initialized = oneshot::channel();
+				running.insert(prefix.clone(), cancel.0);
+				ready.push(initialized.1);
+
+				let this = self.clone();
+				let errors = errors_tx.clone();
+
+				web_async::spawn(async move {
+					if let Err(err) = this.run_announce_prefix(prefix, cancel.1, initialized.0).await {
+						let _ = errors.send(err);
+					}
+				});
+			}
+
+			// Wait until the initial announcements for every new prefix have been received.
+			for initialized in ready {
+				let _ = initialized.await;
+			}
+
+			// Only then stop any announce streams that we're no longer interested in.
+			// That way a broadcast covered by both the old and new prefixes is never unannounced, not even briefly.
+			running.retain(|prefix, _| prefixes.contains(prefix));
+
+			if let Some(init) = init.take() {
+				if let Ok(err) = errors.try_recv() {
+					return Err(err);
+				}
+
+				let _ = init.send(());
+			}
+
+			tokio::select! {
+				res = interest.changed(), if open => {
+					// The Session was dropped, so keep the current interest forever.
+					open = res.is_ok();
+				}
+				Some(err) = errors.recv() => return Err(err),
+			}
+		}
+	}
+
+	// Returns the prefixes we need to request, scoped to the allowed roots.
+	// Any prefix covered by a shorter prefix is skipped to avoid duplicate announcements.
+	fn effective_interest(&self, interest: &[PathOwned]) -> Vec<PathOwned> {
+		let origin = self.origin.as_ref().unwrap();
+
+		let mut scoped = Vec::new();
+		for prefix in interest {
+			for root in origin.allowed() {
+				if prefix.has_prefix(root) {
+					scoped.push(prefix.clone());
+				} else if root.has_prefix(prefix) {
+					scoped.push(root.to_owned());
+				}
+			}
+		}
+
+		let mut prefixes: Vec<PathOwned> = Vec::new();
+		for prefix in &scoped {
+			let covered = scoped.iter().any(|other| other != prefix && prefix.has_prefix(other));
+			if !covered && !prefixes.contains(prefix) {
+				prefixes.push(prefix.clone());
+			}
+		}
+
+		prefixes
+	}
+
+	async fn run_announce_prefix(
+		mut self,
+		prefix: PathOwned,
+		cancel: oneshot::Receiver<()>,
+		init: oneshot::Sender<()>,
+	) -> Result<(), Error> {
 		let mut stream = Stream::open(&self.session, self.version).await?;
 		stream.writer.encode(&lite::ControlType::Announce).await?;
 
-		tracing::trace!(root = %self.log_path(""), "announced start");
+		tracing::trace!(prefix = %self.log_path(&prefix), "announced start");
 
-		// Ask for everything.
-		// TODO This should actually ask for each root.
-		let msg = lite::AnnouncePlease { prefix: "".into() };
+		let msg = lite::AnnouncePlease {
+			prefix: prefix.borrow(),
+		};
 		stream.writer.encode(&msg).await?;
 
-		let mut producers = HashMap::new();
+		let mut suffixes = HashSet::new();
+
+		let res = tokio::select! {
+			res = self.run_announce_stream(&mut stream, &prefix, &mut suffixes, init) => res,
+			// The sender is dropped when we're no longer interested in this prefix.
+			_ = cancel => Err(Error::Cancel),
+		};
 
+		match res {
+			Ok(()) => {
+				// Close the stream when there's nothing more to announce.
+				stream.writer.finish()?;
+				stream.writer.closed().await
+			}
+			Err(Error::Cancel) => {
+				tracing::trace!(prefix = %self.log_path(&prefix), "announced cancelled");
+
+				// Unannounce everything that was announced under this prefix, unless another prefix still covers it.
+				for suffix in suffixes {
+					self.stop_announce(&prefix.join(&suffix));
+				}
+
+				stream.writer.abort(&Error::Cancel);
+				Ok(())
+			}
+			Err(err) => {
+				stream.writer.abort(&err);
+				Err(err)
+			}
+		}
+	}
+
+	async fn run_announce_stream(
+		&mut self,
+		stream: &mut Stream<S, Version>,
+		prefix: &Path<'_>,
+		suffixes: &mut HashSet<PathOwned>,
+		init: oneshot::Sender<()>,
+	) -> Result<(), Error> {
 		let msg: lite::AnnounceInit = stream.reader.decode().await?;
-		for path in msg.suffixes {
-			self.start_announce(path, &mut producers)?;
+		for (suffix, metadata) in msg.suffixes {
+			self.start_announce(prefix, suffix, metadata, suffixes)?;
 		}
 
 		let _ = init.send(());
 
 		while let Some(announce) = stream.reader.decode_maybe::<lite::Announce>().await? {
 			match announce {
-				lite::Announce::Active { suffix: path } => {
-					self.start_announce(path, &mut producers)?;
+				lite::Announce::Active { suffix, metadata } => {
+					self.start_announce(prefix, suffix, metadata, suffixes)?;
 				}
-				lite::Announce::Ended { suffix: path } => {
-					tracing::debug!(broadcast = %self.log_path(&path), "unannounced");
+				lite::Announce::Ended { suffix } => {
+					if !suffixes.remove(&suffix) {
+						return Err(Error::NotFound);
+					}
 
-					// Close the producer.
-					let mut producer = producers.remove(&path.into_owned()).ok_or(Error::NotFound)?;
-					producer.close();
+					self.stop_announce(&prefix.join(&suffix));
 				}
 			}
 		}
 
-		// Close the stream when there's nothing more to announce.
-		stream.writer.finish()?;
-		stream.writer.closed().await
+		Ok(())
 	}
 
 	fn start_announce(
 		&mut self,
-		path: PathOwned,
-		producers: &mut HashMap<PathOwned, BroadcastProducer>,
+		prefix: &Path<'_>,
+		suffix: PathOwned,
+		metadata: Metadata,
+		suffixes: &mut HashSet<PathOwned>,
 	) -> Result<(), Error> {
-		tracing::debug!(broadcast = %self.log_path(&path), "announce");
-
-		let broadcast = Broadcast::produce();
+		let path = prefix.join(&suffix);
 
 		// Make sure the peer doesn't double announce.
-		match producers.entry(path.to_owned()) {
-			Entry::Occupied(_) => return Err(Error::Duplicate),
-			Entry::Vacant(entry) => entry.insert(broadcast.producer.clone()),
+		if !suffixes.insert(suffix) {
+			return Err(Error::Duplicate);
+		}
+
+		let broadcast = match self.broadcasts.lock().entry(path.clone()) {
+			// Already announced via another prefix, so there's nothing more to do.
+			Entry::Occupied(mut entry) => {
+				entry.get_mut().1 += 1;
+				return Ok(());
+			}
+			Entry::Vacant(entry) => {
+				let broadcast = Broadcast::produce_with_metadata(metadata);
+				entry.insert((broadcast.producer.clone(), 1));
+				self.announced.lock().insert(path.clone());
+				broadcast
+			}
 		};
 
+		tracing::debug!(broadcast = %self.log_path(&path), "announce");
+
 		// Run the broadcast in the background until all consumers are dropped.
 		self.origin
 			.as_mut()
@@ -148,39 +314,149 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 		Ok(())
 	}
 
+	// Unannounce the broadcast once no announce stream includes it.
+	fn stop_announce(&self, path: &Path<'_>) {
+		let mut broadcasts = self.broadcasts.lock();
+		let Entry::Occupied(mut entry) = broadcasts.entry(path.to_owned()) else {
+			return;
+		};
+
+		entry.get_mut().1 -= 1;
+		if entry.get().1 == 0 {
+			tracing::debug!(broadcast = %self.log_path(path), "unannounced");
+			self.announced.lock().remove(entry.key());
+			let (mut producer, _) = entry.remove();
+			producer.close();
+		}
+	}
+
 	async fn run_broadcast(self, path: PathOwned, mut broadcast: BroadcastProducer) {
+		// Track discovery was added in Draft03, so older peers would reject the stream.
+		let mut discover = !matches!(self.version, Version::Draft01 | Version::Draft02);
+
 		// Actually start serving subscriptions.
 		loop {
 			// Keep serving requests until there are no more consumers.
 			// This way we'll clean up the task when the broadcast is no longer needed.
 			let track = tokio::select! {
 				_ = broadcast.unused() => break,
+				// Only ask for the list of tracks once somebody wants it.
+				_ = broadcast.tracks_requested(), if discover => None,
 				producer = broadcast.requested_track() => match producer {
-					Some(producer) => producer,
+					Some(producer) => Some(producer),
 					None => break,
 				},
 				_ = self.session.closed() => break,
 			};
 
+			let Some(track) = track else {
+				discover = false;
+				web_async::spawn(self.clone().run_tracks(path.clone(), broadcast.clone()));
+				continue;
+			};
+
 			let id = self.next_id.fetch_add(1, atomic::Ordering::Relaxed);
 			let mut this = self.clone();
 
 			let path = path.clone();
 			web_async::spawn(async move {
-				this.run_subscribe(id, path, track).await;
+				this.run_subscribe(id, path.clone(), track.clone()).await;
+
+				// Keep accepting groups that are still in flight until the final group arrives.
+				// The final group's stream may have been reset, so don't wait forever.
+				if !track.is_complete() {
+					tokio::select! {
+						_ = track.completed() => {},
+						err = this.session.closed() => track.abort(Error::Transport(Arc::new(err))),
+						_ = tokio::time::sleep(FINISH_TIMEOUT) => {
+							let broadcast = this.log_path(&path);
+							tracing::warn!(%broadcast, track = %track.info.name, "timed out waiting for the final group");
+							track.abort(Error::Timeout);
+						}
+					}
+				}
+
 				this.subscribes.lock().remove(&id);
 			});
 		}
 	}
 
+	async fn run_tracks(self, path: PathOwned, mut broadcast: BroadcastProducer) {
+		tracing::debug!(broadcast = %self.log_path(&path), "tracks started");
+
+		// Keep track of what we announced so we can unannounce it when the stream ends.
+		let mut announced = BTreeSet::new();
+
+		let res = tokio::select! {
+			_ = broadcast.unused() => Err(Error::Cancel),
+			res = self.run_tracks_stream(&path, &mut broadcast, &mut announced) => res,
+		};
+
+		match res {
+			Err(Error::Cancel) | Err(Error::Transport(_)) => {
+				tracing::debug!(broadcast = %self.log_path(&path), "tracks cancelled");
+			}
+			Err(err) => {
+				tracing::warn!(broadcast = %self.log_path(&path), %err, "tracks error");
+			}
+			Ok(()) => {
+				tracing::debug!(broadcast = %self.log_path(&path), "tracks complete");
+			}
+		}
+
+		for name in announced {
+			broadcast.unannounce_track(&name);
+		}
+	}
+
+	async fn run_tracks_stream(
+		&self,
+		path: &Path<'_>,
+		broadcast: &mut BroadcastProducer,
+		announced: &mut BTreeSet<String>,
+	) -> Result<(), Error> {
+		let mut stream = Stream::open(&self.session, self.version).await?;
+		stream.writer.encode(&lite::ControlType::Tracks).await?;
+		stream
+			.writer
+			.encode(&lite::TracksPlease {
+				broadcast: path.to_owned(),
+			})
+			.await?;
+
+		let init = stream.reader.decode::<lite::TracksInit>().await?;
+		for name in init.names {
+			broadcast.announce_track(&name);
+			announced.insert(name.into_owned());
+		}
+
+		while let Some(msg) = stream.reader.decode_maybe::<lite::TrackAnnounce>().await? {
+			match msg {
+				lite::TrackAnnounce::Active { name } => {
+					broadcast.announce_track(&name);
+					announced.insert(name.into_owned());
+				}
+				lite::TrackAnnounce::Ended { name } => {
+					broadcast.unannounce_track(&name);
+					announced.remove(name.as_ref());
+				}
+			}
+		}
+
+		stream.writer.finish()?;
+		stream.writer.closed().await
+	}
+
 	async fn run_subscribe(&mut self, id: u64, broadcast: Path<'_>, track: TrackProducer) {
-		self.subscribes.lock().insert(id, track.clone());
+		let budget = self.budgets.track();
+		self.subscribes.lock().insert(id, (track.clone(), budget));
 
 		let msg = lite::Subscribe {
 			id,
 			broadcast: broadcast.to_owned(),
 			track: (&track.info.name).into(),
 			priority: track.info.priority,
+			timeout: track.timeout(),
 		};
 
 		tracing::info!(id, broadcast = %self.log_path(&broadcast), track = %track.info.name, "subscribe started");
@@ -199,40 +475,57 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 				tracing::warn!(id, broadcast = %self.log_path(&broadcast), track = %track.info.name, %err, "subscribe error");
 				track.abort(err);
 			}
-			_ => {
-				tracing::info!(id, broadcast = %self.log_path(&broadcast), track = %track.info.name, "subscribe complete");
-				track.close();
+			Ok(final_group) => {
+				tracing::info!(id, broadcast = %self.log_path(&broadcast), track = %track.info.name, ?final_group, "subscribe complete");
+				match final_group {
+					// The final group may still be in flight, so keep accepting groups until then.
+					Some(sequence) => track.finish(sequence),
+					None => track.close(),
+				}
 			}
 		}
 	}
 
-	async fn run_track(&mut self, msg: lite::Subscribe<'_>) -> Result<(), Error> {
+	// Returns the final group, if the publisher signalled one.
+	async fn run_track(&mut self, msg: lite::Subscribe<'_>) -> Result<Option<u64>, Error> {
 		let mut stream = Stream::open(&self.session, self.version).await?;
 		stream.writer.encode(&lite::ControlType::Subscribe).await?;
 
-		if let Err(err) = self.run_track_stream(&mut stream, msg).await {
-			stream.writer.abort(&err);
-			return Err(err);
-		}
+		let final_group = match self.run_track_stream(&mut stream, msg).await {
+			Ok(final_group) => final_group,
+			Err(err) => {
+				stream.writer.abort(&err);
+				return Err(err);
+			}
+		};
 
-		stream.writer.finish()?;
-		stream.writer.closed().await
+		// The publisher already finished and may have stopped reading, so errors don't matter anymore.
+		stream.writer.finish().ok();
+		stream.writer.closed().await.ok();
+
+		Ok(final_group)
 	}
 
 	async fn run_track_stream(
 		&mut self,
 		stream: &mut Stream<S, Version>,
 		msg: lite::Subscribe<'_>,
-	) -> Result<(), Error> {
+	) -> Result<Option<u64>, Error> {
 		stream.writer.encode(&msg).await?;
 
 		// TODO use the response correctly populate the track info
 		let _info: lite::SubscribeOk = stream.reader.decode().await?;
 
+		// Draft03 sends the final group before finishing the stream.
+		let done = match self.version {
+			Version::Draft01 | Version::Draft02 => None,
+			_ => stream.reader.decode_maybe::<lite::SubscribeDone>().await?,
+		};
+
 		// Wait until the stream is closed
 		stream.reader.closed().await?;
 
-		Ok(())
+		Ok(done.and_then(|done| done.final_group))
 	}
 
 	pub async fn recv_group(&mut self, stream: &mut Reader<S::RecvStream, Version>) -> Result<(), Error> {
@@ -240,10 +533,21 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 
 		let group = {
 			let mut subs = self.subscribes.lock();
-			let track = subs.get_mut(&hdr.subscribe).ok_or(Error::Cancel)?;
+			let (track, budget) = subs.get_mut(&hdr.subscribe).ok_or(Error::Cancel)?;
 
 			let group = Group { sequence: hdr.sequence };
-			track.create_group(group).ok_or(Error::Old)?
+			let group = track.create_group(group).ok_or(Error::Old)?;
+			let group = match self.budgets.group(budget) {
+				Some(budget) => group.with_budget(budget),
+				None => group,
+			};
+
+			// This was the final group after the track finished, so we're done with the subscription.
+			if track.is_complete() {
+				subs.remove(&hdr.subscribe);
+			}
+
+			group
 		};
 
 		let res = tokio::select! {
@@ -275,7 +579,14 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 		mut group: GroupProducer,
 	) -> Result<(), Error> {
 		while let Some(size) = stream.decode_maybe::<u64>().await? {
-			let frame = group.create_frame(Frame { size });
+			// Extensions and streaming frames were added in Draft03.
+			let (size, extensions) = match self.version {
+				Version::Draft01 | Version::Draft02 => (Some(size), Default::default()),
+				// A zero size indicates a streaming frame, otherwise it's the size plus one.
+				_ => (size.checked_sub(1), stream.decode().await?),
+			};
+
+			let frame = group.create_frame(Frame { size, extensions });
 
 			let res = tokio::select! {
 				_ = frame.unused() => Err(Error::Cancel),
@@ -298,9 +609,35 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 		stream: &mut Reader<S::RecvStream, Version>,
 		mut frame: FrameProducer,
 	) -> Result<(), Error> {
-		let mut remain = frame.info.size;
+		tracing::trace!(size = ?frame.info.size, "reading frame");
+
+		match frame.info.size {
+			Some(size) => Self::run_chunk(stream, &mut frame, size).await?,
+			// Streaming frames are a sequence of sized chunks, terminated by an empty chunk.
+			None => loop {
+				let size: u64 = stream.decode().await?;
+				if size == 0 {
+					break;
+				}
 
-		tracing::trace!(size = %frame.info.size, "reading frame");
+				Self::run_chunk(stream, &mut frame, size).await?;
+			},
+		}
+
+		tracing::trace!(size = ?frame.info.size, "read frame");
+
+		frame.close();
+
+		Ok(())
+	}
+
+	// Read exactly `size` bytes into the frame.
+	async fn run_chunk(
+		stream: &mut Reader<S::RecvStream, Version>,
+		frame: &mut FrameProducer,
+		size: u64,
+	) -> Result<(), Error> {
+		let mut remain = size;
 
 		const MAX_CHUNK: usize = 1024 * 1024; // 1 MiB
 		while remain > 0 {
@@ -309,13 +646,9 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 				.await?
 				.ok_or(Error::WrongSize)?;
 			remain = remain.checked_sub(chunk.len() as u64).ok_or(Error::WrongSize)?;
-			frame.write_chunk(chunk);
+			frame.try_write_chunk(chunk)?;
 		}
 
-		tracing::trace!(size = %frame.info.size, "read frame");
-
-		frame.close();
-
 		Ok(())
 	}
 
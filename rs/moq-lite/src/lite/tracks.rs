@@ -0,0 +1,154 @@
+use std::borrow::Cow;
+
+use crate::{
+	Path,
+	coding::*,
+	lite::{AnnounceStatus, Message, Version},
+};
+
+/// Sent by the subscriber to request the tracks within a broadcast.
+///
+/// Added in Draft03.
+#[derive(Clone, Debug)]
+pub struct TracksPlease<'a> {
+	pub broadcast: Path<'a>,
+}
+
+impl Message for TracksPlease<'_> {
+	fn decode_msg<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let broadcast = Path::decode(r, version)?;
+		Ok(Self { broadcast })
+	}
+
+	fn encode_msg<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		self.broadcast.encode(w, version)
+	}
+}
+
+/// Sent by the publisher in response to [TracksPlease] with the currently announced tracks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TracksInit<'a> {
+	pub names: Vec<Cow<'a, str>>,
+}
+
+impl Message for TracksInit<'_> {
+	fn decode_msg<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let count = u64::decode(r, version)?;
+
+		// Don't allocate more than 1024 elements upfront
+		let mut names = Vec::with_capacity(count.min(1024) as usize);
+
+		for _ in 0..count {
+			names.push(Cow::<str>::decode(r, version)?);
+		}
+
+		Ok(Self { names })
+	}
+
+	fn encode_msg<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		(self.names.len() as u64).encode(w, version);
+		for name in &self.names {
+			name.encode(w, version);
+		}
+	}
+}
+
+/// Sent by the publisher after [TracksInit] when a track is added or removed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrackAnnounce<'a> {
+	Active { name: Cow<'a, str> },
+	Ended { name: Cow<'a, str> },
+}
+
+impl Message for TrackAnnounce<'_> {
+	fn decode_msg<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		Ok(match AnnounceStatus::decode(r, version)? {
+			AnnounceStatus::Active => Self::Active {
+				name: Cow::<str>::decode(r, version)?,
+			},
+			AnnounceStatus::Ended => Self::Ended {
+				name: Cow::<str>::decode(r, version)?,
+			},
+		})
+	}
+
+	fn encode_msg<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		match self {
+			Self::Active { name } => {
+				AnnounceStatus::Active.encode(w, version);
+				name.encode(w, version);
+			}
+			Self::Ended { name } => {
+				AnnounceStatus::Ended.encode(w, version);
+				name.encode(w, version);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bytes::BytesMut;
+
+	fn encode_message<M: Message>(msg: &M) -> Vec<u8> {
+		let mut buf = BytesMut::new();
+		msg.encode(&mut buf, Version::Draft03);
+		buf.to_vec()
+	}
+
+	fn decode_message<M: Message>(bytes: &[u8]) -> Result<M, DecodeError> {
+		let mut buf = bytes::Bytes::from(bytes.to_vec());
+		M::decode(&mut buf, Version::Draft03)
+	}
+
+	#[test]
+	fn test_tracks_please() {
+		let msg = TracksPlease {
+			broadcast: Path::new("rooms/1/alice"),
+		};
+
+		let encoded = encode_message(&msg);
+		let decoded: TracksPlease = decode_message(&encoded).unwrap();
+
+		assert_eq!(decoded.broadcast.as_str(), "rooms/1/alice");
+	}
+
+	#[test]
+	fn test_tracks_init() {
+		let msg = TracksInit {
+			names: vec!["video".into(), "audio".into()],
+		};
+
+		let encoded = encode_message(&msg);
+		let decoded: TracksInit = decode_message(&encoded).unwrap();
+		assert_eq!(decoded, msg);
+
+		let empty = TracksInit { names: Vec::new() };
+		let encoded = encode_message(&empty);
+		let decoded: TracksInit = decode_message(&encoded).unwrap();
+		assert_eq!(decoded, empty);
+	}
+
+	#[test]
+	fn test_track_announce() {
+		for msg in [
+			TrackAnnounce::Active { name: "video".into() },
+			TrackAnnounce::Ended { name: "video".into() },
+		] {
+			let encoded = encode_message(&msg);
+			let decoded: TrackAnnounce = decode_message(&encoded).unwrap();
+			assert_eq!(decoded, msg);
+		}
+	}
+
+	#[test]
+	fn test_tracks_init_truncated() {
+		let msg = TracksInit {
+			names: vec!["video".into()],
+		};
+
+		let encoded = encode_message(&msg);
+		assert!(decode_message::<TracksInit>(&encoded[..encoded.len() - 1]).is_err());
+	}
+}
@@ -11,6 +11,7 @@ pub const ALPN: &str = "moql";
 pub enum Version {
 	Draft01 = 0xff0dad01,
 	Draft02 = 0xff0dad02,
+	Draft03 = 0xff0dad03,
 }
 
 impl TryFrom<coding::Version> for Version {
@@ -21,6 +22,8 @@ impl TryFrom<coding::Version> for Version {
 			Ok(Self::Draft01)
 		} else if value == Self::Draft02.coding() {
 			Ok(Self::Draft02)
+		} else if value == Self::Draft03.coding() {
+			Ok(Self::Draft03)
 		} else {
 			Err(())
 		}
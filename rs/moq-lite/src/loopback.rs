@@ -0,0 +1,662 @@
+//! An in-process transport for testing, implementing [web_transport_trait::Session] over channels.
+//!
+//! Create a connected pair with [Loopback::new], optionally simulating [Conditions] such as latency and loss.
+//! All delays use [tokio::time], so tests are deterministic when run with a paused clock.
+//!
+//! ```
+//! # #[tokio::main(flavor = "current_thread", start_paused = true)]
+//! # async fn main() -> Result<(), moq_lite::Error> {
+//! use moq_lite::loopback::{Conditions, Loopback};
+//!
+//! let conditions = Conditions {
+//!     latency: std::time::Duration::from_millis(50),
+//!     ..Default::default()
+//! };
+//! let loopback = Loopback::new(conditions);
+//!
+//! let (client, server) = tokio::try_join!(
+//!     moq_lite::Session::connect(loopback.client, None, None),
+//!     moq_lite::Session::accept(loopback.server, None, None),
+//! )?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use bytes::{Buf, Bytes};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use tokio::{
+	sync::{mpsc, watch},
+	time::Instant,
+};
+
+/// The maximum size of a datagram, matching the minimum QUIC MTU.
+const MAX_DATAGRAM_SIZE: usize = 1200;
+
+/// Simulated network conditions, applied independently to each direction.
+#[derive(Clone, Debug, Default)]
+pub struct Conditions {
+	/// The one-way delay before data arrives.
+	pub latency: Duration,
+
+	/// The maximum throughput in bytes per second, or [None] for unlimited.
+	///
+	/// Writes block until the data has been sent, so a slow link applies backpressure.
+	pub bandwidth: Option<u64>,
+
+	/// The probability (0.0 to 1.0) that a packet is lost.
+	///
+	/// Lost datagrams are dropped, while lost stream data is delayed by a retransmission round trip.
+	pub loss: f64,
+
+	/// The probability (0.0 to 1.0) that a stream is reset by the network when opened.
+	pub reset: f64,
+
+	/// The seed for the random number generator, so loss is reproducible.
+	pub seed: u64,
+}
+
+/// A connected pair of in-process sessions.
+pub struct Loopback {
+	pub client: Session,
+	pub server: Session,
+}
+
+impl Loopback {
+	/// Create a connected pair of sessions with the given network conditions.
+	pub fn new(conditions: Conditions) -> Self {
+		let closed = Arc::new(watch::Sender::new(None));
+
+		let (client, server) = (Half::new(), Half::new());
+		let (client_link, server_link) = (
+			Arc::new(Link::new(&conditions, conditions.seed)),
+			Arc::new(Link::new(&conditions, conditions.seed.wrapping_add(1))),
+		);
+
+		Self {
+			client: Session::new(client.tx, server.rx, client_link, closed.clone()),
+			server: Session::new(server.tx, client.rx, server_link, closed),
+		}
+	}
+}
+
+impl Default for Loopback {
+	fn default() -> Self {
+		Self::new(Conditions::default())
+	}
+}
+
+/// An error returned by the loopback transport.
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum Error {
+	#[error("session closed: code={0} reason={1}")]
+	Closed(u32, String),
+
+	#[error("stream reset: code={0}")]
+	Reset(u32),
+
+	#[error("stream stopped: code={0}")]
+	Stopped(u32),
+
+	#[error("stream finished")]
+	Finished,
+}
+
+impl web_transport_trait::Error for Error {
+	fn session_error(&self) -> Option<(u32, String)> {
+		match self {
+			Self::Closed(code, reason) => Some((*code, reason.clone())),
+			_ => None,
+		}
+	}
+
+	fn stream_error(&self) -> Option<u32> {
+		match self {
+			Self::Reset(code) | Self::Stopped(code) => Some(*code),
+			_ => None,
+		}
+	}
+}
+
+// A chunk of data and when it arrives at the peer.
+type Chunk = (Instant, Bytes);
+
+// One direction of a session: the channels used to deliver streams and datagrams to the peer.
+struct Half {
+	tx: Senders,
+	rx: Receivers,
+}
+
+impl Half {
+	fn new() -> Self {
+		let uni = mpsc::unbounded_channel();
+		let bi = mpsc::unbounded_channel();
+		let datagrams = mpsc::unbounded_channel();
+
+		Self {
+			tx: Senders {
+				uni: uni.0,
+				bi: bi.0,
+				datagrams: datagrams.0,
+			},
+			rx: Receivers {
+				uni: tokio::sync::Mutex::new(uni.1),
+				bi: tokio::sync::Mutex::new(bi.1),
+				datagrams: tokio::sync::Mutex::new(datagrams.1),
+			},
+		}
+	}
+}
+
+struct Senders {
+	uni: mpsc::UnboundedSender<RecvStream>,
+	bi: mpsc::UnboundedSender<(SendStream, RecvStream)>,
+	datagrams: mpsc::UnboundedSender<Chunk>,
+}
+
+struct Receivers {
+	uni: tokio::sync::Mutex<mpsc::UnboundedReceiver<RecvStream>>,
+	bi: tokio::sync::Mutex<mpsc::UnboundedReceiver<(SendStream, RecvStream)>>,
+	datagrams: tokio::sync::Mutex<mpsc::UnboundedReceiver<Chunk>>,
+}
+
+// The simulated network in one direction.
+struct Link {
+	latency: Duration,
+	bandwidth: Option<u64>,
+	loss: f64,
+	reset: f64,
+	rng: Mutex<StdRng>,
+
+	// When the link is next idle, used to queue data behind the bandwidth cap.
+	idle: Mutex<Instant>,
+}
+
+impl Link {
+	fn new(conditions: &Conditions, seed: u64) -> Self {
+		Self {
+			latency: conditions.latency,
+			bandwidth: conditions.bandwidth,
+			loss: conditions.loss,
+			reset: conditions.reset,
+			rng: Mutex::new(StdRng::seed_from_u64(seed)),
+			idle: Mutex::new(Instant::now()),
+		}
+	}
+
+	// Returns when the data has been sent and when it arrives.
+	fn transmit(&self, size: usize) -> (Instant, Instant) {
+		let mut idle = self.idle.lock().unwrap();
+
+		let start = Instant::now().max(*idle);
+		let duration = match self.bandwidth {
+			Some(bandwidth) => Duration::from_secs_f64(size as f64 / bandwidth.max(1) as f64),
+			None => Duration::ZERO,
+		};
+
+		*idle = start + duration;
+		(*idle, *idle + self.latency)
+	}
+
+	fn roll(&self, probability: f64) -> bool {
+		probability > 0.0 && self.rng.lock().unwrap().random_bool(probability.min(1.0))
+	}
+
+	fn lost(&self) -> bool {
+		self.roll(self.loss)
+	}
+}
+
+// State shared between the two sides of a stream.
+#[derive(Default)]
+struct StreamState {
+	fin: bool,
+	reset: Option<u32>,
+	stop: Option<u32>,
+}
+
+/// One side of a [Loopback] connection.
+///
+/// The session is closed once every clone has been dropped.
+#[derive(Clone)]
+pub struct Session {
+	inner: Arc<SessionInner>,
+}
+
+struct SessionInner {
+	tx: Senders,
+	rx: Receivers,
+	link: Arc<Link>,
+	closed: Arc<watch::Sender<Option<Error>>>,
+}
+
+impl Drop for SessionInner {
+	fn drop(&mut self) {
+		close(&self.closed, 0, "dropped");
+	}
+}
+
+fn close(closed: &watch::Sender<Option<Error>>, code: u32, reason: &str) {
+	closed.send_if_modified(|closed| {
+		if closed.is_some() {
+			return false;
+		}
+
+		*closed = Some(Error::Closed(code, reason.to_string()));
+		true
+	});
+}
+
+async fn wait_closed(closed: &watch::Sender<Option<Error>>) -> Error {
+	let mut closed = closed.subscribe();
+	match closed.wait_for(Option::is_some).await {
+		Ok(err) => err.clone().unwrap(),
+		Err(_) => Error::Closed(0, "dropped".to_string()),
+	}
+}
+
+impl Session {
+	fn new(tx: Senders, rx: Receivers, link: Arc<Link>, closed: Arc<watch::Sender<Option<Error>>>) -> Self {
+		Self {
+			inner: Arc::new(SessionInner { tx, rx, link, closed }),
+		}
+	}
+
+	fn error(&self) -> Option<Error> {
+		self.inner.closed.borrow().clone()
+	}
+
+	fn stream(&self) -> (SendStream, RecvStream) {
+		let state = Arc::new(watch::Sender::new(StreamState {
+			reset: self.inner.link.roll(self.inner.link.reset).then_some(0),
+			..Default::default()
+		}));
+		let chunks = mpsc::unbounded_channel();
+
+		let send = SendStream {
+			chunks: Some(chunks.0),
+			state: state.clone(),
+			link: self.inner.link.clone(),
+			closed: self.inner.closed.clone(),
+		};
+
+		let recv = RecvStream {
+			chunks: chunks.1,
+			pending: Bytes::new(),
+			state,
+			closed: self.inner.closed.clone(),
+		};
+
+		(send, recv)
+	}
+}
+
+impl web_transport_trait::Session for Session {
+	type SendStream = SendStream;
+	type RecvStream = RecvStream;
+	type Error = Error;
+
+	async fn accept_uni(&self) -> Result<Self::RecvStream, Self::Error> {
+		let mut uni = self.inner.rx.uni.lock().await;
+
+		tokio::select! {
+			Some(stream) = uni.recv() => Ok(stream),
+			err = wait_closed(&self.inner.closed) => Err(err),
+		}
+	}
+
+	async fn accept_bi(&self) -> Result<(Self::SendStream, Self::RecvStream), Self::Error> {
+		let mut bi = self.inner.rx.bi.lock().await;
+
+		tokio::select! {
+			Some(stream) = bi.recv() => Ok(stream),
+			err = wait_closed(&self.inner.closed) => Err(err),
+		}
+	}
+
+	async fn open_bi(&self) -> Result<(Self::SendStream, Self::RecvStream), Self::Error> {
+		if let Some(err) = self.error() {
+			return Err(err);
+		}
+
+		let (send, peer_recv) = self.stream();
+		let (peer_send, recv) = self.stream();
+		let _ = self.inner.tx.bi.send((peer_send, peer_recv));
+
+		Ok((send, recv))
+	}
+
+	async fn open_uni(&self) -> Result<Self::SendStream, Self::Error> {
+		if let Some(err) = self.error() {
+			return Err(err);
+		}
+
+		let (send, peer_recv) = self.stream();
+		let _ = self.inner.tx.uni.send(peer_recv);
+
+		Ok(send)
+	}
+
+	fn send_datagram(&self, payload: Bytes) -> Result<(), Self::Error> {
+		if let Some(err) = self.error() {
+			return Err(err);
+		}
+
+		// Datagrams are silently dropped, just like the real thing.
+		if payload.len() > MAX_DATAGRAM_SIZE || self.inner.link.lost() {
+			return Ok(());
+		}
+
+		let (_, arrival) = self.inner.link.transmit(payload.len());
+		let _ = self.inner.tx.datagrams.send((arrival, payload));
+
+		Ok(())
+	}
+
+	async fn recv_datagram(&self) -> Result<Bytes, Self::Error> {
+		let mut datagrams = self.inner.rx.datagrams.lock().await;
+
+		tokio::select! {
+			Some((arrival, payload)) = datagrams.recv() => {
+				tokio::time::sleep_until(arrival).await;
+				Ok(payload)
+			}
+			err = wait_closed(&self.inner.closed) => Err(err),
+		}
+	}
+
+	fn max_datagram_size(&self) -> usize {
+		MAX_DATAGRAM_SIZE
+	}
+
+	fn close(&self, code: u32, reason: &str) {
+		close(&self.inner.closed, code, reason);
+	}
+
+	async fn closed(&self) -> Self::Error {
+		wait_closed(&self.inner.closed).await
+	}
+}
+
+/// An outgoing stream of a loopback [Session].
+///
+/// Unlike QUIC, the stream is finished on drop rather than reset.
+pub struct SendStream {
+	// None once finished or reset.
+	chunks: Option<mpsc::UnboundedSender<Chunk>>,
+	state: Arc<watch::Sender<StreamState>>,
+	link: Arc<Link>,
+	closed: Arc<watch::Sender<Option<Error>>>,
+}
+
+impl SendStream {
+	fn error(&self) -> Option<Error> {
+		if let Some(err) = self.closed.borrow().clone() {
+			return Some(err);
+		}
+
+		let state = self.state.borrow();
+		if let Some(code) = state.stop {
+			return Some(Error::Stopped(code));
+		}
+		if let Some(code) = state.reset {
+			return Some(Error::Reset(code));
+		}
+
+		None
+	}
+}
+
+impl web_transport_trait::SendStream for SendStream {
+	type Error = Error;
+
+	async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+		if let Some(err) = self.error() {
+			return Err(err);
+		}
+
+		let chunks = self.chunks.as_ref().ok_or(Error::Finished)?;
+
+		let (sent, mut arrival) = self.link.transmit(buf.len());
+		if self.link.lost() {
+			// Detecting the loss and retransmitting takes another round trip.
+			arrival += 2 * self.link.latency;
+		}
+
+		let _ = chunks.send((arrival, Bytes::copy_from_slice(buf)));
+
+		// Block until the data has left the (simulated) network interface.
+		tokio::time::sleep_until(sent).await;
+
+		Ok(buf.len())
+	}
+
+	fn set_priority(&mut self, _order: u8) {}
+
+	fn finish(&mut self) -> Result<(), Self::Error> {
+		if let Some(err) = self.error() {
+			return Err(err);
+		}
+
+		self.chunks.take().ok_or(Error::Finished)?;
+		self.state.send_modify(|state| state.fin = true);
+
+		Ok(())
+	}
+
+	fn reset(&mut self, code: u32) {
+		// Like QUIC, a reset after a FIN is a no-op once the data has been acknowledged, which we assume is instant.
+		let Some(chunks) = self.chunks.take() else { return };
+
+		self.state.send_if_modified(|state| {
+			if state.reset.is_some() {
+				return false;
+			}
+
+			state.reset = Some(code);
+			true
+		});
+
+		// Close the channel only after the reset is visible, so the reader doesn't mistake it for a FIN.
+		drop(chunks);
+	}
+
+	async fn closed(&mut self) -> Result<(), Self::Error> {
+		let mut state = self.state.subscribe();
+
+		tokio::select! {
+			res = state.wait_for(|state| state.fin || state.reset.is_some() || state.stop.is_some()) => {
+				let state = res.map_err(|_| Error::Finished)?;
+				match state.stop {
+					Some(code) if !state.fin => Err(Error::Stopped(code)),
+					_ => Ok(()),
+				}
+			}
+			err = wait_closed(&self.closed) => Err(err),
+		}
+	}
+}
+
+impl Drop for SendStream {
+	fn drop(&mut self) {
+		if self.chunks.take().is_some() {
+			self.state.send_modify(|state| state.fin = true);
+		}
+	}
+}
+
+/// An incoming stream of a loopback [Session].
+pub struct RecvStream {
+	chunks: mpsc::UnboundedReceiver<Chunk>,
+	// Data that has arrived but not been read yet.
+	pending: Bytes,
+	state: Arc<watch::Sender<StreamState>>,
+	closed: Arc<watch::Sender<Option<Error>>>,
+}
+
+impl web_transport_trait::RecvStream for RecvStream {
+	type Error = Error;
+
+	async fn read(&mut self, dst: &mut [u8]) -> Result<Option<usize>, Self::Error> {
+		if self.pending.is_empty() {
+			let mut state = self.state.subscribe();
+
+			tokio::select! {
+				// A reset discards any data that hasn't been read yet.
+				biased;
+				Some(code) = async { state.wait_for(|state| state.reset.is_some()).await.ok()?.reset } => return Err(Error::Reset(code)),
+				err = wait_closed(&self.closed) => return Err(err),
+				chunk = self.chunks.recv() => match chunk {
+					Some((arrival, data)) => {
+						tokio::time::sleep_until(arrival).await;
+						self.pending = data;
+					}
+					None => return Ok(None),
+				},
+			}
+		}
+
+		let size = dst.len().min(self.pending.len());
+		dst[..size].copy_from_slice(&self.pending[..size]);
+		self.pending.advance(size);
+
+		Ok(Some(size))
+	}
+
+	fn stop(&mut self, code: u32) {
+		self.state.send_if_modified(|state| {
+			if state.stop.is_some() || state.fin {
+				return false;
+			}
+
+			state.stop = Some(code);
+			true
+		});
+	}
+
+	async fn closed(&mut self) -> Result<(), Self::Error> {
+		let mut state = self.state.subscribe();
+
+		tokio::select! {
+			res = state.wait_for(|state| state.fin || state.reset.is_some() || state.stop.is_some()) => match res.map_err(|_| Error::Finished)?.reset {
+				Some(code) => Err(Error::Reset(code)),
+				None => Ok(()),
+			},
+			err = wait_closed(&self.closed) => Err(err),
+		}
+	}
+}
+
+impl Drop for RecvStream {
+	fn drop(&mut self) {
+		web_transport_trait::RecvStream::stop(self, 0);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Broadcast, Origin, Track};
+	use web_transport_trait::{RecvStream as _, SendStream as _, Session as _};
+
+	#[tokio::test(start_paused = true)]
+	async fn test_bandwidth() {
+		let loopback = Loopback::new(Conditions {
+			latency: Duration::from_millis(50),
+			bandwidth: Some(1000),
+			..Default::default()
+		});
+
+		let start = Instant::now();
+
+		let mut send = loopback.client.open_uni().await.unwrap();
+		send.write_all(&[0; 500]).await.unwrap();
+		send.finish().unwrap();
+
+		// Writes block until the data has been sent.
+		assert_eq!(start.elapsed(), Duration::from_millis(500));
+
+		let mut recv = loopback.server.accept_uni().await.unwrap();
+		assert_eq!(recv.read_all().await.unwrap().len(), 500);
+		assert_eq!(start.elapsed(), Duration::from_millis(550));
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_datagram_loss() {
+		let loopback = Loopback::new(Conditions {
+			loss: 1.0,
+			..Default::default()
+		});
+
+		loopback.client.send_datagram(Bytes::from_static(b"lost")).unwrap();
+		assert!(
+			tokio::time::timeout(Duration::from_secs(1), loopback.server.recv_datagram())
+				.await
+				.is_err()
+		);
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_reset() {
+		let loopback = Loopback::default();
+
+		let mut send = loopback.client.open_uni().await.unwrap();
+		send.write_all(b"hello").await.unwrap();
+		send.reset(7);
+
+		let mut recv = loopback.server.accept_uni().await.unwrap();
+		let err = recv.read_all().await.unwrap_err();
+		assert!(matches!(err, Error::Reset(7)));
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_close() {
+		let loopback = Loopback::default();
+
+		loopback.client.close(42, "bye");
+		assert!(matches!(loopback.server.closed().await, Error::Closed(42, _)));
+
+		// Dropping every handle also closes the session.
+		let loopback = Loopback::default();
+		drop(loopback.client);
+		assert!(matches!(loopback.server.closed().await, Error::Closed(0, _)));
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_session() {
+		let latency = Duration::from_millis(50);
+		let loopback = Loopback::new(Conditions {
+			latency,
+			..Default::default()
+		});
+
+		let publisher = Origin::produce();
+		let mut subscriber = Origin::produce();
+
+		let mut broadcast = Broadcast::produce();
+		let mut track = broadcast.producer.create_track(Track::new("test"));
+		publisher.producer.publish_broadcast("demo", broadcast.consumer);
+
+		let (_client, _server) = tokio::try_join!(
+			crate::Session::connect(loopback.client, publisher.consumer, None),
+			crate::Session::accept(loopback.server, None, subscriber.producer),
+		)
+		.unwrap();
+
+		let (path, remote) = subscriber.consumer.announced().await.unwrap();
+		assert_eq!(path.as_str(), "demo");
+
+		let start = Instant::now();
+		track.write_frame(Bytes::from_static(b"hello"));
+
+		// The subscription needs a round trip before the group arrives.
+		let mut remote = remote.unwrap().subscribe_track(&Track::new("test"));
+		let mut group = remote.next_group().await.unwrap().unwrap();
+		assert_eq!(group.read_frame().await.unwrap().unwrap(), "hello");
+		assert!(start.elapsed() >= 2 * latency);
+	}
+}
@@ -1,17 +1,18 @@
 use std::{
-	collections::HashMap,
+	collections::{BTreeSet, HashMap},
 	future::Future,
 	sync::{
 		Arc,
 		atomic::{AtomicUsize, Ordering},
 	},
+	time::Duration,
 };
 
 use crate::{Error, Produce, TrackConsumer, TrackProducer};
 use tokio::sync::watch;
 use web_async::Lock;
 
-use super::Track;
+use super::{Metadata, Track};
 
 struct State {
 	// When explicitly publishing, we hold a reference to the consumer.
@@ -33,7 +34,12 @@ pub struct Broadcast {
 
 impl Broadcast {
 	pub fn produce() -> Produce<BroadcastProducer, BroadcastConsumer> {
-		let producer = BroadcastProducer::new();
+		Self::produce_with_metadata(Metadata::default())
+	}
+
+	/// Produce a broadcast with metadata that is sent along with each announcement.
+	pub fn produce_with_metadata(metadata: Metadata) -> Produce<BroadcastProducer, BroadcastConsumer> {
+		let producer = BroadcastProducer::with_metadata(metadata);
 		let consumer = producer.consume();
 		Produce { producer, consumer }
 	}
@@ -48,6 +54,14 @@ pub struct BroadcastProducer {
 		async_channel::Receiver<TrackProducer>,
 	),
 	cloned: Arc<AtomicUsize>,
+
+	// The names of the tracks announced to consumers via [BroadcastConsumer::tracks].
+	tracks: watch::Sender<BTreeSet<String>>,
+
+	// Set when a consumer has asked for the list of tracks.
+	discover: watch::Sender<bool>,
+
+	metadata: Arc<Metadata>,
 }
 
 impl Default for BroadcastProducer {
@@ -58,6 +72,10 @@ impl Default for BroadcastProducer {
 
 impl BroadcastProducer {
 	fn new() -> Self {
+		Self::with_metadata(Metadata::default())
+	}
+
+	fn with_metadata(metadata: Metadata) -> Self {
 		Self {
 			state: Lock::new(State {
 				published: HashMap::new(),
@@ -66,9 +84,17 @@ impl BroadcastProducer {
 			closed: Default::default(),
 			requested: async_channel::unbounded(),
 			cloned: Default::default(),
+			tracks: Default::default(),
+			discover: Default::default(),
+			metadata: Arc::new(metadata),
 		}
 	}
 
+	/// The metadata provided when the broadcast was created.
+	pub fn metadata(&self) -> &Metadata {
+		&self.metadata
+	}
+
 	/// Return the next requested track.
 	pub async fn requested_track(&mut self) -> Option<TrackProducer> {
 		self.requested.1.recv().await.ok()
@@ -82,18 +108,53 @@ impl BroadcastProducer {
 	}
 
 	/// Insert a track into the lookup, returning true if it was unique.
+	///
+	/// The track is also announced to any [BroadcastTracks] consumers.
 	pub fn insert_track(&mut self, track: TrackConsumer) -> bool {
 		let mut state = self.state.lock();
 		let unique = state.published.insert(track.info.name.clone(), track.clone()).is_none();
 		let removed = state.requested.remove(&track.info.name).is_some();
+		drop(state);
+
+		self.announce_track(&track.info.name);
 
 		unique && !removed
 	}
 
 	/// Remove a track from the lookup.
+	///
+	/// The track is also unannounced to any [BroadcastTracks] consumers.
 	pub fn remove_track(&mut self, name: &str) -> bool {
 		let mut state = self.state.lock();
-		state.published.remove(name).is_some() || state.requested.remove(name).is_some()
+		let removed = state.published.remove(name).is_some() || state.requested.remove(name).is_some();
+		drop(state);
+
+		self.unannounce_track(name);
+
+		removed
+	}
+
+	/// Announce a track to any [BroadcastTracks] consumers, without inserting it into the lookup.
+	///
+	/// This is useful for tracks that are produced on demand via [Self::requested_track].
+	/// Returns true if the track was not already announced.
+	pub fn announce_track(&mut self, name: &str) -> bool {
+		self.tracks.send_if_modified(|tracks| tracks.insert(name.to_string()))
+	}
+
+	/// Stop announcing a track to any [BroadcastTracks] consumers, returning true if it was announced.
+	pub fn unannounce_track(&mut self, name: &str) -> bool {
+		self.tracks.send_if_modified(|tracks| tracks.remove(name))
+	}
+
+	/// Block until a consumer has asked for the list of tracks via [BroadcastConsumer::tracks].
+	///
+	/// This is used to avoid the cost of track discovery unless somebody actually wants it.
+	pub fn tracks_requested(&self) -> impl Future<Output = ()> + use<> {
+		let mut discover = self.discover.subscribe();
+		async move {
+			discover.wait_for(|discover| *discover).await.ok();
+		}
 	}
 
 	pub fn consume(&self) -> BroadcastConsumer {
@@ -101,11 +162,35 @@ impl BroadcastProducer {
 			state: self.state.clone(),
 			closed: self.closed.subscribe(),
 			requested: self.requested.0.clone(),
+			tracks: self.tracks.subscribe(),
+			discover: self.discover.clone(),
+			metadata: self.metadata.clone(),
 		}
 	}
 
+	/// Close the broadcast, aborting any requested tracks with [Error::Closed].
+	///
+	/// Published tracks are owned by their [TrackProducer] and are left untouched.
 	pub fn close(&mut self) {
 		self.closed.send_modify(|closed| *closed = true);
+		self.abort_requested();
+	}
+
+	// Reject any new requests and abort the existing ones, as nobody will serve them.
+	fn abort_requested(&mut self) {
+		// Close the sender so consumers can't send any more requests.
+		self.requested.0.close();
+
+		// Drain any remaining requests.
+		while let Ok(producer) = self.requested.1.try_recv() {
+			producer.abort(Error::Closed);
+		}
+
+		// Abort the requests that are already being served.
+		let requested = std::mem::take(&mut self.state.lock().requested);
+		for producer in requested.into_values() {
+			producer.abort(Error::Closed);
+		}
 	}
 
 	/// Block until there are no more consumers.
@@ -129,6 +214,9 @@ impl Clone for BroadcastProducer {
 			closed: self.closed.clone(),
 			requested: self.requested.clone(),
 			cloned: self.cloned.clone(),
+			tracks: self.tracks.clone(),
+			discover: self.discover.clone(),
+			metadata: self.metadata.clone(),
 		}
 	}
 }
@@ -140,20 +228,18 @@ impl Drop for BroadcastProducer {
 		}
 
 		// Cleanup any lingering state when the last producer is dropped.
-
-		// Close the sender so consumers can't send any more requests.
-		self.requested.0.close();
-
-		// Drain any remaining requests.
-		while let Ok(producer) = self.requested.1.try_recv() {
-			producer.abort(Error::Cancel);
-		}
-
-		let mut state = self.state.lock();
+		self.abort_requested();
 
 		// Cleanup any published tracks.
+		let mut state = self.state.lock();
 		state.published.clear();
-		state.requested.clear();
+
+		// Unannounce any tracks before the channel is closed.
+		self.tracks.send_if_modified(|tracks| {
+			let modified = !tracks.is_empty();
+			tracks.clear();
+			modified
+		});
 	}
 }
 
@@ -188,26 +274,41 @@ pub struct BroadcastConsumer {
 	state: Lock<State>,
 	closed: watch::Receiver<bool>,
 	requested: async_channel::Sender<TrackProducer>,
+	tracks: watch::Receiver<BTreeSet<String>>,
+	discover: watch::Sender<bool>,
+	metadata: Arc<Metadata>,
 }
 
 impl BroadcastConsumer {
+	/// The metadata provided when the broadcast was created, also sent with each announcement.
+	pub fn metadata(&self) -> &Metadata {
+		&self.metadata
+	}
+
 	pub fn subscribe_track(&self, track: &Track) -> TrackConsumer {
+		self.subscribe_track_with_timeout(track, None)
+	}
+
+	/// Subscribe to a track, expiring groups after the given timeout, see [TrackConsumer::with_timeout].
+	///
+	/// The timeout only applies to the returned consumer, not to other subscribers of the same track.
+	/// Only the publisher can expire groups for everybody, see [TrackProducer::with_timeout].
+	pub fn subscribe_track_with_timeout(&self, track: &Track, timeout: Option<Duration>) -> TrackConsumer {
 		let mut state = self.state.lock();
 
 		// Return any explictly published track.
 		if let Some(consumer) = state.published.get(&track.name).cloned() {
-			return consumer;
+			return consumer.with_timeout(timeout);
 		}
 
 		// Return any requested tracks.
 		if let Some(producer) = state.requested.get(&track.name) {
-			return producer.consume();
+			return producer.consume().with_timeout(timeout);
 		}
 
 		// Otherwise we have never seen this track before and need to create a new producer.
-		let track = track.clone().produce();
-		let producer = track.producer;
-		let consumer = track.consumer;
+		let producer = TrackProducer::from(track.clone());
+		let consumer = producer.consume().with_timeout(timeout);
 
 		// Insert the producer into the lookup so we will deduplicate requests.
 		// This is not a subscriber so it doesn't count towards "used" subscribers.
@@ -216,7 +317,7 @@ impl BroadcastConsumer {
 			Err(_) => {
 				// If the BroadcastProducer is closed, immediately close the track.
 				// This is a bit more ergonomic than returning None.
-				producer.abort(Error::Cancel);
+				producer.abort(Error::Closed);
 				return consumer;
 			}
 		}
@@ -242,6 +343,21 @@ impl BroadcastConsumer {
 		}
 	}
 
+	/// Discover the tracks announced by the producer.
+	///
+	/// This is optional and not every producer announces its tracks, especially older moq-lite peers.
+	/// Tracks can still be subscribed to by name via [Self::subscribe_track] even if they're never announced.
+	pub fn tracks(&self) -> BroadcastTracks {
+		// Signal the producer that somebody is interested, so remote broadcasts can start discovery.
+		self.discover
+			.send_if_modified(|discover| !std::mem::replace(discover, true));
+
+		BroadcastTracks {
+			tracks: self.tracks.clone(),
+			active: BTreeSet::new(),
+		}
+	}
+
 	/// Check if this is the exact same instance of a broadcast.
 	///
 	/// Duplicate names are allowed in the case of resumption.
@@ -250,6 +366,46 @@ impl BroadcastConsumer {
 	}
 }
 
+/// Receive track announcements for a broadcast, created via [BroadcastConsumer::tracks].
+///
+/// Each track name is first returned as active, and then as ended when it's removed.
+pub struct BroadcastTracks {
+	tracks: watch::Receiver<BTreeSet<String>>,
+
+	// The tracks we've returned as active so far.
+	active: BTreeSet<String>,
+}
+
+impl BroadcastTracks {
+	/// Returns the next track name and whether it's active (true) or ended (false).
+	///
+	/// Returns None when the producer is dropped, after all tracks have been unannounced.
+	pub async fn announced(&mut self) -> Option<(String, bool)> {
+		loop {
+			if let Some(announced) = self.try_announced() {
+				return Some(announced);
+			}
+
+			self.tracks.changed().await.ok()?;
+		}
+	}
+
+	/// Returns the next track name and whether it's active, without blocking.
+	pub fn try_announced(&mut self) -> Option<(String, bool)> {
+		let tracks = self.tracks.borrow_and_update();
+
+		// Report removals before additions.
+		if let Some(name) = self.active.iter().find(|name| !tracks.contains(*name)).cloned() {
+			self.active.remove(&name);
+			return Some((name, false));
+		}
+
+		let name = tracks.iter().find(|name| !self.active.contains(*name)).cloned()?;
+		self.active.insert(name.clone());
+		Some((name, true))
+	}
+}
+
 #[cfg(test)]
 impl BroadcastConsumer {
 	pub fn assert_not_closed(&self) {
@@ -343,19 +499,145 @@ mod test {
 		drop(producer);
 		consumer.assert_closed();
 
-		// The requested TrackProducer should have been dropped, so the track should be closed.
-		track2.assert_closed();
+		// The requested track can never be served, so it should be aborted.
+		let err = track2.closed().now_or_never().expect("should not block");
+		assert!(matches!(err, Err(Error::Closed)), "should be closed: {err:?}");
 
-		// But track1 is still open because we currently don't cascade the closed state.
+		// But track1 is still open because it's owned by the published TrackProducer.
 		track1c.assert_group();
 		track1c.assert_no_group();
 		track1c.assert_not_closed();
 
-		// TODO: We should probably cascade the closed state.
 		drop(track1.producer);
 		track1c.assert_closed();
 	}
 
+	#[tokio::test]
+	async fn close() {
+		let mut producer = BroadcastProducer::new();
+		let consumer = producer.consume();
+
+		let _published = producer.create_track(Track::new("published"));
+		let published = consumer.subscribe_track(&Track::new("published"));
+
+		// One request is being served, while the other is still queued.
+		let served = consumer.subscribe_track(&Track::new("served"));
+		let mut served_producer = producer.assert_request();
+		let queued = consumer.subscribe_track(&Track::new("queued"));
+
+		producer.close();
+		consumer.assert_closed();
+
+		for track in [&served, &queued] {
+			let err = track.closed().now_or_never().expect("should not block");
+			assert!(matches!(err, Err(Error::Closed)), "should be closed: {err:?}");
+		}
+
+		// The serving side doesn't know yet, but it can't revive the track.
+		served_producer.append_group();
+		served_producer.close();
+		served.assert_error();
+
+		// Published tracks are left untouched.
+		published.assert_not_closed();
+
+		// New requests are aborted immediately.
+		let late = consumer.subscribe_track(&Track::new("late"));
+		late.assert_error();
+		assert!(producer.requested_track().await.is_none());
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn timeout() {
+		let mut producer = BroadcastProducer::new();
+		let consumer = producer.consume();
+
+		let mut track = producer
+			.create_track(Track::new("track"))
+			.with_timeout(Some(Duration::from_secs(2)));
+		track.append_group();
+
+		tokio::time::advance(Duration::from_secs(1)).await;
+
+		// A subscriber can narrow the timeout, but not extend it.
+		let mut narrow = consumer.subscribe_track_with_timeout(&Track::new("track"), Some(Duration::from_millis(500)));
+		assert_eq!(narrow.timeout(), Some(Duration::from_millis(500)));
+		narrow.assert_no_group();
+
+		let mut wide = consumer.subscribe_track_with_timeout(&Track::new("track"), Some(Duration::from_secs(10)));
+		assert_eq!(wide.timeout(), Some(Duration::from_secs(2)));
+		wide.assert_group();
+
+		// Once the group expires, new consumers skip it.
+		tokio::time::advance(Duration::from_secs(1)).await;
+		let mut late = consumer.subscribe_track(&Track::new("track"));
+		late.assert_no_group();
+
+		// But newer groups are still delivered.
+		track.append_group();
+		assert_eq!(late.assert_group().info.sequence, 1);
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn timeout_shared() {
+		let mut producer = BroadcastProducer::new();
+		let consumer = producer.consume();
+
+		// Both subscribers share the same requested track, each with its own timeout.
+		let mut narrow = consumer.subscribe_track_with_timeout(&Track::new("track"), Some(Duration::from_millis(500)));
+		let mut wide = consumer.subscribe_track_with_timeout(&Track::new("track"), Some(Duration::from_secs(2)));
+		let mut plain = consumer.subscribe_track(&Track::new("track"));
+
+		// The subscriber's timeout is not imposed on the publisher, or sent upstream by a relay.
+		let mut track = producer.requested_track().await.expect("no track requested");
+		assert_eq!(track.timeout(), None);
+		assert_eq!(narrow.timeout(), Some(Duration::from_millis(500)));
+		assert_eq!(wide.timeout(), Some(Duration::from_secs(2)));
+		assert_eq!(plain.timeout(), None);
+
+		track.append_group();
+		tokio::time::advance(Duration::from_secs(1)).await;
+
+		// Only the subscriber with the shorter timeout skips the group.
+		narrow.assert_no_group();
+		wide.assert_group();
+		plain.assert_group();
+	}
+
+	#[tokio::test]
+	async fn tracks() {
+		let mut producer = BroadcastProducer::new();
+		let _track1 = producer.create_track(Track::new("track1"));
+
+		// Nobody has asked for the tracks yet.
+		assert!(producer.tracks_requested().now_or_never().is_none());
+
+		let consumer = producer.consume();
+		let mut tracks = consumer.tracks();
+		assert!(producer.tracks_requested().now_or_never().is_some());
+
+		assert_eq!(tracks.try_announced(), Some(("track1".to_string(), true)));
+		assert_eq!(tracks.try_announced(), None);
+
+		// Tracks can be announced without being inserted.
+		assert!(producer.announce_track("track2"));
+		assert!(!producer.announce_track("track2"));
+		assert_eq!(tracks.announced().await, Some(("track2".to_string(), true)));
+
+		assert!(producer.remove_track("track1"));
+		assert_eq!(tracks.announced().await, Some(("track1".to_string(), false)));
+
+		// A new consumer gets the current state.
+		let mut tracks2 = consumer.tracks();
+		assert_eq!(tracks2.try_announced(), Some(("track2".to_string(), true)));
+		assert_eq!(tracks2.try_announced(), None);
+
+		// Dropping the producer unannounces everything.
+		drop(producer);
+		assert_eq!(tracks.announced().await, Some(("track2".to_string(), false)));
+		assert_eq!(tracks.announced().await, None);
+	}
+
 	#[tokio::test]
 	async fn select() {
 		let mut producer = BroadcastProducer::new();
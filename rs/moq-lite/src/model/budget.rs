@@ -0,0 +1,230 @@
+use std::sync::{
+	Arc,
+	atomic::{AtomicU64, Ordering},
+};
+
+use crate::{Error, Result};
+
+/// A limit on the number of bytes buffered, shared by every frame charged against it.
+///
+/// Budgets form a hierarchy via [Budget::child]: a reservation is charged against the budget and all of its parents.
+/// Bytes are released when the [crate::FrameConsumer]s holding them are dropped.
+#[derive(Clone, Debug)]
+pub struct Budget {
+	state: Arc<BudgetState>,
+}
+
+#[derive(Debug)]
+struct BudgetState {
+	// Shared between sibling budgets created via [Budget::child_shared].
+	limit: Arc<AtomicU64>,
+	used: AtomicU64,
+	parent: Option<Budget>,
+}
+
+impl Budget {
+	/// Create a budget that allows up to `limit` bytes to be buffered.
+	pub fn new(limit: u64) -> Self {
+		Self::with_parent(limit, None)
+	}
+
+	/// Create a budget that never runs out, useful only as a parent for other budgets.
+	pub fn unlimited() -> Self {
+		Self::new(u64::MAX)
+	}
+
+	/// Create a child budget, which is limited by both `limit` and this budget.
+	pub fn child(&self, limit: u64) -> Self {
+		Self::with_parent(limit, Some(self.clone()))
+	}
+
+	// Create a child budget whose limit is shared with other budgets, so changing it applies to all of them.
+	pub(crate) fn child_shared(&self, limit: Arc<AtomicU64>) -> Self {
+		Self {
+			state: Arc::new(BudgetState {
+				limit,
+				used: AtomicU64::new(0),
+				parent: Some(self.clone()),
+			}),
+		}
+	}
+
+	fn with_parent(limit: u64, parent: Option<Budget>) -> Self {
+		Self {
+			state: Arc::new(BudgetState {
+				limit: Arc::new(AtomicU64::new(limit)),
+				used: AtomicU64::new(0),
+				parent,
+			}),
+		}
+	}
+
+	/// The maximum number of bytes that can be buffered.
+	pub fn limit(&self) -> u64 {
+		self.state.limit.load(Ordering::Relaxed)
+	}
+
+	/// Change the limit, which only applies to future reservations.
+	pub fn set_limit(&self, limit: u64) {
+		self.state.limit.store(limit, Ordering::Relaxed);
+	}
+
+	/// The number of bytes currently buffered.
+	pub fn used(&self) -> u64 {
+		self.state.used.load(Ordering::Relaxed)
+	}
+
+	/// Charge `size` bytes against this budget and all of its parents.
+	///
+	/// Returns [Error::TooLarge] without charging anything if any budget would be exceeded.
+	pub(crate) fn reserve(&self, size: u64) -> Result<Reservation> {
+		let mut current = Some(self);
+		while let Some(budget) = current {
+			if let Err(err) = budget.charge(size) {
+				// Roll back the budgets we already charged.
+				let mut rollback = Some(self);
+				while let Some(charged) = rollback.filter(|charged| !Arc::ptr_eq(&charged.state, &budget.state)) {
+					charged.release(size);
+					rollback = charged.state.parent.as_ref();
+				}
+
+				return Err(err);
+			}
+
+			current = budget.state.parent.as_ref();
+		}
+
+		Ok(Reservation {
+			budget: self.clone(),
+			size,
+		})
+	}
+
+	fn charge(&self, size: u64) -> Result<()> {
+		let limit = self.limit();
+		self.state
+			.used
+			.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+				used.checked_add(size).filter(|total| *total <= limit)
+			})
+			.map(|_| ())
+			.map_err(|_| Error::TooLarge)
+	}
+
+	fn release(&self, size: u64) {
+		self.state.used.fetch_sub(size, Ordering::Relaxed);
+	}
+}
+
+impl Default for Budget {
+	fn default() -> Self {
+		Self::unlimited()
+	}
+}
+
+/// Bytes charged against a [Budget], released when dropped.
+#[derive(Debug)]
+pub(crate) struct Reservation {
+	budget: Budget,
+	size: u64,
+}
+
+impl Reservation {
+	/// Charge an additional `size` bytes, leaving the reservation unchanged on error.
+	pub fn grow(&mut self, size: u64) -> Result<()> {
+		let extra = self.budget.reserve(size)?;
+		self.size += size;
+
+		// The bytes are now owned by this reservation instead.
+		std::mem::forget(extra);
+
+		Ok(())
+	}
+}
+
+impl Drop for Reservation {
+	fn drop(&mut self) {
+		let mut current = Some(&self.budget);
+		while let Some(budget) = current {
+			budget.release(self.size);
+			current = budget.state.parent.as_ref();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reserve() {
+		let budget = Budget::new(10);
+
+		let a = budget.reserve(6).unwrap();
+		assert_eq!(budget.used(), 6);
+
+		assert!(matches!(budget.reserve(5), Err(Error::TooLarge)));
+		assert_eq!(budget.used(), 6);
+
+		let b = budget.reserve(4).unwrap();
+		assert_eq!(budget.used(), 10);
+
+		drop(a);
+		drop(b);
+		assert_eq!(budget.used(), 0);
+	}
+
+	#[test]
+	fn hierarchy() {
+		let session = Budget::new(10);
+		let track = session.child(8);
+		let group = track.child(u64::MAX);
+
+		let mut reservation = group.reserve(5).unwrap();
+		assert_eq!((session.used(), track.used(), group.used()), (5, 5, 5));
+
+		// The track limit is hit first, so nothing should be charged.
+		assert!(matches!(reservation.grow(4), Err(Error::TooLarge)));
+		assert_eq!((session.used(), track.used(), group.used()), (5, 5, 5));
+
+		// A sibling track is still limited by the session.
+		let other = session.child(u64::MAX);
+		assert!(matches!(other.reserve(6), Err(Error::TooLarge)));
+		let _other = other.reserve(5).unwrap();
+		assert_eq!(session.used(), 10);
+
+		reservation.grow(3).ok();
+		assert_eq!(track.used(), 5);
+
+		drop(reservation);
+		assert_eq!((session.used(), track.used(), group.used()), (5, 0, 0));
+	}
+
+	#[test]
+	fn child_shared() {
+		let session = Budget::unlimited();
+		let limit = Arc::new(AtomicU64::new(u64::MAX));
+		let a = session.child_shared(limit.clone());
+		let b = session.child_shared(limit.clone());
+
+		let _a = a.reserve(10).unwrap();
+
+		// Changing the shared limit applies to existing budgets.
+		limit.store(10, Ordering::Relaxed);
+		assert!(matches!(a.reserve(1), Err(Error::TooLarge)));
+		b.reserve(10).unwrap();
+		assert!(matches!(b.reserve(11), Err(Error::TooLarge)));
+	}
+
+	#[test]
+	fn set_limit() {
+		let budget = Budget::unlimited();
+		let _a = budget.reserve(100).unwrap();
+
+		budget.set_limit(50);
+		assert!(matches!(budget.reserve(1), Err(Error::TooLarge)));
+
+		budget.set_limit(101);
+		budget.reserve(1).unwrap();
+	}
+}
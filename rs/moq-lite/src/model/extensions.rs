@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+
+use crate::coding::{Decode, DecodeError, Encode};
+
+// Extensions are meant to be small, so ignore anything excessive.
+const MAX_EXTENSIONS: usize = 64;
+
+/// Typed extension headers attached to a [crate::Frame], forwarded untouched by relays.
+///
+/// Like the IETF object extension headers, the type of each value depends on its ID:
+/// even IDs contain a varint while odd IDs contain bytes.
+/// This is useful for capture timestamps, encryption key IDs, etc. without changing the payload format.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Extensions {
+	vars: BTreeMap<u64, u64>,
+	bytes: BTreeMap<u64, Bytes>,
+}
+
+impl Extensions {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn get_varint(&self, id: u64) -> Option<u64> {
+		self.vars.get(&id).copied()
+	}
+
+	/// Set a varint extension, returning the previous value if any.
+	///
+	/// Panics if the ID is odd.
+	pub fn set_varint(&mut self, id: u64, value: u64) -> Option<u64> {
+		assert!(id.is_multiple_of(2), "varint extensions must use an even ID");
+		self.vars.insert(id, value)
+	}
+
+	pub fn get_bytes(&self, id: u64) -> Option<&Bytes> {
+		self.bytes.get(&id)
+	}
+
+	/// Set a bytes extension, returning the previous value if any.
+	///
+	/// Panics if the ID is even.
+	pub fn set_bytes(&mut self, id: u64, value: impl Into<Bytes>) -> Option<Bytes> {
+		assert!(!id.is_multiple_of(2), "bytes extensions must use an odd ID");
+		self.bytes.insert(id, value.into())
+	}
+
+	/// Remove an extension of either type, returning true if it was present.
+	pub fn remove(&mut self, id: u64) -> bool {
+		self.vars.remove(&id).is_some() || self.bytes.remove(&id).is_some()
+	}
+
+	pub fn len(&self) -> usize {
+		self.vars.len() + self.bytes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.vars.is_empty() && self.bytes.is_empty()
+	}
+
+	// Encode each key/value pair without any length prefix.
+	fn encode_pairs<W: bytes::BufMut, V: Clone>(&self, w: &mut W, version: V) {
+		for (id, value) in &self.vars {
+			id.encode(w, version.clone());
+			value.encode(w, version.clone());
+		}
+
+		for (id, value) in &self.bytes {
+			id.encode(w, version.clone());
+			value.len().encode(w, version.clone());
+			w.put_slice(value);
+		}
+	}
+
+	// IETF peers may repeat an ID, so only the first value is kept.
+	// Anything beyond MAX_EXTENSIONS is skipped rather than rejected; the size is bounded anyway.
+	fn decode_pairs<V: Clone>(buf: &mut Bytes, version: V) -> Result<Self, DecodeError> {
+		let mut extensions = Self::default();
+
+		while !buf.is_empty() {
+			let full = extensions.len() >= MAX_EXTENSIONS;
+
+			let id = u64::decode(buf, version.clone())?;
+			if id.is_multiple_of(2) {
+				let value = u64::decode(buf, version.clone())?;
+				if !full {
+					extensions.vars.entry(id).or_insert(value);
+				}
+			} else {
+				let size = usize::decode(buf, version.clone())?;
+				if buf.len() < size {
+					return Err(DecodeError::Short);
+				}
+				let value = buf.split_to(size);
+				if !full {
+					extensions.bytes.entry(id).or_insert(value);
+				}
+			}
+		}
+
+		Ok(extensions)
+	}
+}
+
+/// Encoded as the total size in bytes, followed by each key/value pair.
+impl<V: Clone> Encode<V> for Extensions {
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: V) {
+		let mut buf = Vec::new();
+		self.encode_pairs(&mut buf, version.clone());
+
+		buf.len().encode(w, version);
+		w.put_slice(&buf);
+	}
+}
+
+impl<V: Clone> Decode<V> for Extensions {
+	fn decode<R: bytes::Buf>(r: &mut R, version: V) -> Result<Self, DecodeError> {
+		let size = usize::decode(r, version.clone())?;
+		if r.remaining() < size {
+			return Err(DecodeError::Short);
+		}
+
+		let mut buf = r.copy_to_bytes(size);
+
+		// The size is known upfront, so running out of data means the extensions are malformed.
+		Self::decode_pairs(&mut buf, version).map_err(|err| match err {
+			DecodeError::Short => DecodeError::InvalidValue,
+			err => err,
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn round_trip() {
+		let mut extensions = Extensions::new();
+		extensions.set_varint(2, 1234);
+		extensions.set_bytes(3, "key-id");
+
+		let mut buf = Vec::new();
+		extensions.encode(&mut buf, ());
+
+		let decoded = Extensions::decode(&mut buf.as_slice(), ()).unwrap();
+		assert_eq!(decoded, extensions);
+		assert_eq!(decoded.get_varint(2), Some(1234));
+		assert_eq!(decoded.get_bytes(3).unwrap().as_ref(), b"key-id");
+	}
+
+	#[test]
+	fn empty() {
+		let mut buf = Vec::new();
+		Extensions::new().encode(&mut buf, ());
+		assert_eq!(buf, [0]);
+	}
+
+	#[test]
+	fn duplicate() {
+		let mut pairs = Vec::new();
+		for value in [1u64, 2] {
+			2u64.encode(&mut pairs, ());
+			value.encode(&mut pairs, ());
+		}
+
+		let mut buf = Vec::new();
+		pairs.len().encode(&mut buf, ());
+		buf.extend_from_slice(&pairs);
+
+		// The first value wins.
+		let decoded = Extensions::decode(&mut buf.as_slice(), ()).unwrap();
+		assert_eq!(decoded.len(), 1);
+		assert_eq!(decoded.get_varint(2), Some(1));
+	}
+
+	#[test]
+	fn too_many() {
+		let mut pairs = Vec::new();
+		for id in 0..(MAX_EXTENSIONS as u64 + 2) {
+			(id * 2).encode(&mut pairs, ());
+			id.encode(&mut pairs, ());
+		}
+
+		let mut buf = Vec::new();
+		pairs.len().encode(&mut buf, ());
+		buf.extend_from_slice(&pairs);
+		buf.push(0xff);
+
+		// The excess is skipped without consuming anything past the extensions.
+		let mut r = buf.as_slice();
+		let decoded = Extensions::decode(&mut r, ()).unwrap();
+		assert_eq!(decoded.len(), MAX_EXTENSIONS);
+		assert_eq!(decoded.get_varint(MAX_EXTENSIONS as u64 * 2), None);
+		assert_eq!(r, [0xff]);
+	}
+
+	#[test]
+	fn short() {
+		let mut buf = Vec::new();
+		let mut extensions = Extensions::new();
+		extensions.set_bytes(1, "hello");
+		extensions.encode(&mut buf, ());
+
+		buf.pop();
+		assert!(matches!(
+			Extensions::decode(&mut buf.as_slice(), ()),
+			Err(DecodeError::Short)
+		));
+	}
+}
@@ -3,16 +3,31 @@ use std::future::Future;
 use bytes::{Bytes, BytesMut};
 use tokio::sync::watch;
 
-use crate::{Error, Produce, Result};
+use crate::{Error, Extensions, Produce, Result};
 
-/// A chunk of data with an upfront size.
-#[derive(Clone, Debug)]
+use super::{Budget, Evictor, Reservation};
+
+/// A chunk of data, usually with an upfront size.
+#[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Frame {
-	pub size: u64,
+	/// The size of the frame, or None if it's only known once the frame is closed.
+	///
+	/// A consumer fills in the size once it has read the whole frame.
+	/// Frames without a size can be forwarded before they're fully produced.
+	/// However older moq-lite versions and IETF peers require a size, so the frame is buffered instead.
+	pub size: Option<u64>,
+
+	/// Optional extension headers, sent before the payload.
+	pub extensions: Extensions,
 }
 
 impl Frame {
+	/// A frame with an unknown size, determined when [FrameProducer::close] is called.
+	pub fn streaming() -> Self {
+		Self::default()
+	}
+
 	/// Create a new producer and consumer for the frame.
 	pub fn produce(self) -> Produce<FrameProducer, FrameConsumer> {
 		let producer = FrameProducer::new(self);
@@ -23,25 +38,37 @@ impl Frame {
 
 impl From<usize> for Frame {
 	fn from(size: usize) -> Self {
-		Self { size: size as u64 }
+		Self {
+			size: Some(size as u64),
+			..Default::default()
+		}
 	}
 }
 
 impl From<u64> for Frame {
 	fn from(size: u64) -> Self {
-		Self { size }
+		Self {
+			size: Some(size),
+			..Default::default()
+		}
 	}
 }
 
 impl From<u32> for Frame {
 	fn from(size: u32) -> Self {
-		Self { size: size as u64 }
+		Self {
+			size: Some(size as u64),
+			..Default::default()
+		}
 	}
 }
 
 impl From<u16> for Frame {
 	fn from(size: u16) -> Self {
-		Self { size: size as u64 }
+		Self {
+			size: Some(size as u64),
+			..Default::default()
+		}
 	}
 }
 
@@ -52,6 +79,12 @@ struct FrameState {
 
 	// Set when the writer or all readers are dropped.
 	closed: Option<Result<()>>,
+
+	// The bytes charged against the budget, released when the state is dropped.
+	reserved: Option<Reservation>,
+
+	// The final size of the frame, set when it's closed.
+	size: Option<u64>,
 }
 
 /// Used to write a frame's worth of data in chunks.
@@ -64,7 +97,13 @@ pub struct FrameProducer {
 	state: watch::Sender<FrameState>,
 
 	// Sanity check to ensure we don't write more than the frame size.
-	written: usize,
+	written: u64,
+
+	// Each chunk is charged against this budget, if any.
+	budget: Option<Budget>,
+
+	// Used to make room in the budget by evicting older frames in the group.
+	evictor: Option<Evictor>,
 }
 
 impl FrameProducer {
@@ -73,23 +112,94 @@ impl FrameProducer {
 			info,
 			state: Default::default(),
 			written: 0,
+			budget: None,
+			evictor: None,
 		}
 	}
 
+	/// Charge each chunk against the budget, see [Self::try_write_chunk].
+	pub fn with_budget(mut self, budget: Budget) -> Self {
+		self.budget = Some(budget);
+		self
+	}
+
+	pub(super) fn with_evictor(mut self, evictor: Evictor) -> Self {
+		self.evictor = Some(evictor);
+		self
+	}
+
+	/// Write a chunk of the frame.
+	///
+	/// If the budget is exceeded, the frame is aborted with [Error::TooLarge] and further writes are ignored.
+	/// Use [Self::try_write_chunk] to find out when that happens.
 	pub fn write_chunk<B: Into<Bytes>>(&mut self, chunk: B) {
+		// Consumers see the error, which is the best we can do without a Result.
+		self.try_write_chunk(chunk).ok();
+	}
+
+	/// Write a chunk of the frame, aborting the frame with [Error::TooLarge] if the budget is exceeded.
+	///
+	/// Returns the error the frame was aborted with, if any.
+	pub fn try_write_chunk<B: Into<Bytes>>(&mut self, chunk: B) -> Result<()> {
 		let chunk = chunk.into();
-		self.written += chunk.len();
-		assert!(self.written <= self.info.size as usize);
+		let size = chunk.len() as u64;
+		assert!(self.info.size.is_none_or(|max| self.written + size <= max));
+
+		let mut result = Ok(());
+		self.state.send_if_modified(|state| {
+			match &state.closed {
+				None => {}
+				Some(Ok(())) => panic!("frame is closed"),
+				Some(Err(err)) => {
+					result = Err(err.clone());
+					return false;
+				}
+			}
+
+			if let Some(budget) = &self.budget {
+				loop {
+					result = match &mut state.reserved {
+						Some(reserved) => reserved.grow(size),
+						None => budget.reserve(size).map(|reserved| state.reserved = Some(reserved)),
+					};
+
+					// Try again if evicting older frames made some room.
+					match &self.evictor {
+						Some(evictor) if result.is_err() && evictor.evict() => continue,
+						_ => break,
+					}
+				}
+			}
 
-		self.state.send_modify(|state| {
-			assert!(state.closed.is_none());
-			state.chunks.push(chunk);
+			match &result {
+				Ok(()) => state.chunks.push(chunk),
+				Err(err) => state.closed = Some(Err(err.clone())),
+			}
+
+			true
 		});
+
+		if result.is_ok() {
+			self.written += size;
+		}
+
+		result
 	}
 
+	/// Finish the frame, which must match the upfront size if provided.
+	///
+	/// This does nothing if the frame was aborted, such as when the budget was exceeded.
 	pub fn close(self) {
-		assert!(self.written == self.info.size as usize);
-		self.state.send_modify(|state| state.closed = Some(Ok(())));
+		self.state.send_if_modified(|state| {
+			if state.closed.is_some() {
+				return false;
+			}
+
+			assert!(self.info.size.is_none_or(|size| self.written == size));
+			state.size = Some(self.written);
+			state.closed = Some(Ok(()));
+			true
+		});
 	}
 
 	pub fn abort(self, err: Error) {
@@ -136,6 +246,8 @@ pub struct FrameConsumer {
 
 impl FrameConsumer {
 	/// Return the next chunk.
+	///
+	/// Once the end of a streaming frame is reached, [Frame::size] is set to the final size.
 	pub async fn read_chunk(&mut self) -> Result<Option<Bytes>> {
 		loop {
 			{
@@ -147,7 +259,10 @@ impl FrameConsumer {
 				}
 
 				match &state.closed {
-					Some(Ok(_)) => return Ok(None),
+					Some(Ok(_)) => {
+						self.info.size = self.info.size.or(state.size);
+						return Ok(None);
+					}
 					Some(Err(err)) => return Err(err.clone()),
 					_ => {}
 				}
@@ -169,6 +284,7 @@ impl FrameConsumer {
 		if let Some(Err(err)) = &state.closed {
 			return Err(err.clone());
 		}
+		self.info.size = self.info.size.or(state.size);
 
 		// Get all of the remaining chunks.
 		let chunks = state.chunks[self.index..].to_vec();
@@ -187,6 +303,7 @@ impl FrameConsumer {
 		if let Some(Err(err)) = &state.closed {
 			return Err(err.clone());
 		}
+		self.info.size = self.info.size.or(state.size);
 
 		// Get all of the remaining chunks.
 		let chunks = &state.chunks[self.index..];
@@ -7,14 +7,22 @@
 //! The reader can be cloned, in which case each reader receives a copy of each frame. (fanout)
 //!
 //! The stream is closed with [ServeError::MoqError] when all writers or readers are dropped.
-use std::future::Future;
+use std::{
+	collections::VecDeque,
+	future::Future,
+	sync::{
+		Arc, Weak,
+		atomic::{AtomicUsize, Ordering},
+	},
+};
 
 use bytes::Bytes;
-use tokio::sync::watch;
+use tokio::{sync::watch, time::Instant};
+use web_async::Lock;
 
 use crate::{Error, Produce, Result};
 
-use super::{Frame, FrameConsumer, FrameProducer};
+use super::{Budget, Frame, FrameConsumer, FrameProducer};
 
 /// A group contains a sequence number because they can arrive out of order.
 ///
@@ -65,13 +73,67 @@ impl From<u16> for Group {
 
 #[derive(Default)]
 struct GroupState {
-	// The frames that has been written thus far
-	frames: Vec<FrameConsumer>,
+	// The frames that has been written thus far, minus any evicted frames.
+	frames: VecDeque<FrameConsumer>,
+
+	// The number of frames evicted from the front, so they're no longer buffered.
+	evicted: usize,
 
 	// Whether the group is closed
 	closed: Option<Result<()>>,
 }
 
+impl GroupState {
+	// Evict the frames before the given index, returning true if any were evicted.
+	fn evict(&mut self, index: usize) -> bool {
+		let count = index.saturating_sub(self.evicted).min(self.frames.len());
+		self.frames.drain(..count);
+		self.evicted += count;
+		count > 0
+	}
+}
+
+// How far each active consumer has read, so a budgeted group can evict frames they have all moved past.
+#[derive(Clone, Default)]
+struct Cursors(Lock<Vec<Weak<AtomicUsize>>>);
+
+impl Cursors {
+	fn register(&self, index: usize) -> Arc<AtomicUsize> {
+		let cursor = Arc::new(AtomicUsize::new(index));
+		self.0.lock().push(Arc::downgrade(&cursor));
+		cursor
+	}
+
+	// The index of the oldest frame still needed, or None if nobody is reading.
+	fn min(&self) -> Option<usize> {
+		let mut cursors = self.0.lock();
+		cursors.retain(|cursor| cursor.strong_count() > 0);
+		cursors
+			.iter()
+			.filter_map(Weak::upgrade)
+			.map(|cursor| cursor.load(Ordering::Relaxed))
+			.min()
+	}
+}
+
+// Makes room in a group's budget by evicting the frames every active consumer has read.
+// Used by each frame in the group when a chunk doesn't fit in the budget.
+#[derive(Clone)]
+pub(super) struct Evictor {
+	state: watch::Sender<GroupState>,
+	cursors: Cursors,
+}
+
+impl Evictor {
+	// Returns true if any frames were evicted, in which case it's worth trying again.
+	pub fn evict(&self) -> bool {
+		match self.cursors.min() {
+			Some(index) => self.state.send_if_modified(|state| state.evict(index)),
+			None => false,
+		}
+	}
+}
+
 /// Create a group, frame-by-frame.
 #[derive(Clone)]
 pub struct GroupProducer {
@@ -80,6 +142,15 @@ pub struct GroupProducer {
 
 	// Immutable stream state.
 	pub info: Group,
+
+	// Frames created by this producer are charged against this budget, if any.
+	budget: Option<Budget>,
+
+	// Used to evict frames when there's a budget.
+	cursors: Cursors,
+
+	// Used to expire the group after the track's timeout.
+	created: Instant,
 }
 
 impl GroupProducer {
@@ -87,18 +158,30 @@ impl GroupProducer {
 		Self {
 			info,
 			state: Default::default(),
+			budget: None,
+			cursors: Default::default(),
+			created: Instant::now(),
 		}
 	}
 
+	/// Charge each frame created via [Self::create_frame] against the budget.
+	///
+	/// When a frame doesn't fit, frames that every active consumer has read are evicted to make room.
+	/// A consumer that hasn't read an evicted frame yet, such as one that starts reading later, gets [Error::TooLarge].
+	pub fn with_budget(mut self, budget: Budget) -> Self {
+		self.budget = Some(budget);
+		self
+	}
+
 	/// A helper method to write a frame from a single byte buffer.
 	///
 	/// If you want to write multiple chunks, use [Self::create_frame] to get a frame producer.
 	/// But an upfront size is required.
+	///
+	/// If the budget is exceeded, the frame is aborted with [Error::TooLarge], see [Self::with_budget].
 	pub fn write_frame<B: Into<Bytes>>(&mut self, frame: B) {
 		let data = frame.into();
-		let frame = Frame {
-			size: data.len() as u64,
-		};
+		let frame = Frame::from(data.len());
 		let mut frame = self.create_frame(frame);
 		frame.write_chunk(data);
 		frame.close();
@@ -108,14 +191,24 @@ impl GroupProducer {
 	pub fn create_frame(&mut self, info: Frame) -> FrameProducer {
 		let frame = Frame::produce(info);
 		self.append_frame(frame.consumer);
-		frame.producer
+
+		match &self.budget {
+			Some(budget) => {
+				let evictor = Evictor {
+					state: self.state.clone(),
+					cursors: self.cursors.clone(),
+				};
+				frame.producer.with_budget(budget.clone()).with_evictor(evictor)
+			}
+			None => frame.producer,
+		}
 	}
 
 	/// Append a frame to the group.
 	pub fn append_frame(&mut self, consumer: FrameConsumer) {
 		self.state.send_modify(|state| {
 			assert!(state.closed.is_none());
-			state.frames.push(consumer)
+			state.frames.push_back(consumer)
 		});
 	}
 
@@ -135,6 +228,9 @@ impl GroupProducer {
 			state: self.state.subscribe(),
 			index: 0,
 			active: None,
+			cursors: self.cursors.clone(),
+			cursor: None,
+			created: self.created,
 		}
 	}
 
@@ -153,7 +249,6 @@ impl From<Group> for GroupProducer {
 }
 
 /// Consume a group, frame-by-frame.
-#[derive(Clone)]
 pub struct GroupConsumer {
 	// Modify the stream state.
 	state: watch::Receiver<GroupState>,
@@ -167,9 +262,39 @@ pub struct GroupConsumer {
 
 	// Used to make read_frame cancel safe.
 	active: Option<FrameConsumer>,
+
+	// Our read position, registered once we start reading so frames we still need aren't evicted.
+	cursors: Cursors,
+	cursor: Option<Arc<AtomicUsize>>,
+
+	// When the group was created, or received from the network.
+	created: Instant,
+}
+
+impl Clone for GroupConsumer {
+	fn clone(&self) -> Self {
+		Self {
+			state: self.state.clone(),
+			info: self.info.clone(),
+			index: self.index,
+			active: self.active.clone(),
+			cursors: self.cursors.clone(),
+			// The clone registers its own position once it starts reading.
+			cursor: None,
+			created: self.created,
+		}
+	}
 }
 
 impl GroupConsumer {
+	/// Returns when the group was created, or received from the network.
+	///
+	/// The creation time isn't sent over the wire, so it resets at every relay hop.
+	/// A track's timeout is measured per hop rather than end-to-end.
+	pub(crate) fn created(&self) -> Instant {
+		self.created
+	}
+
 	/// Read the next frame.
 	pub async fn read_frame(&mut self) -> Result<Option<Bytes>> {
 		// In order to be cancel safe, we need to save the active frame.
@@ -200,8 +325,20 @@ impl GroupConsumer {
 			{
 				let state = self.state.borrow_and_update();
 
-				if let Some(frame) = state.frames.get(self.index).cloned() {
+				// Never skip frames, as the start of the group is often required to decode the rest.
+				if self.index < state.evicted {
+					return Err(Error::TooLarge);
+				}
+
+				if let Some(frame) = state.frames.get(self.index - state.evicted).cloned() {
 					self.index += 1;
+					drop(state);
+
+					match &self.cursor {
+						Some(cursor) => cursor.store(self.index, Ordering::Relaxed),
+						None => self.cursor = Some(self.cursors.register(self.index)),
+					}
+
 					return Ok(Some(frame));
 				}
 
@@ -218,3 +355,68 @@ impl GroupConsumer {
 		}
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use futures::FutureExt;
+
+	#[test]
+	fn budget_exceeded() {
+		let group = Group { sequence: 0 }.produce();
+		let mut producer = group.producer.with_budget(Budget::new(10));
+		let mut consumer = group.consumer;
+
+		producer.write_frame(Bytes::from_static(b"hello"));
+
+		// Nobody has read the first frame, so the second doesn't fit; this used to panic.
+		producer.write_frame(Bytes::from_static(b"world!"));
+
+		let frame = consumer.read_frame().now_or_never().unwrap().unwrap();
+		assert_eq!(frame, Some(Bytes::from_static(b"hello")));
+		assert!(matches!(
+			consumer.read_frame().now_or_never().unwrap(),
+			Err(Error::TooLarge)
+		));
+	}
+
+	#[test]
+	fn evict() {
+		let budget = Budget::new(10);
+		let group = Group { sequence: 0 }.produce();
+		let mut producer = group.producer.with_budget(budget.clone());
+		let mut consumer = group.consumer;
+		let mut slow = consumer.clone();
+
+		producer.write_frame(Bytes::from_static(b"hello"));
+		let frame = consumer.read_frame().now_or_never().unwrap().unwrap();
+		assert_eq!(frame, Some(Bytes::from_static(b"hello")));
+
+		// Nothing is evicted while the frames fit in the budget.
+		producer.write_frame(Bytes::from_static(b"world"));
+		assert_eq!(budget.used(), 10);
+
+		// Every active consumer has read the first frame, so it's evicted to make room.
+		// A consumer that hasn't started reading doesn't hold back eviction.
+		producer.write_frame(Bytes::from_static(b"!"));
+		assert_eq!(budget.used(), 6);
+
+		// But it's not allowed to skip the start of the group either.
+		assert!(matches!(
+			slow.read_frame().now_or_never().unwrap(),
+			Err(Error::TooLarge)
+		));
+
+		let frame = consumer.read_frame().now_or_never().unwrap().unwrap();
+		assert_eq!(frame, Some(Bytes::from_static(b"world")));
+		let frame = consumer.read_frame().now_or_never().unwrap().unwrap();
+		assert_eq!(frame, Some(Bytes::from_static(b"!")));
+
+		producer.write_frame(Bytes::from_static(b"abcd"));
+		assert_eq!(budget.used(), 10);
+
+		producer.write_frame(Bytes::from_static(b"xy"));
+		assert_eq!(budget.used(), 6, "only the frames the consumer read should be evicted");
+	}
+}
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use crate::{
+	Error,
+	coding::{Decode, DecodeError, Encode},
+};
+
+/// Small key/value pairs attached to a broadcast when it's announced.
+///
+/// This is useful for a title, start time, content type, etc. so a directory doesn't need to subscribe to each broadcast.
+/// Use [crate::Broadcast::produce_with_metadata] to attach metadata and [crate::BroadcastConsumer::metadata] to read it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "BTreeMap<String, String>"))]
+pub struct Metadata(BTreeMap<String, String>);
+
+impl Metadata {
+	/// Metadata is meant to be small, so peers refuse to decode more entries than this.
+	pub const MAX_ENTRIES: usize = 64;
+
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Set a value, returning the previous value if any.
+	///
+	/// Returns [Error::TooLarge] if this would exceed [Self::MAX_ENTRIES], as the peer would reject the announcement.
+	pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Result<Option<String>, Error> {
+		let key = key.into();
+		if self.0.len() >= Self::MAX_ENTRIES && !self.0.contains_key(&key) {
+			return Err(Error::TooLarge);
+		}
+
+		Ok(self.0.insert(key, value.into()))
+	}
+
+	/// Set a value, returning self for chaining.
+	///
+	/// Returns [Error::TooLarge] if this would exceed [Self::MAX_ENTRIES], see [Self::insert].
+	pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Result<Self, Error> {
+		self.insert(key, value)?;
+		Ok(self)
+	}
+
+	pub fn get(&self, key: &str) -> Option<&str> {
+		self.0.get(key).map(String::as_str)
+	}
+
+	pub fn remove(&mut self, key: &str) -> Option<String> {
+		self.0.remove(key)
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.0.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+	}
+
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+impl TryFrom<BTreeMap<String, String>> for Metadata {
+	type Error = DecodeError;
+
+	fn try_from(entries: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+		if entries.len() > Self::MAX_ENTRIES {
+			return Err(DecodeError::TooMany);
+		}
+
+		Ok(Self(entries))
+	}
+}
+
+impl<V: Clone> Decode<V> for Metadata {
+	fn decode<R: bytes::Buf>(r: &mut R, version: V) -> Result<Self, DecodeError> {
+		let count = u64::decode(r, version.clone())?;
+		if count > Self::MAX_ENTRIES as u64 {
+			return Err(DecodeError::TooMany);
+		}
+
+		let mut entries = BTreeMap::new();
+		for _ in 0..count {
+			let key = String::decode(r, version.clone())?;
+			let value = String::decode(r, version.clone())?;
+
+			if entries.insert(key, value).is_some() {
+				return Err(DecodeError::Duplicate);
+			}
+		}
+
+		Ok(Self(entries))
+	}
+}
+
+impl<V: Clone> Encode<V> for Metadata {
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: V) {
+		(self.0.len() as u64).encode(w, version.clone());
+		for (key, value) in &self.0 {
+			key.encode(w, version.clone());
+			value.encode(w, version.clone());
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn round_trip() {
+		let metadata = Metadata::new()
+			.with("title", "Big Buck Bunny")
+			.unwrap()
+			.with("type", "video")
+			.unwrap();
+
+		let mut buf = Vec::new();
+		metadata.encode(&mut buf, ());
+
+		let decoded = Metadata::decode(&mut buf.as_slice(), ()).unwrap();
+		assert_eq!(decoded, metadata);
+		assert_eq!(decoded.get("title"), Some("Big Buck Bunny"));
+	}
+
+	#[test]
+	fn too_many() {
+		let mut metadata = Metadata::new();
+		for i in 0..Metadata::MAX_ENTRIES {
+			metadata.insert(i.to_string(), "value").unwrap();
+		}
+
+		// Replacing an existing key is fine.
+		assert_eq!(metadata.insert("0", "new").unwrap(), Some("value".to_string()));
+
+		let mut buf = Vec::new();
+		metadata.encode(&mut buf, ());
+		assert_eq!(Metadata::decode(&mut buf.as_slice(), ()).unwrap(), metadata);
+
+		// But a new key would be rejected by the peer, so it's an error instead.
+		assert!(matches!(metadata.insert("extra", "value"), Err(Error::TooLarge)));
+		assert!(matches!(metadata.clone().with("extra", "value"), Err(Error::TooLarge)));
+		assert_eq!(metadata.len(), Metadata::MAX_ENTRIES);
+		assert_eq!(metadata.get("extra"), None);
+	}
+
+	#[test]
+	fn duplicate() {
+		let mut buf = Vec::new();
+		2u64.encode(&mut buf, ());
+		for _ in 0..2 {
+			"key".to_string().encode(&mut buf, ());
+			"value".to_string().encode(&mut buf, ());
+		}
+
+		assert!(matches!(
+			Metadata::decode(&mut buf.as_slice(), ()),
+			Err(DecodeError::Duplicate)
+		));
+	}
+}
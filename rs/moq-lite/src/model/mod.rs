@@ -1,14 +1,20 @@
 mod broadcast;
+mod budget;
+mod extensions;
 mod frame;
 mod group;
+mod metadata;
 mod origin;
 mod produce;
 mod time;
 mod track;
 
 pub use broadcast::*;
+pub use budget::*;
+pub use extensions::*;
 pub use frame::*;
 pub use group::*;
+pub use metadata::*;
 pub use origin::*;
 pub use produce::*;
 pub use time::*;
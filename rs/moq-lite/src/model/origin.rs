@@ -2,7 +2,7 @@ use std::{
 	collections::HashMap,
 	sync::atomic::{AtomicU64, Ordering},
 };
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
 use web_async::Lock;
 
 use super::BroadcastConsumer;
@@ -328,6 +328,8 @@ impl Default for OriginNodes {
 }
 
 /// A broadcast path and its associated consumer, or None if closed.
+///
+/// Any announced metadata is available via [BroadcastConsumer::metadata].
 pub type OriginAnnounce = (PathOwned, Option<BroadcastConsumer>);
 
 /// A collection of broadcasts that can be published and subscribed to.
@@ -350,6 +352,9 @@ pub struct OriginProducer {
 
 	/// The prefix that is automatically stripped from all paths.
 	root: PathOwned,
+
+	// Dropped along with the last producer, ending each consumer's announcements.
+	closed: watch::Sender<()>,
 }
 
 impl OriginProducer {
@@ -399,12 +404,13 @@ impl OriginProducer {
 		Some(OriginProducer {
 			nodes: self.nodes.select(prefixes)?,
 			root: self.root.clone(),
+			closed: self.closed.clone(),
 		})
 	}
 
 	/// Subscribe to all announced broadcasts.
 	pub fn consume(&self) -> OriginConsumer {
-		OriginConsumer::new(self.root.clone(), self.nodes.clone())
+		OriginConsumer::new(self.root.clone(), self.nodes.clone(), self.closed.subscribe())
 	}
 
 	/// Subscribe to all announced broadcasts matching the prefix.
@@ -413,7 +419,11 @@ impl OriginProducer {
 	///
 	/// Returns None if there are no legal prefixes.
 	pub fn consume_only(&self, prefixes: &[Path]) -> Option<OriginConsumer> {
-		Some(OriginConsumer::new(self.root.clone(), self.nodes.select(prefixes)?))
+		Some(OriginConsumer::new(
+			self.root.clone(),
+			self.nodes.select(prefixes)?,
+			self.closed.subscribe(),
+		))
 	}
 
 	/// Returns a new OriginProducer that automatically strips out the provided prefix.
@@ -425,6 +435,7 @@ impl OriginProducer {
 		Some(Self {
 			root: self.root.join(&prefix).to_owned(),
 			nodes: self.nodes.root(&prefix)?,
+			closed: self.closed.clone(),
 		})
 	}
 
@@ -453,10 +464,13 @@ pub struct OriginConsumer {
 
 	/// A prefix that is automatically stripped from all paths.
 	root: PathOwned,
+
+	// Errors once every OriginProducer has been dropped.
+	closed: watch::Receiver<()>,
 }
 
 impl OriginConsumer {
-	fn new(root: PathOwned, nodes: OriginNodes) -> Self {
+	fn new(root: PathOwned, nodes: OriginNodes, closed: watch::Receiver<()>) -> Self {
 		let (tx, rx) = mpsc::unbounded_channel();
 
 		let id = ConsumerId::new();
@@ -474,6 +488,7 @@ impl OriginConsumer {
 			nodes,
 			updates: rx,
 			root,
+			closed,
 		}
 	}
 
@@ -481,21 +496,31 @@ impl OriginConsumer {
 	///
 	/// The broadcast will only be announced if it was previously unannounced.
 	/// The same path won't be announced/unannounced twice, instead it will toggle.
-	/// Returns None if the consumer is closed.
+	/// Returns None once every [OriginProducer] has been dropped and any pending updates have been returned.
 	///
 	/// Note: The returned path is absolute and will always match this consumer's prefix.
 	pub async fn announced(&mut self) -> Option<OriginAnnounce> {
-		self.updates.recv().await
+		tokio::select! {
+			biased;
+			update = self.updates.recv() => update,
+			// Nothing new can be published, so return anything still queued.
+			_ = self.closed.changed() => self.updates.try_recv().ok(),
+		}
 	}
 
 	/// Returns the next (un)announced broadcast and the absolute path without blocking.
 	///
 	/// Returns None if there is no update available; NOT because the consumer is closed.
-	/// You have to use `is_closed` to check if the consumer is closed.
+	/// You have to use [Self::is_closed] to check if the consumer is closed.
 	pub fn try_announced(&mut self) -> Option<OriginAnnounce> {
 		self.updates.try_recv().ok()
 	}
 
+	/// Returns true if every [OriginProducer] has been dropped, so nothing new will be announced.
+	pub fn is_closed(&self) -> bool {
+		self.closed.has_changed().is_err()
+	}
+
 	pub fn consume(&self) -> Self {
 		self.clone()
 	}
@@ -516,7 +541,11 @@ impl OriginConsumer {
 	///
 	/// Returns None if there are no legal prefixes (would always return None).
 	pub fn consume_only(&self, prefixes: &[Path]) -> Option<OriginConsumer> {
-		Some(OriginConsumer::new(self.root.clone(), self.nodes.select(prefixes)?))
+		Some(OriginConsumer::new(
+			self.root.clone(),
+			self.nodes.select(prefixes)?,
+			self.closed.clone(),
+		))
 	}
 
 	/// Returns a new OriginConsumer that automatically strips out the provided prefix.
@@ -525,7 +554,11 @@ impl OriginConsumer {
 	pub fn with_root(&self, prefix: impl AsPath) -> Option<Self> {
 		let prefix = prefix.as_path();
 
-		Some(Self::new(self.root.join(&prefix).to_owned(), self.nodes.root(&prefix)?))
+		Some(Self::new(
+			self.root.join(&prefix).to_owned(),
+			self.nodes.root(&prefix)?,
+			self.closed.clone(),
+		))
 	}
 
 	/// Returns the prefix that is automatically stripped from all paths.
@@ -553,7 +586,7 @@ impl Drop for OriginConsumer {
 
 impl Clone for OriginConsumer {
 	fn clone(&self) -> Self {
-		OriginConsumer::new(self.root.clone(), self.nodes.clone())
+		OriginConsumer::new(self.root.clone(), self.nodes.clone(), self.closed.clone())
 	}
 }
 
@@ -589,14 +622,12 @@ impl OriginConsumer {
 		}
 	}
 
-	/*
 	pub fn assert_next_closed(&mut self) {
 		assert!(
 			self.announced().now_or_never().expect("next blocked").is_none(),
 			"next should be closed"
 		);
 	}
-	*/
 }
 
 #[cfg(test)]
@@ -662,11 +693,14 @@ mod tests {
 		consumer2.assert_next_none("test2");
 		consumer3.assert_next_none("test2");
 
-		/* TODO close the origin consumer when the producer is dropped
+		// Dropping the producer closes all of the consumers.
+		assert!(!consumer1.is_closed());
+		drop(origin.producer);
+
 		consumer1.assert_next_closed();
 		consumer2.assert_next_closed();
 		consumer3.assert_next_closed();
-		*/
+		assert!(consumer1.is_closed());
 	}
 
 	#[tokio::test]
@@ -12,13 +12,13 @@
 //!
 //! The track is closed with [Error] when all writers or readers are dropped.
 
-use tokio::sync::watch;
+use tokio::{sync::watch, time::Instant};
 
 use crate::{Error, Produce, Result};
 
 use super::{Group, GroupConsumer, GroupProducer};
 
-use std::{cmp::Ordering, future::Future};
+use std::{cmp::Ordering, future::Future, time::Duration};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -46,6 +46,23 @@ impl Track {
 struct TrackState {
 	latest: Option<GroupConsumer>,
 	closed: Option<Result<()>>,
+
+	// The sequence number of the final group, set when the track is closed cleanly.
+	fin: Option<u64>,
+
+	// How long a group is useful after it's created, set by the producer.
+	timeout: Option<Duration>,
+}
+
+impl TrackState {
+	// Returns true if the track is closed and no more groups will be inserted.
+	fn is_complete(&self) -> bool {
+		match (&self.closed, self.fin) {
+			(None, _) => false,
+			(Some(Ok(())), Some(fin)) => self.latest.as_ref().is_some_and(|group| group.info.sequence >= fin),
+			_ => true,
+		}
+	}
 }
 
 /// A producer for a track, used to create new groups.
@@ -63,10 +80,36 @@ impl TrackProducer {
 		}
 	}
 
+	/// Expire groups after the given duration, or [None] to deliver them until they're replaced.
+	///
+	/// Expired groups are no longer returned by [TrackConsumer::next_group] and their streams are reset.
+	/// This applies to existing consumers too.
+	///
+	/// NOTE: Groups received from the network are timed from when they arrive, so each relay hop restarts the clock.
+	pub fn with_timeout(self, timeout: Option<Duration>) -> Self {
+		self.state.send_if_modified(|state| {
+			let modified = state.timeout != timeout;
+			state.timeout = timeout;
+			modified
+		});
+		self
+	}
+
+	/// Returns how long a group is useful after it's created, see [Self::with_timeout].
+	pub fn timeout(&self) -> Option<Duration> {
+		self.state.borrow().timeout
+	}
+
 	/// Insert a group into the track, returning true if this is the latest group.
+	///
+	/// Groups up to the final group can still be inserted after [Self::finish], as they may be in flight.
 	pub fn insert_group(&mut self, group: GroupConsumer) -> bool {
 		self.state.send_if_modified(|state| {
-			assert!(state.closed.is_none());
+			match (&state.closed, state.fin) {
+				(None, _) => {}
+				(Some(Ok(())), Some(fin)) if group.info.sequence <= fin => {}
+				_ => return false,
+			}
 
 			if let Some(latest) = &state.latest {
 				match group.info.cmp(&latest.info) {
@@ -90,18 +133,24 @@ impl TrackProducer {
 	}
 
 	/// Create a new group with the next sequence number.
+	///
+	/// If the track was aborted, such as when the broadcast is closed, the group is not inserted and nobody will read it.
 	pub fn append_group(&mut self) -> GroupProducer {
 		let mut producer = None;
 
 		self.state.send_if_modified(|state| {
-			assert!(state.closed.is_none());
-
 			let sequence = state.latest.as_ref().map_or(0, |group| group.info.sequence + 1);
 			let group = Group { sequence }.produce();
-			state.latest = Some(group.consumer);
 			producer = Some(group.producer);
 
-			true
+			match state.closed {
+				None => {
+					state.latest = Some(group.consumer);
+					true
+				}
+				Some(Ok(())) => panic!("track is closed"),
+				Some(Err(_)) => false,
+			}
 		});
 
 		producer.unwrap()
@@ -114,12 +163,49 @@ impl TrackProducer {
 		group.close();
 	}
 
+	/// Close the track, marking the latest group as the final group.
+	///
+	/// This does nothing if the track was already closed or aborted.
 	pub fn close(self) {
-		self.state.send_modify(|state| state.closed = Some(Ok(())));
+		self.state.send_if_modified(|state| {
+			if state.closed.is_some() {
+				return false;
+			}
+
+			state.fin = state.latest.as_ref().map(|group| group.info.sequence);
+			state.closed = Some(Ok(()));
+			true
+		});
+	}
+
+	/// Close the track, marking the given sequence number as the final group.
+	///
+	/// This is used when the final group is signalled before it has been received, such as by a relay.
+	/// [TrackConsumer::next_group] keeps returning groups until it reaches the final group.
+	pub fn finish(self, sequence: u64) {
+		self.state.send_if_modified(|state| {
+			if state.closed.is_some() {
+				return false;
+			}
+
+			state.fin = Some(sequence);
+			state.closed = Some(Ok(()));
+			true
+		});
 	}
 
+	/// Abort the track with an error, unless it was already complete or aborted.
+	///
+	/// A track that was [Self::finish]ed can still be aborted while waiting for the final group.
 	pub fn abort(self, err: Error) {
-		self.state.send_modify(|state| state.closed = Some(Err(err)));
+		self.state.send_if_modified(|state| {
+			if state.is_complete() {
+				return false;
+			}
+
+			state.closed = Some(Err(err));
+			true
+		});
 	}
 
 	/// Create a new consumer for the track.
@@ -128,6 +214,24 @@ impl TrackProducer {
 			info: self.info.clone(),
 			state: self.state.subscribe(),
 			prev: None,
+			timeout: None,
+		}
+	}
+
+	/// Returns true if the track is closed and no more groups will be inserted.
+	///
+	/// After [Self::finish], this is false until the final group has been inserted.
+	pub fn is_complete(&self) -> bool {
+		self.state.borrow().is_complete()
+	}
+
+	/// Block until the track is complete, see [Self::is_complete].
+	///
+	/// NOTE: This counts as a consumer while waiting, so don't combine it with [Self::unused].
+	pub fn completed(&self) -> impl Future<Output = ()> + use<> {
+		let mut state = self.state.subscribe();
+		async move {
+			state.wait_for(TrackState::is_complete).await.ok();
 		}
 	}
 
@@ -157,35 +261,85 @@ pub struct TrackConsumer {
 	pub info: Track,
 	state: watch::Receiver<TrackState>,
 	prev: Option<u64>, // The previous sequence number
+
+	// A narrower timeout requested by this consumer.
+	timeout: Option<Duration>,
 }
 
 impl TrackConsumer {
 	/// Return the next group in order.
 	///
+	/// Returns [None] once the final group has been returned, see [Self::final_group].
+	///
 	/// NOTE: This can have gaps if the reader is too slow or there were network slowdowns.
+	/// Groups older than [Self::timeout] are skipped.
 	pub async fn next_group(&mut self) -> Result<Option<GroupConsumer>> {
-		// Wait until there's a new latest group or the track is closed.
-		let Ok(state) = self
-			.state
-			.wait_for(|state| {
-				state.latest.as_ref().map(|group| group.info.sequence) > self.prev || state.closed.is_some()
-			})
-			.await
-		else {
-			return Err(Error::Cancel);
-		};
-
-		match &state.closed {
-			Some(Ok(_)) => return Ok(None),
-			Some(Err(err)) => return Err(err.clone()),
-			_ => {}
+		loop {
+			// Wait until there's a new latest group or the track is finished.
+			let Ok(state) = self
+				.state
+				.wait_for(|state| {
+					state.latest.as_ref().map(|group| group.info.sequence) > self.prev
+						|| match &state.closed {
+							Some(Ok(())) => state.fin.is_none_or(|fin| self.prev >= Some(fin)),
+							Some(Err(_)) => true,
+							None => false,
+						}
+				})
+				.await
+			else {
+				return Err(Error::Cancel);
+			};
+
+			if let Some(Err(err)) = &state.closed {
+				return Err(err.clone());
+			}
+
+			// If there's a new latest group, return it.
+			let group = match state.latest.clone() {
+				Some(group) if Some(group.info.sequence) > self.prev => group,
+				_ => return Ok(None),
+			};
+			drop(state);
+
+			self.prev = Some(group.info.sequence);
+
+			if self.deadline(&group).is_none_or(|deadline| deadline > Instant::now()) {
+				return Ok(Some(group));
+			}
+
+			tracing::trace!(track = %self.info.name, sequence = %group.info.sequence, "skipping expired group");
 		}
+	}
+
+	/// Expire groups sooner than the producer's [TrackProducer::timeout].
+	///
+	/// The timeout can only be narrowed; a longer timeout is ignored.
+	pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+		self.timeout = narrow(self.timeout, timeout);
+		self
+	}
 
-		// If there's a new latest group, return it.
-		let group = state.latest.clone().unwrap();
-		self.prev = Some(group.info.sequence);
+	/// Returns how long a group is useful after it's created, the narrower of the producer and consumer timeouts.
+	pub fn timeout(&self) -> Option<Duration> {
+		narrow(self.state.borrow().timeout, self.timeout)
+	}
 
-		Ok(Some(group))
+	/// Returns when the group expires, based on [Self::timeout].
+	pub(crate) fn deadline(&self, group: &GroupConsumer) -> Option<Instant> {
+		Some(group.created() + self.timeout()?)
+	}
+
+	/// Returns the sequence number of the final group, once the track has been closed cleanly.
+	///
+	/// This can be used to tell a finished track apart from one that was aborted, for example by a dropped connection.
+	/// Returns [None] if the track is still active, was aborted, or was closed before any groups were created.
+	pub fn final_group(&self) -> Option<u64> {
+		let state = self.state.borrow();
+		match state.closed {
+			Some(Ok(())) => state.fin,
+			_ => None,
+		}
 	}
 
 	/// Block until the track is closed.
@@ -201,6 +355,14 @@ impl TrackConsumer {
 	}
 }
 
+// Combine two timeouts, returning the narrower one.
+fn narrow(a: Option<Duration>, b: Option<Duration>) -> Option<Duration> {
+	match (a, b) {
+		(Some(a), Some(b)) => Some(a.min(b)),
+		(a, b) => a.or(b),
+	}
+}
+
 #[cfg(test)]
 use futures::FutureExt;
 
@@ -245,3 +407,88 @@ impl TrackConsumer {
 		assert!(!self.is_clone(other), "should not be clone");
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn abort_incomplete() {
+		let track = Track::new("test").produce();
+		let mut producer = track.producer;
+		let mut consumer = track.consumer;
+
+		producer.append_group();
+		let completed = producer.completed();
+
+		// The final group never arrives, such as when its stream is reset.
+		producer.clone().finish(1);
+		assert!(!producer.is_complete());
+		consumer.assert_group();
+		consumer.assert_no_group();
+
+		// So the track can still be aborted instead of waiting forever.
+		producer.clone().abort(Error::Timeout);
+		assert!(matches!(
+			consumer.next_group().now_or_never(),
+			Some(Err(Error::Timeout))
+		));
+		assert!(completed.now_or_never().is_some());
+	}
+
+	#[test]
+	fn abort_complete() {
+		let track = Track::new("test").produce();
+		let mut producer = track.producer;
+		let mut consumer = track.consumer;
+
+		producer.append_group();
+		producer.clone().finish(1);
+
+		let completed = producer.completed();
+		producer.create_group(Group { sequence: 1 }).unwrap();
+		assert!(completed.now_or_never().is_some());
+
+		// Aborting a complete track does nothing.
+		producer.abort(Error::Timeout);
+		assert_eq!(consumer.assert_group().info.sequence, 1);
+		assert!(consumer.next_group().now_or_never().unwrap().unwrap().is_none());
+		assert_eq!(consumer.final_group(), Some(1));
+	}
+
+	#[tokio::test]
+	async fn final_group() {
+		let mut track = Track::new("track").produce();
+		track.producer.append_group();
+
+		let mut consumer = track.consumer.clone();
+		assert_eq!(consumer.assert_group().info.sequence, 0);
+
+		// The final group is signalled before groups 1 and 2 have arrived.
+		let mut producer = track.producer.clone();
+		track.producer.finish(2);
+		assert_eq!(consumer.final_group(), Some(2));
+		assert!(!producer.is_complete());
+		consumer.assert_no_group();
+
+		producer.create_group(Group { sequence: 1 }).unwrap();
+		producer.create_group(Group { sequence: 2 }).unwrap();
+		assert!(producer.is_complete());
+		assert_eq!(consumer.assert_group().info.sequence, 2);
+
+		// Nothing past the final group can be inserted.
+		assert!(producer.create_group(Group { sequence: 3 }).is_none());
+		assert!(consumer.next_group().await.unwrap().is_none());
+
+		// Closing marks the latest group as final.
+		let mut track = Track::new("track").produce();
+		track.producer.append_group();
+		track.producer.append_group();
+		track.producer.close();
+
+		let mut consumer = track.consumer;
+		assert_eq!(consumer.final_group(), Some(1));
+		assert_eq!(consumer.assert_group().info.sequence, 1);
+		assert!(consumer.next_group().await.unwrap().is_none());
+	}
+}
@@ -1,9 +1,23 @@
-use std::{future::Future, pin::Pin, sync::Arc};
+use std::{
+	collections::HashSet,
+	future::Future,
+	pin::Pin,
+	sync::{
+		Arc,
+		atomic::{AtomicU64, Ordering},
+	},
+};
+
+use tokio::sync::watch;
+use web_async::Lock;
 
 use crate::{
-	Error, OriginConsumer, OriginProducer,
+	AsPath, Error, Limits, OriginConsumer, OriginProducer, PathOwned, Stats, TransportStats,
 	coding::{self, Decode, Encode, Stream},
-	ietf, lite, setup,
+	ietf,
+	limits::Budgets,
+	lite, setup,
+	stats::Tracked,
 };
 
 /// A MoQ transport session, wrapping a WebTransport connection.
@@ -11,14 +25,26 @@ use crate::{
 /// Created via:
 /// - [`Session::connect`] for clients.
 /// - [`Session::accept`] for servers.
+#[derive(Clone)]
 pub struct Session {
 	session: Arc<dyn SessionInner>,
+	version: coding::Version,
+	goaway: watch::Sender<Option<String>>,
+	going_away: watch::Receiver<Option<String>>,
+	interest: watch::Sender<Vec<PathOwned>>,
+	throughput: watch::Receiver<Option<u64>>,
+	streams: Arc<AtomicU64>,
+	budgets: Budgets,
+	announced: Lock<HashSet<PathOwned>>,
+	transport: Option<Arc<dyn Fn() -> TransportStats + Send + Sync>>,
+	protocol: Option<String>,
 }
 
 /// The versions of MoQ that are supported by this implementation.
 ///
 /// Ordered by preference, with the client's preference taking priority.
-pub const VERSIONS: [coding::Version; 3] = [
+pub const VERSIONS: [coding::Version; 4] = [
+	lite::Version::Draft03.coding(),
 	lite::Version::Draft02.coding(),
 	lite::Version::Draft01.coding(),
 	ietf::Version::Draft14.coding(),
@@ -27,10 +53,34 @@ pub const VERSIONS: [coding::Version; 3] = [
 /// The ALPN strings for supported versions.
 pub const ALPNS: [&str; 2] = [lite::ALPN, ietf::ALPN];
 
+// How long to wait for the final group streams after a publisher ends a track, before closing it anyway.
+// The streams may have been reset or lost, and we don't want to leak the track forever.
+pub(crate) const FINISH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
 impl Session {
-	fn new<S: web_transport_trait::Session>(session: S) -> Self {
+	#[allow(clippy::too_many_arguments)]
+	fn new<S: web_transport_trait::Session>(
+		session: Tracked<S>,
+		version: coding::Version,
+		goaway: watch::Sender<Option<String>>,
+		going_away: watch::Receiver<Option<String>>,
+		interest: watch::Sender<Vec<PathOwned>>,
+		throughput: watch::Receiver<Option<u64>>,
+		budgets: Budgets,
+		announced: Lock<HashSet<PathOwned>>,
+	) -> Self {
 		Self {
+			streams: session.streams(),
 			session: Arc::new(session),
+			version,
+			goaway,
+			going_away,
+			interest,
+			throughput,
+			budgets,
+			announced,
+			transport: None,
+			protocol: None,
 		}
 	}
 
@@ -43,18 +93,65 @@ impl Session {
 		publish: impl Into<Option<OriginConsumer>>,
 		subscribe: impl Into<Option<OriginProducer>>,
 	) -> Result<Self, Error> {
+		Self::connect_inner(session, None, publish.into(), subscribe.into()).await
+	}
+
+	/// Perform the MoQ handshake as a client, sending the path (and query) in the SETUP message.
+	///
+	/// Raw QUIC connections don't have a URL, so this is how the server learns the path and any auth token.
+	/// It should not be used for WebTransport, which already provides the URL.
+	pub async fn connect_with_path<S: web_transport_trait::Session>(
+		session: S,
+		path: &str,
+		publish: impl Into<Option<OriginConsumer>>,
+		subscribe: impl Into<Option<OriginProducer>>,
+	) -> Result<Self, Error> {
+		Self::connect_inner(session, Some(path), publish.into(), subscribe.into()).await
+	}
+
+	/// Perform the MoQ handshake as a client, only offering the given versions.
+	#[cfg(test)]
+	pub(crate) async fn connect_with_versions<S: web_transport_trait::Session>(
+		session: S,
+		versions: Vec<coding::Version>,
+		publish: impl Into<Option<OriginConsumer>>,
+		subscribe: impl Into<Option<OriginProducer>>,
+	) -> Result<Self, Error> {
+		Self::connect_versions(session, None, &versions, publish.into(), subscribe.into()).await
+	}
+
+	async fn connect_inner<S: web_transport_trait::Session>(
+		session: S,
+		path: Option<&str>,
+		publish: Option<OriginConsumer>,
+		subscribe: Option<OriginProducer>,
+	) -> Result<Self, Error> {
+		Self::connect_versions(session, path, &VERSIONS, publish, subscribe).await
+	}
+
+	async fn connect_versions<S: web_transport_trait::Session>(
+		session: S,
+		path: Option<&str>,
+		versions: &[coding::Version],
+		publish: Option<OriginConsumer>,
+		subscribe: Option<OriginProducer>,
+	) -> Result<Self, Error> {
+		let session = Tracked::new(session);
 		let mut stream = Stream::open(&session, setup::ServerKind::Ietf14).await?;
 
 		let mut parameters = ietf::Parameters::default();
 		parameters.set_varint(ietf::ParameterVarInt::MaxRequestId, u32::MAX as u64);
 		parameters.set_bytes(ietf::ParameterBytes::Implementation, b"moq-lite-rs".to_vec());
+		if let Some(path) = path {
+			parameters.set_bytes(ietf::ParameterBytes::Path, path.as_bytes().to_vec());
+		}
 		let parameters = parameters.encode_bytes(());
 
 		let client = setup::Client {
 			// Unfortunately, we have to pick a single draft range to support.
 			// moq-lite can support this handshake.
 			kind: setup::ClientKind::Ietf14,
-			versions: VERSIONS.into(),
+			versions: versions.to_vec().into(),
 			parameters,
 		};
 
@@ -65,9 +162,30 @@ impl Session {
 		let mut server: setup::Server = stream.reader.decode().await?;
 		tracing::trace!(?server, "received server setup");
 
+		let goaway = watch::channel(None);
+		let going_away = watch::channel(None);
+		let interest = watch::channel(Session::default_interest(subscribe.as_ref()));
+		let throughput = watch::channel(None);
+		let budgets = Budgets::default();
+		let announced = Lock::<HashSet<PathOwned>>::default();
+
 		if let Ok(version) = lite::Version::try_from(server.version) {
 			let stream = stream.with_version(version);
-			lite::start(session.clone(), stream, publish.into(), subscribe.into(), version).await?;
+			lite::start(
+				session.clone(),
+				stream,
+				publish,
+				subscribe,
+				version,
+				goaway.1,
+				going_away.0,
+				interest.1,
+				session.received(),
+				throughput.0,
+				budgets.clone(),
+				announced.clone(),
+			)
+			.await?;
 		} else if let Ok(version) = ietf::Version::try_from(server.version) {
 			// Decode the parameters to get the initial request ID.
 			let parameters = ietf::Parameters::decode(&mut server.parameters, version)?;
@@ -80,9 +198,13 @@ impl Session {
 				stream,
 				request_id_max,
 				true,
-				publish.into(),
-				subscribe.into(),
+				publish,
+				subscribe,
 				version,
+				goaway.1,
+				going_away.0,
+				budgets.clone(),
+				announced.clone(),
 			)
 			.await?;
 		} else {
@@ -92,7 +214,16 @@ impl Session {
 
 		tracing::debug!(version = ?server.version, "connected");
 
-		Ok(Self::new(session))
+		Ok(Self::new(
+			session,
+			server.version,
+			goaway.0,
+			going_away.1,
+			interest.0,
+			throughput.1,
+			budgets,
+			announced,
+		))
 	}
 
 	/// Perform the MoQ handshake as a server.
@@ -104,11 +235,214 @@ impl Session {
 		publish: impl Into<Option<OriginConsumer>>,
 		subscribe: impl Into<Option<OriginProducer>>,
 	) -> Result<Self, Error> {
+		SessionRequest::accept(session).await?.ok(publish, subscribe).await
+	}
+
+	/// Returns the negotiated version, which determines whether moq-lite or IETF MoQ is used.
+	///
+	/// Use [lite::Version] or [ietf::Version] to convert it.
+	pub fn version(&self) -> coding::Version {
+		self.version
+	}
+
+	/// Returns the rate at which the peer is receiving data from us, in bits per second.
+	///
+	/// The peer periodically reports how many bytes arrived over the last second.
+	/// This is the observed throughput, not an estimate of the available bandwidth:
+	/// it's capped by how much we actually send, so it only bounds the bandwidth from below.
+	/// The value is [None] until the peer reports receiving something.
+	///
+	/// NOTE: Only moq-lite supports this; it's always [None] for IETF MoQ.
+	pub fn throughput(&self) -> watch::Receiver<Option<u64>> {
+		self.throughput.clone()
+	}
+
+	/// Provide statistics from the underlying transport, reported by [Self::stats].
+	///
+	/// The [web_transport_trait::Session] doesn't expose statistics, so the caller supplies them.
+	/// The function is called each time [Self::stats] is called.
+	pub fn with_transport_stats(mut self, stats: impl Fn() -> TransportStats + Send + Sync + 'static) -> Self {
+		self.transport = Some(Arc::new(stats));
+		self
+	}
+
+	/// Record the protocol negotiated by the underlying transport, reported by [Self::protocol].
+	///
+	/// Like [Self::with_transport_stats], the [web_transport_trait::Session] doesn't expose it, so the caller supplies it.
+	pub fn with_protocol(mut self, protocol: impl Into<String>) -> Self {
+		self.protocol = Some(protocol.into());
+		self
+	}
+
+	/// Returns the protocol negotiated by the underlying transport, if provided via [Self::with_protocol].
+	///
+	/// For QUIC this is the ALPN: `h3` for WebTransport, or [lite::ALPN] and [ietf::ALPN] for raw QUIC.
+	/// Use [Self::version] for the negotiated MoQ version.
+	pub fn protocol(&self) -> Option<&str> {
+		self.protocol.as_deref()
+	}
+
+	/// Returns the broadcasts currently announced by the peer, relative to the subscribe [OriginProducer].
+	///
+	/// Unlike the [OriginProducer], this excludes broadcasts published by anything other than this session.
+	pub fn announced(&self) -> Vec<PathOwned> {
+		self.announced.lock().iter().cloned().collect()
+	}
+
+	/// Limit the number of bytes buffered for remote subscriptions, unlimited by default.
+	///
+	/// A group that would exceed a limit is aborted with [Error::TooLarge].
+	/// The limits can be changed at any time, including for existing subscriptions, but only apply to new groups.
+	pub fn with_limits(self, limits: Limits) -> Self {
+		self.budgets.set(limits);
+		self
+	}
+
+	/// Returns a snapshot of the session's health, useful for logging and metrics.
+	///
+	/// The [Stats::transport] field is only populated when the caller provided a side channel via [Self::with_transport_stats],
+	/// as the [web_transport_trait::Session] has no way to report them.
+	/// moq-native does this for QUIC and iroh, but a WebSocket has no transport statistics so it's always [None].
+	pub fn stats(&self) -> Stats {
+		Stats {
+			version: self.version,
+			streams: self.streams.load(Ordering::Relaxed),
+			buffered: self.budgets.used(),
+			transport: self.transport.as_ref().map(|stats| stats()),
+		}
+	}
+
+	/// Ask the peer to migrate to a new session, optionally at a different URI.
+	///
+	/// This sends a GOAWAY but leaves the session open.
+	/// Call [Self::close] once the peer has had a chance to migrate.
+	pub fn goaway(&self, uri: Option<&str>) {
+		self.goaway.send_replace(Some(uri.unwrap_or_default().to_string()));
+	}
+
+	/// Block until the peer sends a GOAWAY, returning the URI of the new session.
+	///
+	/// An empty URI means the new session should use the same URI as this one.
+	/// The session remains open; it's up to the caller to migrate and then [Self::close] it.
+	/// Returns [None] if the session was closed without a GOAWAY.
+	pub async fn going_away(&self) -> Option<String> {
+		let mut going_away = self.going_away.clone();
+		going_away
+			.wait_for(Option::is_some)
+			.await
+			.ok()
+			.and_then(|uri| uri.clone())
+	}
+
+	// By default, ask for every root that we're allowed to subscribe to.
+	fn default_interest(subscribe: Option<&OriginProducer>) -> Vec<PathOwned> {
+		subscribe
+			.map(|origin| origin.allowed().map(|root| root.to_owned()).collect())
+			.unwrap_or_default()
+	}
+
+	/// Ask the peer to announce any broadcasts matching the prefix, relative to the subscribe origin.
+	///
+	/// By default, the session is interested in each root allowed by the subscribe [OriginProducer].
+	/// The prefix is scoped to the allowed roots, so broadcasts outside of them are never requested.
+	/// Returns false if the prefix was already requested.
+	///
+	/// NOTE: Only moq-lite supports this; it has no effect for IETF MoQ.
+	pub fn add_interest(&self, prefix: impl AsPath) -> bool {
+		let prefix = prefix.as_path().to_owned();
+		self.interest.send_if_modified(|interest| {
+			if interest.contains(&prefix) {
+				return false;
+			}
+
+			interest.push(prefix);
+			true
+		})
+	}
+
+	/// Stop asking the peer to announce broadcasts matching the prefix, unannouncing them locally.
+	///
+	/// Broadcasts that are still covered by a shorter prefix remain announced.
+	/// Returns false if the prefix was not requested.
+	pub fn remove_interest(&self, prefix: impl AsPath) -> bool {
+		let prefix = prefix.as_path();
+		self.interest.send_if_modified(|interest| {
+			let len = interest.len();
+			interest.retain(|existing| existing != &prefix);
+			interest.len() != len
+		})
+	}
+
+	/// Close the underlying transport session.
+	pub fn close(self, err: Error) {
+		self.session.close(err.to_code(), err.to_string().as_ref());
+	}
+
+	/// Block until the transport session is closed.
+	// TODO Remove the Result the next time we make a breaking change.
+	pub async fn closed(&self) -> Result<(), Error> {
+		let err = self.session.closed().await;
+		Err(Error::Transport(err))
+	}
+}
+
+/// A MoQ session requested by a client, before it has been accepted.
+///
+/// The client's SETUP has been received, so the requested path is available for authorization.
+/// Created via [SessionRequest::accept].
+pub struct SessionRequest<S: web_transport_trait::Session> {
+	session: Tracked<S>,
+	stream: Stream<Tracked<S>, ()>,
+	client: setup::Client,
+}
+
+impl<S: web_transport_trait::Session> SessionRequest<S> {
+	/// Receive the client's SETUP message, without responding yet.
+	pub async fn accept(session: S) -> Result<Self, Error> {
+		let session = Tracked::new(session);
+
 		// Accept with an initial version; we'll switch to the negotiated version later
 		let mut stream = Stream::accept(&session, ()).await?;
 		let client: setup::Client = stream.reader.decode().await?;
 		tracing::trace!(?client, "received client setup");
 
+		Ok(Self {
+			session,
+			stream,
+			client,
+		})
+	}
+
+	/// Returns the path (and query) requested by the client, if any.
+	///
+	/// This is only provided by clients using [Session::connect_with_path], typically over raw QUIC.
+	pub fn path(&self) -> Option<String> {
+		if self.client.kind != setup::ClientKind::Ietf14 {
+			return None;
+		}
+
+		let parameters = ietf::Parameters::decode(&mut self.client.parameters.clone(), ietf::Version::Draft14).ok()?;
+		let path = parameters.get_bytes(ietf::ParameterBytes::Path)?;
+		String::from_utf8(path.to_vec()).ok()
+	}
+
+	/// Accept the session, performing the rest of the MoQ handshake.
+	///
+	/// Publishing is performed with [OriginConsumer] and subscribing with [OriginProducer].
+	/// The connection remains active until the session is closed.
+	pub async fn ok(
+		self,
+		publish: impl Into<Option<OriginConsumer>>,
+		subscribe: impl Into<Option<OriginProducer>>,
+	) -> Result<Session, Error> {
+		let Self {
+			session,
+			stream,
+			client,
+		} = self;
+		let publish = publish.into();
+		let subscribe = subscribe.into();
+
 		// Choose the version to use
 		let version = client
 			.versions
@@ -133,9 +467,30 @@ impl Session {
 		let mut stream = stream.with_version(client.kind.reply());
 		stream.writer.encode(&server).await?;
 
+		let goaway = watch::channel(None);
+		let going_away = watch::channel(None);
+		let interest = watch::channel(Session::default_interest(subscribe.as_ref()));
+		let throughput = watch::channel(None);
+		let budgets = Budgets::default();
+		let announced = Lock::<HashSet<PathOwned>>::default();
+
 		if let Ok(version) = lite::Version::try_from(version) {
 			let stream = stream.with_version(version);
-			lite::start(session.clone(), stream, publish.into(), subscribe.into(), version).await?;
+			lite::start(
+				session.clone(),
+				stream,
+				publish,
+				subscribe,
+				version,
+				goaway.1,
+				going_away.0,
+				interest.1,
+				session.received(),
+				throughput.0,
+				budgets.clone(),
+				announced.clone(),
+			)
+			.await?;
 		} else if let Ok(version) = ietf::Version::try_from(version) {
 			// Decode the parameters to get the initial request ID.
 			let parameters = ietf::Parameters::decode(&mut server.parameters, version)?;
@@ -148,9 +503,13 @@ impl Session {
 				stream,
 				request_id_max,
 				false,
-				publish.into(),
-				subscribe.into(),
+				publish,
+				subscribe,
 				version,
+				goaway.1,
+				going_away.0,
+				budgets.clone(),
+				announced.clone(),
 			)
 			.await?;
 		} else {
@@ -160,20 +519,22 @@ impl Session {
 
 		tracing::debug!(?version, "connected");
 
-		Ok(Self::new(session))
+		Ok(Session::new(
+			session,
+			version,
+			goaway.0,
+			going_away.1,
+			interest.0,
+			throughput.1,
+			budgets,
+			announced,
+		))
 	}
 
-	/// Close the underlying transport session.
+	/// Reject the session, closing the underlying transport.
 	pub fn close(self, err: Error) {
 		self.session.close(err.to_code(), err.to_string().as_ref());
 	}
-
-	/// Block until the transport session is closed.
-	// TODO Remove the Result the next time we make a breaking change.
-	pub async fn closed(&self) -> Result<(), Error> {
-		let err = self.session.closed().await;
-		Err(Error::Transport(err))
-	}
 }
 
 // We use a wrapper type that is dyn-compatible to remove the generic bounds from Session.
@@ -191,3 +552,606 @@ impl<S: web_transport_trait::Session> SessionInner for S {
 		Box::pin(async move { Arc::new(S::closed(self).await) as Arc<dyn crate::error::SendSyncError> })
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use std::time::Duration;
+
+	use bytes::Bytes;
+
+	use super::*;
+	use crate::{
+		Broadcast, BroadcastProducer, Extensions, Frame, Metadata, Origin, Track, TrackConsumer, TrackProducer,
+		loopback::Loopback,
+	};
+
+	// A client publishing the "demo" broadcast to a server over a loopback transport.
+	struct Fixture {
+		client: Session,
+		server: Session,
+		broadcast: BroadcastProducer,
+		// Broadcasts announced by the client are inserted here.
+		subscriber: OriginConsumer,
+		_publisher: OriginProducer,
+	}
+
+	impl Fixture {
+		async fn new() -> Self {
+			Self::with_versions(VERSIONS.to_vec()).await
+		}
+
+		async fn with_version(version: coding::Version) -> Self {
+			Self::with_versions(vec![version]).await
+		}
+
+		async fn with_versions(versions: Vec<coding::Version>) -> Self {
+			let loopback = Loopback::default();
+
+			let publisher = Origin::produce();
+			let subscriber = Origin::produce();
+			let broadcast = publisher.producer.create_broadcast("demo").unwrap();
+			let mut announced = subscriber.consumer.consume();
+
+			let (client, server) = tokio::try_join!(
+				Session::connect_with_versions(loopback.client, versions, publisher.consumer, None),
+				Session::accept(loopback.server, None, subscriber.producer),
+			)
+			.unwrap();
+
+			// IETF doesn't wait for the initial announcements during the handshake.
+			let (path, active) = announced.announced().await.unwrap();
+			assert_eq!(path.as_str(), "demo");
+			assert!(active.is_some());
+
+			Self {
+				client,
+				server,
+				broadcast,
+				subscriber: subscriber.consumer,
+				_publisher: publisher.producer,
+			}
+		}
+
+		// Subscribe to a track in the "demo" broadcast.
+		fn subscribe(&self, track: &str) -> TrackConsumer {
+			let broadcast = self.subscriber.consume_broadcast("demo").unwrap();
+			broadcast.subscribe_track(&Track::new(track))
+		}
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_stats() {
+		let fixture = Fixture::new().await;
+
+		let stats = fixture.client.stats();
+		assert_eq!(stats.version, fixture.client.version());
+		assert_eq!(stats.transport, None);
+
+		// At the very least, the control stream is open on both sides.
+		assert!(stats.streams >= 1);
+		assert!(fixture.server.stats().streams >= 1);
+
+		let transport = crate::TransportStats {
+			rtt: Duration::from_millis(20),
+			packets_lost: 3,
+			..Default::default()
+		};
+
+		let client = fixture.client.with_transport_stats(move || transport.clone());
+		let stats = client.stats().transport.unwrap();
+		assert_eq!(stats.rtt, Duration::from_millis(20));
+		assert_eq!(stats.packets_lost, 3);
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_interest() {
+		let loopback = Loopback::default();
+
+		let publisher = Origin::produce();
+		let _room1 = publisher.producer.create_broadcast("rooms/1/alice").unwrap();
+		let _room2 = publisher.producer.create_broadcast("rooms/2/bob").unwrap();
+		let _other = publisher.producer.create_broadcast("other").unwrap();
+
+		// Only ask for the rooms we're allowed to subscribe to.
+		let subscriber = Origin::produce();
+		let mut announced = subscriber.producer.consume();
+		let subscribe = subscriber.producer.publish_only(&["rooms/1".into()]).unwrap();
+
+		let (_client, session) = tokio::try_join!(
+			Session::connect(loopback.client, publisher.consumer, None),
+			Session::accept(loopback.server, None, subscribe),
+		)
+		.unwrap();
+
+		let (path, active) = announced.try_announced().unwrap();
+		assert_eq!(path.as_str(), "rooms/1/alice");
+		assert!(active.is_some());
+		assert!(announced.try_announced().is_none());
+
+		// Swap the interest at runtime.
+		assert!(session.remove_interest("rooms/1"));
+		assert!(!session.remove_interest("rooms/1"));
+
+		let (path, active) = announced.announced().await.unwrap();
+		assert_eq!(path.as_str(), "rooms/1/alice");
+		assert!(active.is_none());
+
+		// Broadcasts outside of the allowed roots are ignored.
+		assert!(session.add_interest("rooms"));
+		assert!(!session.add_interest("rooms"));
+
+		let (path, active) = announced.announced().await.unwrap();
+		assert_eq!(path.as_str(), "rooms/1/alice");
+		assert!(active.is_some());
+
+		tokio::time::sleep(Duration::from_secs(1)).await;
+		assert!(announced.try_announced().is_none());
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_interest_narrow() {
+		let loopback = Loopback::default();
+
+		let publisher = Origin::produce();
+		let _room = publisher.producer.create_broadcast("rooms/1/alice").unwrap();
+		let _other = publisher.producer.create_broadcast("other").unwrap();
+
+		let subscriber = Origin::produce();
+		let mut announced = subscriber.consumer.consume();
+
+		let (_client, session) = tokio::try_join!(
+			Session::connect(loopback.client, publisher.consumer, None),
+			Session::accept(loopback.server, None, subscriber.producer),
+		)
+		.unwrap();
+
+		let mut initial = [announced.try_announced().unwrap(), announced.try_announced().unwrap()];
+		initial.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+		assert_eq!(initial[0].0.as_str(), "other");
+		assert_eq!(initial[1].0.as_str(), "rooms/1/alice");
+		assert!(initial.iter().all(|(_, active)| active.is_some()));
+
+		// Narrow the interest from everything to just the rooms.
+		assert!(session.add_interest("rooms"));
+		assert!(session.remove_interest(""));
+
+		// Only the broadcast outside of the remaining prefix is unannounced.
+		let (path, active) = announced.announced().await.unwrap();
+		assert_eq!(path.as_str(), "other");
+		assert!(active.is_none());
+
+		// The covered broadcast stays announced the whole time, so it's still usable.
+		tokio::time::sleep(Duration::from_secs(1)).await;
+		assert!(announced.try_announced().is_none());
+		assert!(subscriber.consumer.consume_broadcast("rooms/1/alice").is_some());
+	}
+
+	async fn announced(version: coding::Version) {
+		let loopback = Loopback::default();
+
+		let publisher = Origin::produce();
+		let remote = publisher.producer.create_broadcast("remote").unwrap();
+
+		// Also publish a broadcast locally to the same origin.
+		let subscriber = Origin::produce();
+		let _local = subscriber.producer.create_broadcast("local").unwrap();
+		let mut announced = subscriber.consumer.consume();
+
+		let (_client, session) = tokio::try_join!(
+			Session::connect_with_versions(loopback.client, vec![version], publisher.consumer, None),
+			Session::accept(loopback.server, None, subscriber.producer),
+		)
+		.unwrap();
+
+		while announced.announced().await.unwrap().0.as_str() != "remote" {}
+
+		// Only the broadcasts announced by the peer are reported.
+		assert_eq!(session.announced(), vec![PathOwned::from("remote")]);
+
+		drop(remote);
+		let (path, active) = announced.announced().await.unwrap();
+		assert_eq!(path.as_str(), "remote");
+		assert!(active.is_none());
+		assert!(session.announced().is_empty());
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_announced_lite() {
+		announced(lite::Version::Draft03.coding()).await;
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_announced_ietf() {
+		announced(ietf::Version::Draft14.coding()).await;
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_metadata() {
+		let loopback = Loopback::default();
+
+		let publisher = Origin::produce();
+		let subscriber = Origin::produce();
+		let mut announced = subscriber.consumer.consume();
+
+		let metadata = Metadata::new().with("title", "before").unwrap();
+		let before = Broadcast::produce_with_metadata(metadata.clone());
+		publisher.producer.publish_broadcast("before", before.consumer);
+
+		let (_client, _server) = tokio::try_join!(
+			Session::connect(loopback.client, publisher.consumer, None),
+			Session::accept(loopback.server, None, subscriber.producer),
+		)
+		.unwrap();
+
+		// Metadata is included in the initial announcements.
+		let (path, active) = announced.announced().await.unwrap();
+		assert_eq!(path.as_str(), "before");
+		assert_eq!(active.unwrap().metadata(), &metadata);
+
+		// And in any subsequent announcements.
+		let metadata = Metadata::new()
+			.with("title", "after")
+			.unwrap()
+			.with("type", "video")
+			.unwrap();
+		let after = Broadcast::produce_with_metadata(metadata.clone());
+		publisher.producer.publish_broadcast("after", after.consumer);
+
+		let (path, active) = announced.announced().await.unwrap();
+		assert_eq!(path.as_str(), "after");
+		assert_eq!(active.unwrap().metadata(), &metadata);
+	}
+
+	async fn extensions(version: coding::Version) -> (Fixture, TrackProducer, TrackConsumer) {
+		let mut fixture = Fixture::with_version(version).await;
+		let mut track = fixture.broadcast.create_track(Track::new("test"));
+		let mut remote = fixture.subscribe("test");
+
+		let mut extensions = Extensions::new();
+		extensions.set_varint(2, 1234);
+		extensions.set_bytes(3, "key-id");
+
+		let mut group = track.append_group();
+		let mut frame = group.create_frame(Frame {
+			size: Some(5),
+			extensions: extensions.clone(),
+		});
+		frame.write_chunk(Bytes::from_static(b"hello"));
+		frame.close();
+
+		// Frames without extensions still work.
+		group.write_frame(Bytes::from_static(b"world"));
+		group.close();
+
+		let mut group = remote.next_group().await.unwrap().unwrap();
+
+		let mut frame = group.next_frame().await.unwrap().unwrap();
+		assert_eq!(frame.info.extensions, extensions);
+		assert_eq!(frame.read_all().await.unwrap(), "hello");
+
+		let mut frame = group.next_frame().await.unwrap().unwrap();
+		assert!(frame.info.extensions.is_empty());
+		assert_eq!(frame.read_all().await.unwrap(), "world");
+
+		(fixture, track, remote)
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_extensions_lite() {
+		extensions(lite::Version::Draft03.coding()).await;
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_extensions_ietf() {
+		let (_fixture, mut track, mut remote) = extensions(ietf::Version::Draft14.coding()).await;
+
+		// The group header only signals extensions if the first frame has them.
+		let mut group = track.append_group();
+		group.write_frame(Bytes::from_static(b"hello"));
+
+		let mut extensions = Extensions::new();
+		extensions.set_varint(2, 1234);
+
+		let mut frame = group.create_frame(Frame {
+			size: Some(5),
+			extensions,
+		});
+		frame.write_chunk(Bytes::from_static(b"world"));
+		frame.close();
+		group.close();
+
+		let mut group = remote.next_group().await.unwrap().unwrap();
+		assert_eq!(group.read_frame().await.unwrap().unwrap(), "hello");
+
+		let mut frame = group.next_frame().await.unwrap().unwrap();
+		assert!(frame.info.extensions.is_empty());
+		assert_eq!(frame.read_all().await.unwrap(), "world");
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_streaming() {
+		let mut fixture = Fixture::new().await;
+		let mut track = fixture.broadcast.create_track(Track::new("test"));
+		let mut remote = fixture.subscribe("test");
+
+		let mut group = track.append_group();
+		let mut frame = group.create_frame(Frame::streaming());
+		frame.write_chunk(Bytes::from_static(b"hello"));
+
+		// The chunk is forwarded before the frame is finished.
+		let mut remote_group = remote.next_group().await.unwrap().unwrap();
+		let mut remote_frame = remote_group.next_frame().await.unwrap().unwrap();
+		assert_eq!(remote_frame.info.size, None);
+		assert_eq!(remote_frame.read_chunk().await.unwrap().unwrap(), "hello");
+
+		// Empty chunks are skipped rather than ending the frame early.
+		frame.write_chunk(Bytes::new());
+		frame.write_chunk(Bytes::from_static(b" world"));
+		frame.close();
+
+		assert_eq!(remote_frame.read_all().await.unwrap(), " world");
+		assert_eq!(remote_frame.info.size, Some(11));
+
+		// Sized frames can follow streaming frames.
+		group.write_frame(Bytes::from_static(b"done"));
+		group.close();
+
+		let mut remote_frame = remote_group.next_frame().await.unwrap().unwrap();
+		assert_eq!(remote_frame.info.size, Some(4));
+		assert_eq!(remote_frame.read_all().await.unwrap(), "done");
+		assert!(remote_group.next_frame().await.unwrap().is_none());
+	}
+
+	// Versions without streaming frames buffer them until they're closed.
+	async fn streaming_buffered(version: coding::Version) {
+		let mut fixture = Fixture::with_version(version).await;
+		let mut track = fixture.broadcast.create_track(Track::new("test"));
+		let mut remote = fixture.subscribe("test");
+
+		let mut group = track.append_group();
+		let mut frame = group.create_frame(Frame::streaming());
+		frame.write_chunk(Bytes::from_static(b"hello"));
+
+		let mut remote_group = remote.next_group().await.unwrap().unwrap();
+		assert!(
+			tokio::time::timeout(Duration::from_secs(1), remote_group.next_frame())
+				.await
+				.is_err()
+		);
+
+		frame.write_chunk(Bytes::from_static(b" world"));
+		frame.close();
+
+		// The frame arrives with the final size.
+		let mut remote_frame = remote_group.next_frame().await.unwrap().unwrap();
+		assert_eq!(remote_frame.info.size, Some(11));
+		assert_eq!(remote_frame.read_all().await.unwrap(), "hello world");
+
+		// Empty streaming frames are sent too.
+		group.create_frame(Frame::streaming()).close();
+		group.close();
+
+		let mut remote_frame = remote_group.next_frame().await.unwrap().unwrap();
+		assert_eq!(remote_frame.info.size, Some(0));
+		assert_eq!(remote_frame.read_all().await.unwrap(), "");
+		assert!(remote_group.next_frame().await.unwrap().is_none());
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_streaming_draft01() {
+		streaming_buffered(lite::Version::Draft01.coding()).await;
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_streaming_draft02() {
+		streaming_buffered(lite::Version::Draft02.coding()).await;
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_streaming_ietf() {
+		streaming_buffered(ietf::Version::Draft14.coding()).await;
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_budget() {
+		let mut fixture = Fixture::new().await;
+		let mut track = fixture.broadcast.create_track(Track::new("test"));
+		let mut remote = fixture.subscribe("test");
+
+		// Limits also apply to subscriptions made before they were set.
+		let server = fixture.server.with_limits(Limits {
+			group: Some(8),
+			..Default::default()
+		});
+
+		// The second frame exceeds the group limit, aborting the group.
+		let mut group = track.append_group();
+		group.write_frame(Bytes::from_static(b"hello"));
+		group.write_frame(Bytes::from_static(b"world!"));
+		group.close();
+
+		let mut group = remote.next_group().await.unwrap().unwrap();
+		assert_eq!(group.read_frame().await.unwrap().unwrap(), "hello");
+		assert!(matches!(group.read_frame().await, Err(Error::TooLarge)));
+		assert_eq!(server.stats().buffered, 5);
+
+		// Each group has its own budget.
+		let mut next = track.append_group();
+		next.write_frame(Bytes::from_static(b"abc"));
+		next.close();
+
+		let mut next = remote.next_group().await.unwrap().unwrap();
+		assert_eq!(next.read_frame().await.unwrap().unwrap(), "abc");
+		assert_eq!(server.stats().buffered, 8);
+
+		// The bytes are released once nothing references the old group.
+		drop(group);
+		assert_eq!(server.stats().buffered, 3);
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_unlimited() {
+		let mut fixture = Fixture::new().await;
+		let mut track = fixture.broadcast.create_track(Track::new("test"));
+		let mut remote = fixture.subscribe("test");
+
+		let mut group = track.append_group();
+		group.write_frame(Bytes::from_static(b"hello"));
+		group.write_frame(Bytes::from_static(b"world"));
+		group.close();
+
+		let mut group = remote.next_group().await.unwrap().unwrap();
+		let mut late = group.clone();
+		assert_eq!(group.read_frame().await.unwrap().unwrap(), "hello");
+		assert_eq!(group.read_frame().await.unwrap().unwrap(), "world");
+
+		// Without any limits nothing is charged, so a late consumer still gets the whole group.
+		assert_eq!(fixture.server.stats().buffered, 0);
+		assert_eq!(late.read_frame().await.unwrap().unwrap(), "hello");
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_timeout() {
+		let mut fixture = Fixture::new().await;
+		let mut track = fixture
+			.broadcast
+			.create_track(Track::new("test"))
+			.with_timeout(Some(Duration::from_secs(1)));
+		let mut remote = fixture.subscribe("test");
+
+		// The group is never closed, so the stream is reset after the timeout.
+		let mut group = track.append_group();
+		group.write_frame(Bytes::from_static(b"hello"));
+
+		let mut stale = remote.next_group().await.unwrap().unwrap();
+		assert_eq!(stale.read_frame().await.unwrap().unwrap(), "hello");
+		assert!(stale.read_frame().await.is_err());
+
+		// Newer groups are still delivered.
+		let mut group = track.append_group();
+		group.write_frame(Bytes::from_static(b"world"));
+		group.close();
+
+		let mut group = remote.next_group().await.unwrap().unwrap();
+		assert_eq!(group.info.sequence, 1);
+		assert_eq!(group.read_frame().await.unwrap().unwrap(), "world");
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_final_group() {
+		let mut fixture = Fixture::new().await;
+		let mut track = fixture.broadcast.create_track(Track::new("test"));
+		let mut remote = fixture.subscribe("test");
+
+		track.write_frame(Bytes::from_static(b"hello"));
+		let mut group = remote.next_group().await.unwrap().unwrap();
+		assert_eq!(group.read_frame().await.unwrap().unwrap(), "hello");
+
+		track.write_frame(Bytes::from_static(b"world"));
+		track.close();
+
+		let mut group = remote.next_group().await.unwrap().unwrap();
+		assert_eq!(group.read_frame().await.unwrap().unwrap(), "world");
+
+		// The track finished cleanly, rather than the connection dropping.
+		assert!(remote.next_group().await.unwrap().is_none());
+		assert_eq!(remote.final_group(), Some(1));
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_tracks() {
+		let mut fixture = Fixture::new().await;
+		let _video = fixture.broadcast.create_track(Track::new("video"));
+
+		let remote = fixture.subscriber.consume_broadcast("demo").unwrap();
+		let mut tracks = remote.tracks();
+
+		// Discovery is only requested on demand, so it arrives asynchronously.
+		assert_eq!(tracks.announced().await, Some(("video".to_string(), true)));
+
+		let _audio = fixture.broadcast.create_track(Track::new("audio"));
+		assert_eq!(tracks.announced().await, Some(("audio".to_string(), true)));
+
+		fixture.broadcast.remove_track("video");
+		assert_eq!(tracks.announced().await, Some(("video".to_string(), false)));
+
+		tokio::time::sleep(Duration::from_secs(1)).await;
+		assert_eq!(tracks.try_announced(), None);
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_throughput() {
+		let mut fixture = Fixture::new().await;
+		let mut track = fixture.broadcast.create_track(Track::new("test"));
+		let mut remote = fixture.subscribe("test");
+
+		// Nothing has been received yet.
+		let mut throughput = fixture.client.throughput();
+		assert_eq!(*throughput.borrow(), None);
+
+		// Send 10KB every 100ms, roughly 800kbit/s.
+		// The link is unlimited, so this measures what we send rather than what the link could carry.
+		let reader = tokio::spawn(async move {
+			while let Ok(Some(mut group)) = remote.next_group().await {
+				while let Ok(Some(_)) = group.read_frame().await {}
+			}
+		});
+
+		for _ in 0..20 {
+			track.write_frame(Bytes::from(vec![0u8; 10_000]));
+			tokio::time::sleep(Duration::from_millis(100)).await;
+		}
+
+		throughput.wait_for(Option::is_some).await.unwrap();
+		let rate = throughput.borrow().unwrap();
+		assert!((700_000..1_000_000).contains(&rate), "throughput: {rate}");
+
+		reader.abort();
+	}
+
+	// The server asks the client to migrate, while the existing subscription keeps working until the client closes.
+	async fn goaway(version: coding::Version) {
+		let mut fixture = Fixture::with_version(version).await;
+		let mut track = fixture.broadcast.create_track(Track::new("test"));
+		let mut remote = fixture.subscribe("test");
+
+		fixture.server.goaway(Some("https://relay.example/new"));
+		let uri = fixture.client.going_away().await;
+		assert_eq!(uri.as_deref(), Some("https://relay.example/new"));
+
+		// Either side can go away; an empty URI means reconnecting to the same URL.
+		fixture.client.goaway(None);
+		assert_eq!(fixture.server.going_away().await, Some(String::new()));
+
+		// The session is drained, not closed.
+		track.write_frame(Bytes::from_static(b"hello"));
+		let mut group = remote.next_group().await.unwrap().unwrap();
+		assert_eq!(group.read_frame().await.unwrap().unwrap(), "hello");
+
+		fixture.client.close(Error::Cancel);
+		assert!(fixture.server.closed().await.is_err());
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_goaway_lite() {
+		goaway(lite::Version::Draft03.coding()).await;
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_goaway_ietf() {
+		goaway(ietf::Version::Draft14.coding()).await;
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_goaway_unsupported() {
+		let fixture = Fixture::with_version(lite::Version::Draft02.coding()).await;
+
+		// Draft02 doesn't have a GOAWAY, so it's not sent instead of confusing the peer.
+		fixture.server.goaway(None);
+		assert!(
+			tokio::time::timeout(Duration::from_secs(10), fixture.client.going_away())
+				.await
+				.is_err()
+		);
+	}
+}
@@ -0,0 +1,246 @@
+use std::{
+	sync::{
+		Arc,
+		atomic::{AtomicU64, Ordering},
+	},
+	time::Duration,
+};
+
+use bytes::{Buf, BufMut, Bytes};
+
+use crate::coding;
+
+/// A snapshot of a [crate::Session]'s health, returned by [crate::Session::stats].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+	/// The negotiated version, see [crate::Session::version].
+	pub version: coding::Version,
+
+	/// The number of streams currently open in either direction, including the control stream.
+	pub streams: u64,
+
+	/// The number of bytes currently buffered for remote subscriptions, see [crate::Session::with_limits].
+	pub buffered: u64,
+
+	/// Statistics from the underlying transport, if it provides them.
+	///
+	/// This is [None] unless supplied via [crate::Session::with_transport_stats], such as for a WebSocket.
+	pub transport: Option<TransportStats>,
+}
+
+/// Statistics reported by the underlying transport, typically QUIC.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TransportStats {
+	/// The current best estimate of the round-trip time.
+	pub rtt: Duration,
+
+	/// The congestion window in bytes, limiting how much data can be in flight.
+	pub congestion_window: u64,
+
+	/// The total number of bytes sent, including headers and retransmissions.
+	pub bytes_sent: u64,
+
+	/// The total number of bytes received, including headers.
+	pub bytes_received: u64,
+
+	/// The total number of packets sent.
+	pub packets_sent: u64,
+
+	/// The total number of packets received.
+	pub packets_received: u64,
+
+	/// The total number of packets deemed lost.
+	pub packets_lost: u64,
+}
+
+// A session wrapper that counts the number of open streams and bytes received.
+#[derive(Clone)]
+pub(crate) struct Tracked<S> {
+	inner: S,
+	streams: Arc<AtomicU64>,
+	received: Arc<AtomicU64>,
+}
+
+impl<S: web_transport_trait::Session> Tracked<S> {
+	pub fn new(inner: S) -> Self {
+		Self {
+			inner,
+			streams: Default::default(),
+			received: Default::default(),
+		}
+	}
+
+	pub fn streams(&self) -> Arc<AtomicU64> {
+		self.streams.clone()
+	}
+
+	pub fn received(&self) -> Arc<AtomicU64> {
+		self.received.clone()
+	}
+
+	fn open(&self) -> Arc<StreamGuard> {
+		self.streams.fetch_add(1, Ordering::Relaxed);
+		Arc::new(StreamGuard(self.streams.clone()))
+	}
+}
+
+// Decrements the stream count once both halves of a stream are dropped.
+struct StreamGuard(Arc<AtomicU64>);
+
+impl Drop for StreamGuard {
+	fn drop(&mut self) {
+		self.0.fetch_sub(1, Ordering::Relaxed);
+	}
+}
+
+impl<S: web_transport_trait::Session> web_transport_trait::Session for Tracked<S> {
+	type SendStream = TrackedSend<S::SendStream>;
+	type RecvStream = TrackedRecv<S::RecvStream>;
+	type Error = S::Error;
+
+	async fn accept_uni(&self) -> Result<Self::RecvStream, Self::Error> {
+		let recv = self.inner.accept_uni().await?;
+		Ok(TrackedRecv::new(recv, self.open(), self.received.clone()))
+	}
+
+	async fn accept_bi(&self) -> Result<(Self::SendStream, Self::RecvStream), Self::Error> {
+		let (send, recv) = self.inner.accept_bi().await?;
+		let guard = self.open();
+		Ok((
+			TrackedSend::new(send, guard.clone()),
+			TrackedRecv::new(recv, guard, self.received.clone()),
+		))
+	}
+
+	async fn open_bi(&self) -> Result<(Self::SendStream, Self::RecvStream), Self::Error> {
+		let (send, recv) = self.inner.open_bi().await?;
+		let guard = self.open();
+		Ok((
+			TrackedSend::new(send, guard.clone()),
+			TrackedRecv::new(recv, guard, self.received.clone()),
+		))
+	}
+
+	async fn open_uni(&self) -> Result<Self::SendStream, Self::Error> {
+		let send = self.inner.open_uni().await?;
+		Ok(TrackedSend::new(send, self.open()))
+	}
+
+	fn send_datagram(&self, payload: Bytes) -> Result<(), Self::Error> {
+		self.inner.send_datagram(payload)
+	}
+
+	async fn recv_datagram(&self) -> Result<Bytes, Self::Error> {
+		self.inner.recv_datagram().await
+	}
+
+	fn max_datagram_size(&self) -> usize {
+		self.inner.max_datagram_size()
+	}
+
+	fn close(&self, code: u32, reason: &str) {
+		self.inner.close(code, reason)
+	}
+
+	async fn closed(&self) -> Self::Error {
+		self.inner.closed().await
+	}
+}
+
+pub(crate) struct TrackedSend<T> {
+	inner: T,
+	_guard: Arc<StreamGuard>,
+}
+
+impl<T> TrackedSend<T> {
+	fn new(inner: T, guard: Arc<StreamGuard>) -> Self {
+		Self { inner, _guard: guard }
+	}
+}
+
+impl<T: web_transport_trait::SendStream> web_transport_trait::SendStream for TrackedSend<T> {
+	type Error = T::Error;
+
+	async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+		self.inner.write(buf).await
+	}
+
+	async fn write_buf<B: Buf + web_transport_trait::MaybeSend>(&mut self, buf: &mut B) -> Result<usize, Self::Error> {
+		self.inner.write_buf(buf).await
+	}
+
+	async fn write_chunk(&mut self, chunk: Bytes) -> Result<(), Self::Error> {
+		self.inner.write_chunk(chunk).await
+	}
+
+	fn set_priority(&mut self, order: u8) {
+		self.inner.set_priority(order)
+	}
+
+	fn finish(&mut self) -> Result<(), Self::Error> {
+		self.inner.finish()
+	}
+
+	fn reset(&mut self, code: u32) {
+		self.inner.reset(code)
+	}
+
+	async fn closed(&mut self) -> Result<(), Self::Error> {
+		self.inner.closed().await
+	}
+}
+
+pub(crate) struct TrackedRecv<T> {
+	inner: T,
+	_guard: Arc<StreamGuard>,
+	received: Arc<AtomicU64>,
+}
+
+impl<T> TrackedRecv<T> {
+	fn new(inner: T, guard: Arc<StreamGuard>, received: Arc<AtomicU64>) -> Self {
+		Self {
+			inner,
+			_guard: guard,
+			received,
+		}
+	}
+
+	fn count(&self, size: usize) {
+		self.received.fetch_add(size as u64, Ordering::Relaxed);
+	}
+}
+
+impl<T: web_transport_trait::RecvStream> web_transport_trait::RecvStream for TrackedRecv<T> {
+	type Error = T::Error;
+
+	async fn read(&mut self, dst: &mut [u8]) -> Result<Option<usize>, Self::Error> {
+		let size = self.inner.read(dst).await?;
+		self.count(size.unwrap_or_default());
+		Ok(size)
+	}
+
+	async fn read_buf<B: BufMut + web_transport_trait::MaybeSend>(
+		&mut self,
+		buf: &mut B,
+	) -> Result<Option<usize>, Self::Error> {
+		let size = self.inner.read_buf(buf).await?;
+		self.count(size.unwrap_or_default());
+		Ok(size)
+	}
+
+	async fn read_chunk(&mut self, max: usize) -> Result<Option<Bytes>, Self::Error> {
+		let chunk = self.inner.read_chunk(max).await?;
+		self.count(chunk.as_ref().map(Bytes::len).unwrap_or_default());
+		Ok(chunk)
+	}
+
+	fn stop(&mut self, code: u32) {
+		self.inner.stop(code)
+	}
+
+	async fn closed(&mut self) -> Result<(), Self::Error> {
+		self.inner.closed().await
+	}
+}
@@ -0,0 +1,616 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Context;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64;
+use rustls::pki_types::{CertificateDer, PrivatePkcs8KeyDer};
+use rustls::sign::{CertifiedKey, SigningKey};
+use serde::Deserialize;
+use serde_json::json;
+use url::Url;
+
+use crate::crypto;
+
+const LETS_ENCRYPT: &str = "https://acme-v02.api.letsencrypt.org/directory";
+
+// Renew certificates when they have less than this much validity remaining.
+const RENEW_BEFORE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+// How long to wait before retrying after a failed order.
+const RETRY_DELAY: Duration = Duration::from_secs(10 * 60);
+
+/// Automatic certificate provisioning via ACME (ex. Let's Encrypt).
+///
+/// Certificates are obtained using the HTTP-01 challenge, so [AcmeChallenges] must be served on port 80.
+/// The moq-relay serves them via its HTTP listener (`--web-http-listen`).
+#[derive(clap::Args, Clone, Default, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, default)]
+#[non_exhaustive]
+pub struct AcmeConfig {
+	/// Obtain a certificate for the given hostnames via ACME.
+	#[arg(
+		long = "tls-acme-domain",
+		id = "tls-acme-domain",
+		value_delimiter = ',',
+		env = "MOQ_SERVER_TLS_ACME_DOMAIN"
+	)]
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub domain: Vec<String>,
+
+	/// The ACME directory URL (default: Let's Encrypt)
+	#[arg(
+		long = "tls-acme-directory",
+		id = "tls-acme-directory",
+		env = "MOQ_SERVER_TLS_ACME_DIRECTORY"
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub directory: Option<Url>,
+
+	/// Contact URLs for the ACME account, ex. `mailto:admin@example.com`.
+	#[arg(
+		long = "tls-acme-contact",
+		id = "tls-acme-contact",
+		value_delimiter = ',',
+		env = "MOQ_SERVER_TLS_ACME_CONTACT"
+	)]
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub contact: Vec<String>,
+
+	/// Store the account key and certificates in this directory.
+	///
+	/// Without a cache, a new certificate is ordered on every restart which will quickly hit rate limits.
+	#[arg(long = "tls-acme-cache", id = "tls-acme-cache", env = "MOQ_SERVER_TLS_ACME_CACHE")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub cache: Option<PathBuf>,
+
+	/// Trust the given PEM root when connecting to the ACME directory.
+	///
+	/// Useful for testing against a local ACME server such as pebble.
+	#[arg(long = "tls-acme-root", id = "tls-acme-root", env = "MOQ_SERVER_TLS_ACME_ROOT")]
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub root: Vec<PathBuf>,
+}
+
+/// The pending HTTP-01 challenges, mapping each token to its key authorization.
+///
+/// Serve these at `http://<domain>/.well-known/acme-challenge/<token>` with a `200 OK`.
+#[derive(Clone, Default, Debug)]
+pub struct AcmeChallenges(Arc<Mutex<HashMap<String, String>>>);
+
+impl AcmeChallenges {
+	/// Returns the key authorization for the given token, if the challenge is pending.
+	pub fn get(&self, token: &str) -> Option<String> {
+		self.0.lock().unwrap().get(token).cloned()
+	}
+
+	fn insert(&self, token: String, authorization: String) {
+		self.0.lock().unwrap().insert(token, authorization);
+	}
+
+	fn remove(&self, token: &str) {
+		self.0.lock().unwrap().remove(token);
+	}
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Directory {
+	new_nonce: Url,
+	new_account: Url,
+	new_order: Url,
+}
+
+#[derive(Deserialize)]
+struct Order {
+	authorizations: Vec<Url>,
+	finalize: Url,
+	certificate: Option<Url>,
+}
+
+#[derive(Deserialize)]
+struct Authorization {
+	status: String,
+	challenges: Vec<Challenge>,
+}
+
+#[derive(Deserialize)]
+struct Challenge {
+	#[serde(rename = "type")]
+	kind: String,
+	url: Url,
+	token: String,
+}
+
+pub(crate) struct Acme {
+	config: AcmeConfig,
+	provider: crypto::Provider,
+	challenges: AcmeChallenges,
+	// Built on first use, since reading the roots shouldn't block the runtime.
+	http: tokio::sync::OnceCell<reqwest::Client>,
+}
+
+impl Acme {
+	pub fn new(config: AcmeConfig, provider: crypto::Provider, challenges: AcmeChallenges) -> Self {
+		Self {
+			config,
+			provider,
+			challenges,
+			http: Default::default(),
+		}
+	}
+
+	async fn http(&self) -> anyhow::Result<&reqwest::Client> {
+		self.http
+			.get_or_try_init(async || {
+				let mut http = reqwest::Client::builder().user_agent(concat!("moq-native/", env!("CARGO_PKG_VERSION")));
+
+				for root in &self.config.root {
+					let pem = tokio::fs::read(root).await.context("failed to read ACME root")?;
+					http =
+						http.add_root_certificate(reqwest::Certificate::from_pem(&pem).context("invalid ACME root")?);
+				}
+
+				anyhow::Ok(http.build()?)
+			})
+			.await
+	}
+
+	/// Obtain a certificate and keep it renewed, calling `install` each time.
+	pub async fn run(self, install: impl Fn(Arc<CertifiedKey>)) {
+		loop {
+			let delay = match self.renew().await {
+				Ok((cert, expires)) => {
+					install(cert);
+
+					let remaining = expires.duration_since(std::time::SystemTime::now()).unwrap_or_default();
+					remaining.saturating_sub(RENEW_BEFORE).max(RETRY_DELAY)
+				}
+				Err(err) => {
+					tracing::warn!(err = format!("{err:#}"), "failed to obtain ACME certificate");
+					RETRY_DELAY
+				}
+			};
+
+			tokio::time::sleep(delay).await;
+		}
+	}
+
+	// Returns a cached certificate if it's still fresh, otherwise orders a new one.
+	async fn renew(&self) -> anyhow::Result<(Arc<CertifiedKey>, std::time::SystemTime)> {
+		if let Some(cache) = &self.config.cache
+			&& let Ok(chain) = tokio::fs::read(cache.join("cert.pem")).await
+			&& let Ok(key) = tokio::fs::read(cache.join("key.pem")).await
+		{
+			let (cert, expires) = self.certified_key(&chain, &key)?;
+			let remaining = expires.duration_since(std::time::SystemTime::now()).unwrap_or_default();
+
+			if remaining > RENEW_BEFORE {
+				tracing::info!(domain = ?self.config.domain, ?remaining, "using cached ACME certificate");
+				return Ok((cert, expires));
+			}
+		}
+
+		let (chain, key) = self.order().await?;
+
+		if let Some(cache) = &self.config.cache {
+			tokio::fs::create_dir_all(cache)
+				.await
+				.context("failed to create ACME cache")?;
+			tokio::fs::write(cache.join("cert.pem"), &chain)
+				.await
+				.context("failed to write ACME certificate")?;
+			tokio::fs::write(cache.join("key.pem"), &key)
+				.await
+				.context("failed to write ACME key")?;
+		}
+
+		self.certified_key(chain.as_bytes(), key.as_bytes())
+	}
+
+	fn certified_key(&self, chain: &[u8], key: &[u8]) -> anyhow::Result<(Arc<CertifiedKey>, std::time::SystemTime)> {
+		let chain: Vec<CertificateDer> = rustls_pemfile::certs(&mut &chain[..])
+			.collect::<Result<_, _>>()
+			.context("failed to read certs")?;
+		let leaf = chain.first().context("missing certificate")?;
+
+		let (_, parsed) = x509_parser::parse_x509_certificate(leaf).context("failed to parse certificate")?;
+		let expires =
+			std::time::UNIX_EPOCH + Duration::from_secs(parsed.validity().not_after.timestamp().max(0) as u64);
+
+		let key = rustls_pemfile::private_key(&mut &key[..])?.context("missing private key")?;
+		let key = self.provider.key_provider.load_private_key(key)?;
+
+		Ok((Arc::new(CertifiedKey::new(chain, key)), expires))
+	}
+
+	// Perform an ACME order, returning the PEM certificate chain and key.
+	async fn order(&self) -> anyhow::Result<(String, String)> {
+		let directory = self
+			.config
+			.directory
+			.clone()
+			.unwrap_or_else(|| LETS_ENCRYPT.parse().unwrap());
+		tracing::info!(domain = ?self.config.domain, %directory, "ordering ACME certificate");
+
+		let http = self.http().await?;
+		let directory: Directory = http
+			.get(directory)
+			.send()
+			.await?
+			.error_for_status()?
+			.json()
+			.await
+			.context("invalid ACME directory")?;
+
+		let mut account = Account::new(self, http, &directory).await?;
+
+		let identifiers: Vec<_> = self
+			.config
+			.domain
+			.iter()
+			.map(|domain| json!({ "type": "dns", "value": domain }))
+			.collect();
+
+		let resp = account
+			.post(&directory.new_order, Some(json!({ "identifiers": identifiers })))
+			.await?;
+		let order_url: Url = location(&resp)?;
+		let order: Order = resp.json().await?;
+
+		for authorization in &order.authorizations {
+			self.authorize(&mut account, authorization).await?;
+		}
+
+		let order: Order = account.poll(&order_url, "ready").await?;
+
+		// Generate a new key for each certificate.
+		let key = rcgen::KeyPair::generate()?;
+		let csr = rcgen::CertificateParams::new(self.config.domain.clone())?.serialize_request(&key)?;
+
+		account
+			.post(&order.finalize, Some(json!({ "csr": BASE64.encode(csr.der()) })))
+			.await?;
+
+		let order: Order = account.poll(&order_url, "valid").await?;
+		let certificate = order.certificate.context("missing certificate URL")?;
+
+		let chain = account.post(&certificate, None).await?.text().await?;
+		tracing::info!(domain = ?self.config.domain, "obtained ACME certificate");
+
+		Ok((chain, key.serialize_pem()))
+	}
+
+	async fn authorize(&self, account: &mut Account<'_>, url: &Url) -> anyhow::Result<()> {
+		let authorization: Authorization = account.post(url, None).await?.json().await?;
+		if authorization.status == "valid" {
+			return Ok(());
+		}
+
+		let challenge = authorization
+			.challenges
+			.into_iter()
+			.find(|challenge| challenge.kind == "http-01")
+			.context("no http-01 challenge offered")?;
+
+		let authorization = format!("{}.{}", challenge.token, account.thumbprint);
+		self.challenges.insert(challenge.token.clone(), authorization);
+
+		// Tell the server we're ready, then wait for it to validate the challenge.
+		let res = async {
+			account.post(&challenge.url, Some(json!({}))).await?;
+			account.poll::<Authorization>(url, "valid").await
+		}
+		.await;
+
+		self.challenges.remove(&challenge.token);
+		res.map(|_| ())
+	}
+}
+
+// An ACME account, used to sign requests.
+struct Account<'a> {
+	http: &'a reqwest::Client,
+	key: Arc<dyn SigningKey>,
+	jwk: serde_json::Value,
+	thumbprint: String,
+	kid: Option<Url>,
+	nonce: Option<String>,
+	new_nonce: Url,
+}
+
+impl<'a> Account<'a> {
+	async fn new(acme: &Acme, http: &'a reqwest::Client, directory: &Directory) -> anyhow::Result<Self> {
+		let cached = acme.config.cache.as_ref().map(|cache| cache.join("account.pem"));
+
+		let pem = match &cached {
+			Some(path) => tokio::fs::read_to_string(path).await.ok(),
+			None => None,
+		};
+
+		let key = match pem {
+			Some(pem) => rcgen::KeyPair::from_pem(&pem).context("invalid ACME account key")?,
+			None => {
+				let key = rcgen::KeyPair::generate_for(&rcgen::PKCS_ECDSA_P256_SHA256)?;
+				if let Some(path) = &cached {
+					tokio::fs::create_dir_all(path.parent().unwrap())
+						.await
+						.context("failed to create ACME cache")?;
+					tokio::fs::write(path, key.serialize_pem())
+						.await
+						.context("failed to write ACME account key")?;
+				}
+				key
+			}
+		};
+
+		let (jwk, thumbprint) = jwk(&acme.provider, key.public_key_raw())?;
+
+		let der = PrivatePkcs8KeyDer::from(key.serialize_der());
+		let key = acme.provider.key_provider.load_private_key(der.into())?;
+
+		let mut account = Self {
+			http,
+			key,
+			jwk,
+			thumbprint,
+			kid: None,
+			nonce: None,
+			new_nonce: directory.new_nonce.clone(),
+		};
+
+		// Returns the existing account if the key is already registered.
+		let resp = account
+			.post(
+				&directory.new_account,
+				Some(json!({ "termsOfServiceAgreed": true, "contact": acme.config.contact })),
+			)
+			.await?;
+		account.kid = Some(location(&resp)?);
+
+		Ok(account)
+	}
+
+	// Send a signed request, with no payload performing a POST-as-GET.
+	async fn post(&mut self, url: &Url, payload: Option<serde_json::Value>) -> anyhow::Result<reqwest::Response> {
+		let payload = payload
+			.map(|payload| BASE64.encode(payload.to_string()))
+			.unwrap_or_default();
+
+		// Retry once if the nonce was rejected.
+		for _ in 0..2 {
+			let nonce = match self.nonce.take() {
+				Some(nonce) => nonce,
+				None => self.nonce().await?,
+			};
+
+			let mut protected = json!({ "alg": "ES256", "nonce": nonce, "url": url });
+			match &self.kid {
+				Some(kid) => protected["kid"] = json!(kid),
+				None => protected["jwk"] = self.jwk.clone(),
+			}
+			let protected = BASE64.encode(protected.to_string());
+
+			let signature = self.sign(format!("{protected}.{payload}").as_bytes())?;
+			let body = json!({ "protected": protected, "payload": payload, "signature": signature });
+
+			let resp = self
+				.http
+				.post(url.clone())
+				.header("content-type", "application/jose+json")
+				.body(body.to_string())
+				.send()
+				.await?;
+
+			self.nonce = resp
+				.headers()
+				.get("replay-nonce")
+				.and_then(|nonce| nonce.to_str().ok())
+				.map(String::from);
+
+			if resp.status().is_success() {
+				return Ok(resp);
+			}
+
+			let status = resp.status();
+			let problem: serde_json::Value = resp.json().await.unwrap_or_default();
+			if problem["type"] != "urn:ietf:params:acme:error:badNonce" {
+				anyhow::bail!("ACME request failed: {status}: {problem}");
+			}
+		}
+
+		anyhow::bail!("ACME server rejected nonce")
+	}
+
+	// Poll the resource until it has the desired status.
+	async fn poll<T: for<'de> Deserialize<'de>>(&mut self, url: &Url, status: &str) -> anyhow::Result<T> {
+		for _ in 0..30 {
+			let resp: serde_json::Value = self.post(url, None).await?.json().await?;
+			match resp["status"].as_str() {
+				Some(current) if current == status => return Ok(serde_json::from_value(resp)?),
+				Some("invalid") => anyhow::bail!("ACME validation failed: {resp}"),
+				_ => tokio::time::sleep(Duration::from_secs(2)).await,
+			}
+		}
+
+		anyhow::bail!("timed out waiting for ACME status: {status}")
+	}
+
+	async fn nonce(&self) -> anyhow::Result<String> {
+		let resp = self
+			.http
+			.head(self.new_nonce.clone())
+			.send()
+			.await?
+			.error_for_status()?;
+		let nonce = resp.headers().get("replay-nonce").context("missing nonce")?;
+		Ok(nonce.to_str()?.to_string())
+	}
+
+	fn sign(&self, message: &[u8]) -> anyhow::Result<String> {
+		sign(self.key.as_ref(), message)
+	}
+}
+
+// Returns the JWK for a P-256 public key, encoded as an uncompressed point, and its RFC 7638 thumbprint.
+fn jwk(provider: &crypto::Provider, point: &[u8]) -> anyhow::Result<(serde_json::Value, String)> {
+	// The public key is an uncompressed point: 0x04 || x || y
+	anyhow::ensure!(point.len() == 65 && point[0] == 0x04, "ACME account key must be P-256");
+
+	// The members must be in lexicographic order for the thumbprint.
+	let jwk = json!({
+		"crv": "P-256",
+		"kty": "EC",
+		"x": BASE64.encode(&point[1..33]),
+		"y": BASE64.encode(&point[33..]),
+	});
+	let thumbprint = BASE64.encode(crypto::sha256(provider, jwk.to_string().as_bytes()));
+
+	Ok((jwk, thumbprint))
+}
+
+// Sign a JWS with ES256, returning the base64url encoded signature.
+fn sign(key: &dyn SigningKey, message: &[u8]) -> anyhow::Result<String> {
+	let signer = key
+		.choose_scheme(&[rustls::SignatureScheme::ECDSA_NISTP256_SHA256])
+		.context("unsupported ACME account key")?;
+	let der = signer.sign(message)?;
+
+	Ok(BASE64.encode(ecdsa_der_to_raw(&der)?))
+}
+
+fn location(resp: &reqwest::Response) -> anyhow::Result<Url> {
+	let location = resp.headers().get("location").context("missing location")?;
+	Ok(location.to_str()?.parse()?)
+}
+
+// JWS requires the raw r || s encoding instead of the ASN.1 DER produced by rustls.
+fn ecdsa_der_to_raw(der: &[u8]) -> anyhow::Result<[u8; 64]> {
+	// SEQUENCE { INTEGER r, INTEGER s }, each at most 33 bytes so lengths are a single byte.
+	let (&[0x30, len], rest) = der.split_at_checked(2).context("invalid signature")? else {
+		anyhow::bail!("invalid signature");
+	};
+	anyhow::ensure!(rest.len() == len as usize, "invalid signature");
+
+	let mut raw = [0u8; 64];
+	let mut rest = rest;
+
+	for part in raw.chunks_mut(32) {
+		let (&[0x02, len], tail) = rest.split_at_checked(2).context("invalid signature")? else {
+			anyhow::bail!("invalid signature");
+		};
+		let (int, tail) = tail.split_at_checked(len as usize).context("invalid signature")?;
+
+		// Strip the leading zero used to keep the integer positive.
+		let int = int.strip_prefix(&[0]).unwrap_or(int);
+		anyhow::ensure!(int.len() <= 32, "invalid signature");
+		part[32 - int.len()..].copy_from_slice(int);
+
+		rest = tail;
+	}
+
+	anyhow::ensure!(rest.is_empty(), "invalid signature");
+
+	Ok(raw)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	// Re-encode a raw r || s signature as DER, the inverse of ecdsa_der_to_raw.
+	fn raw_to_der(raw: &[u8]) -> Vec<u8> {
+		let mut ints = Vec::new();
+		for part in raw.chunks(32) {
+			let start = part.iter().position(|&b| b != 0).unwrap_or(part.len() - 1);
+			let mut int = part[start..].to_vec();
+			if int[0] & 0x80 != 0 {
+				int.insert(0, 0);
+			}
+			ints.extend([0x02, int.len() as u8]);
+			ints.extend(int);
+		}
+
+		let mut der = vec![0x30, ints.len() as u8];
+		der.extend(ints);
+		der
+	}
+
+	#[test]
+	fn der_to_raw() {
+		// r has a leading zero to keep it positive, and s is short.
+		let mut r = vec![0x00, 0x80];
+		r.extend([0x11; 31]);
+		let s = [0x01, 0x02];
+
+		let mut der = vec![0x30, (4 + r.len() + s.len()) as u8, 0x02, r.len() as u8];
+		der.extend(&r);
+		der.extend([0x02, s.len() as u8]);
+		der.extend(s);
+
+		let raw = ecdsa_der_to_raw(&der).unwrap();
+		assert_eq!(raw[..32], r[1..]);
+		assert_eq!(raw[32..62], [0; 30]);
+		assert_eq!(raw[62..], s);
+		assert_eq!(raw_to_der(&raw), der);
+
+		// Truncated, wrong tags, trailing data, and oversized integers are rejected.
+		assert!(ecdsa_der_to_raw(&der[..der.len() - 1]).is_err());
+		assert!(ecdsa_der_to_raw(&[0x31, 0x00]).is_err());
+		assert!(ecdsa_der_to_raw(&[0x30, 0x06, 0x02, 0x01, 0x01, 0x03, 0x01, 0x01]).is_err());
+		assert!(ecdsa_der_to_raw(&[0x30, 0x07, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01, 0x00]).is_err());
+
+		let mut long = vec![0x30, 0x26, 0x02, 0x21];
+		long.extend([0x11; 33]);
+		long.extend([0x02, 0x01, 0x01]);
+		assert!(ecdsa_der_to_raw(&long).is_err());
+	}
+
+	#[test]
+	fn thumbprint() {
+		// The P-256 key from RFC 7515 Appendix A.3.
+		let x = BASE64.decode("f83OJ3D2xF1Bg8vub9tLe1gHMzV76e8Tus9uPHvRVEU").unwrap();
+		let y = BASE64.decode("x_FEzRu9m36HLN_tue659LNpXW6pCyStikYjKIWI5a0").unwrap();
+		let point = [&[0x04][..], &x, &y].concat();
+
+		let (jwk, thumbprint) = jwk(&crypto::provider(), &point).unwrap();
+		assert_eq!(jwk["kty"], "EC");
+		assert_eq!(jwk["x"], "f83OJ3D2xF1Bg8vub9tLe1gHMzV76e8Tus9uPHvRVEU");
+		assert_eq!(thumbprint, "oKIywvGUpTVTyxMQ3bwIIeQUudfr_CkLMjCE19ECD-U");
+
+		// Only uncompressed P-256 points are supported.
+		assert!(super::jwk(&crypto::provider(), &point[..33]).is_err());
+	}
+
+	#[test]
+	fn signature() {
+		let provider = crypto::provider();
+
+		let key = rcgen::KeyPair::generate_for(&rcgen::PKCS_ECDSA_P256_SHA256).unwrap();
+		let point = key.public_key_raw().to_vec();
+		let der = PrivatePkcs8KeyDer::from(key.serialize_der());
+		let key = provider.key_provider.load_private_key(der.into()).unwrap();
+
+		let message = b"protected.payload";
+		let signature = BASE64.decode(sign(key.as_ref(), message).unwrap()).unwrap();
+		assert_eq!(signature.len(), 64);
+
+		// The raw signature verifies against the public key once converted back to DER.
+		let (_, algorithms) = provider
+			.signature_verification_algorithms
+			.mapping
+			.iter()
+			.find(|(scheme, _)| *scheme == rustls::SignatureScheme::ECDSA_NISTP256_SHA256)
+			.unwrap();
+		let verify = |message: &[u8]| {
+			algorithms
+				.iter()
+				.any(|alg| alg.verify_signature(&point, message, &raw_to_der(&signature)).is_ok())
+		};
+
+		assert!(verify(message));
+		assert!(!verify(b"protected.tampered"));
+	}
+}
@@ -1,8 +1,10 @@
-use crate::crypto;
+use crate::priority::{Prioritized, QuinnSendStream};
+use crate::{CongestionControl, crypto};
 use anyhow::Context;
+use futures::stream::{FuturesUnordered, StreamExt};
 use rustls::RootCertStore;
-use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
-use std::collections::HashSet;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::PathBuf;
 use std::sync::{LazyLock, Mutex};
 use std::{fs, io, net, sync::Arc, time};
@@ -14,6 +16,9 @@ use web_transport_ws::{tokio_tungstenite, tungstenite};
 // Track servers (hostname:port) where WebSocket won the race, so we won't give QUIC a headstart next time
 static WEBSOCKET_WON: LazyLock<Mutex<HashSet<(String, u16)>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
 
+// Track whether IPv6 (true) or IPv4 (false) won the race for each server (hostname:port), so we try it first next time
+static FAMILY_WON: LazyLock<Mutex<HashMap<(String, u16), bool>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
 /// TLS configuration for the client.
 #[derive(Clone, Default, Debug, clap::Args, serde::Serialize, serde::Deserialize)]
 #[serde(default, deny_unknown_fields)]
@@ -38,6 +43,52 @@ pub struct ClientTls {
 		action = clap::ArgAction::SetTrue
 	)]
 	pub disable_verify: Option<bool>,
+
+	/// Present this certificate chain to the server, encoded as PEM.
+	///
+	/// Only needed when the server requires client certificates (mTLS).
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[arg(
+		id = "tls-client-cert",
+		long = "tls-client-cert",
+		requires = "tls-client-key",
+		env = "MOQ_CLIENT_TLS_CERT"
+	)]
+	pub cert: Option<PathBuf>,
+
+	/// The private key for the client certificate, encoded as PEM.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[arg(
+		id = "tls-client-key",
+		long = "tls-client-key",
+		requires = "tls-client-cert",
+		env = "MOQ_CLIENT_TLS_KEY"
+	)]
+	pub key: Option<PathBuf>,
+}
+
+impl ClientTls {
+	// Load the client certificate chain and private key, if configured.
+	fn identity(&self) -> anyhow::Result<Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>> {
+		let (cert, key) = match (&self.cert, &self.key) {
+			(Some(cert), Some(key)) => (cert, key),
+			(None, None) => return Ok(None),
+			_ => anyhow::bail!("must provide both client cert and key"),
+		};
+
+		let chain = fs::File::open(cert).context("failed to open client cert file")?;
+		let mut chain = io::BufReader::new(chain);
+		let chain: Vec<CertificateDer> = rustls_pemfile::certs(&mut chain)
+			.collect::<Result<_, _>>()
+			.context("failed to read client certs")?;
+		anyhow::ensure!(!chain.is_empty(), "could not find client certificate");
+
+		let key = fs::File::open(key).context("failed to open client key file")?;
+		let mut key = io::BufReader::new(key);
+		let key = rustls_pemfile::private_key(&mut key)?.context("missing client private key")?;
+
+		Ok(Some((chain, key)))
+	}
 }
 
 /// WebSocket configuration for the client.
@@ -59,6 +110,55 @@ pub struct ClientWebSocket {
 	pub delay: Option<time::Duration>,
 }
 
+/// Reconnection configuration for the client, see [Client::connect_reconnecting].
+#[derive(Clone, Debug, clap::Args, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct ClientReconnect {
+	/// Delay before the first reconnect attempt, doubling after each failure (default: 1s)
+	#[arg(
+		id = "reconnect-delay",
+		long = "reconnect-delay",
+		env = "MOQ_CLIENT_RECONNECT_DELAY",
+		default_value = "1s",
+		value_parser = humantime::parse_duration,
+	)]
+	#[serde(with = "humantime_serde")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub delay: Option<time::Duration>,
+
+	/// The maximum delay between reconnect attempts (default: 30s)
+	#[arg(
+		id = "reconnect-max-delay",
+		long = "reconnect-max-delay",
+		env = "MOQ_CLIENT_RECONNECT_MAX_DELAY",
+		default_value = "30s",
+		value_parser = humantime::parse_duration,
+	)]
+	#[serde(with = "humantime_serde")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub max_delay: Option<time::Duration>,
+
+	/// Give up after this many consecutive failed attempts (default: unlimited)
+	#[arg(
+		id = "reconnect-attempts",
+		long = "reconnect-attempts",
+		env = "MOQ_CLIENT_RECONNECT_ATTEMPTS"
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub attempts: Option<u32>,
+}
+
+impl Default for ClientReconnect {
+	fn default() -> Self {
+		Self {
+			delay: Some(time::Duration::from_secs(1)),
+			max_delay: Some(time::Duration::from_secs(30)),
+			attempts: None,
+		}
+	}
+}
+
 impl Default for ClientWebSocket {
 	fn default() -> Self {
 		Self {
@@ -67,6 +167,159 @@ impl Default for ClientWebSocket {
 	}
 }
 
+/// QUIC transport configuration for the client.
+#[derive(clap::Args, Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, default)]
+#[non_exhaustive]
+pub struct ClientQuic {
+	/// Close the connection after this much inactivity (default: 10s)
+	/// Set to 0 to disable the idle timeout.
+	#[arg(
+		id = "quic-idle-timeout",
+		long = "quic-idle-timeout",
+		env = "MOQ_CLIENT_QUIC_IDLE_TIMEOUT",
+		default_value = "10s",
+		value_parser = humantime::parse_duration,
+	)]
+	#[serde(with = "humantime_serde")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub idle_timeout: Option<time::Duration>,
+
+	/// Send a keep-alive at this interval to prevent the idle timeout (default: 4s)
+	/// Set to 0 to disable keep-alives.
+	#[arg(
+		id = "quic-keep-alive",
+		long = "quic-keep-alive",
+		env = "MOQ_CLIENT_QUIC_KEEP_ALIVE",
+		default_value = "4s",
+		value_parser = humantime::parse_duration,
+	)]
+	#[serde(with = "humantime_serde")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub keep_alive: Option<time::Duration>,
+
+	/// Enable path MTU discovery, which is disabled by default.
+	#[arg(
+		id = "quic-mtu-discovery",
+		long = "quic-mtu-discovery",
+		env = "MOQ_CLIENT_QUIC_MTU_DISCOVERY",
+		action = clap::ArgAction::SetTrue
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub mtu_discovery: Option<bool>,
+
+	/// The maximum number of unacknowledged bytes per stream.
+	#[arg(
+		id = "quic-stream-window",
+		long = "quic-stream-window",
+		env = "MOQ_CLIENT_QUIC_STREAM_WINDOW"
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub stream_window: Option<u64>,
+
+	/// The maximum number of unacknowledged bytes across all streams.
+	#[arg(
+		id = "quic-connection-window",
+		long = "quic-connection-window",
+		env = "MOQ_CLIENT_QUIC_CONNECTION_WINDOW"
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub connection_window: Option<u64>,
+
+	/// The maximum number of concurrent unidirectional streams the peer may open.
+	#[arg(
+		id = "quic-max-uni-streams",
+		long = "quic-max-uni-streams",
+		env = "MOQ_CLIENT_QUIC_MAX_UNI_STREAMS"
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub max_uni_streams: Option<u64>,
+
+	/// The congestion controller to use (default: cubic)
+	#[arg(
+		id = "quic-congestion",
+		long = "quic-congestion",
+		env = "MOQ_CLIENT_QUIC_CONGESTION",
+		value_enum
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub congestion: Option<CongestionControl>,
+
+	/// Delay before racing a connection to the next resolved address (default: 250ms)
+	/// Addresses alternate between IPv6 and IPv4, so a broken path for one family doesn't block the other.
+	#[arg(
+		id = "quic-attempt-delay",
+		long = "quic-attempt-delay",
+		env = "MOQ_CLIENT_QUIC_ATTEMPT_DELAY",
+		default_value = "250ms",
+		value_parser = humantime::parse_duration,
+	)]
+	#[serde(with = "humantime_serde")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub attempt_delay: Option<time::Duration>,
+}
+
+impl Default for ClientQuic {
+	fn default() -> Self {
+		Self {
+			idle_timeout: Some(time::Duration::from_secs(10)),
+			keep_alive: Some(time::Duration::from_secs(4)),
+			mtu_discovery: None,
+			stream_window: None,
+			connection_window: None,
+			max_uni_streams: None,
+			congestion: None,
+			attempt_delay: Some(time::Duration::from_millis(250)),
+		}
+	}
+}
+
+impl ClientQuic {
+	pub(crate) fn transport(&self) -> anyhow::Result<Arc<quinn::TransportConfig>> {
+		crate::quic::Transport {
+			idle_timeout: self.idle_timeout,
+			keep_alive: self.keep_alive,
+			mtu_discovery: self.mtu_discovery.unwrap_or_default(),
+			stream_window: self.stream_window,
+			connection_window: self.connection_window,
+			max_uni_streams: self.max_uni_streams,
+			congestion: self.congestion.unwrap_or_default(),
+		}
+		.build()
+	}
+}
+
+/// Limits on the bytes buffered for our subscriptions, see [moq_lite::Session::with_limits].
+#[derive(Clone, Debug, Default, clap::Args, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct ClientLimits {
+	/// The maximum number of bytes buffered across all subscriptions (default: unlimited)
+	#[arg(id = "limits-session", long = "limits-session", env = "MOQ_CLIENT_LIMITS_SESSION")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub session: Option<u64>,
+
+	/// The maximum number of bytes buffered for each subscription (default: unlimited)
+	#[arg(id = "limits-track", long = "limits-track", env = "MOQ_CLIENT_LIMITS_TRACK")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub track: Option<u64>,
+
+	/// The maximum number of bytes buffered for each group (default: unlimited)
+	#[arg(id = "limits-group", long = "limits-group", env = "MOQ_CLIENT_LIMITS_GROUP")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub group: Option<u64>,
+}
+
+impl ClientLimits {
+	pub(crate) fn limits(&self) -> moq_lite::Limits {
+		moq_lite::Limits {
+			session: self.session,
+			track: self.track,
+			group: self.group,
+		}
+	}
+}
+
 /// Configuration for the MoQ client.
 #[derive(Clone, Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
 #[serde(deny_unknown_fields, default)]
@@ -81,6 +334,10 @@ pub struct ClientConfig {
 	)]
 	pub bind: net::SocketAddr,
 
+	#[command(flatten)]
+	#[serde(default)]
+	pub quic: ClientQuic,
+
 	#[command(flatten)]
 	#[serde(default)]
 	pub tls: ClientTls,
@@ -88,6 +345,14 @@ pub struct ClientConfig {
 	#[command(flatten)]
 	#[serde(default)]
 	pub websocket: ClientWebSocket,
+
+	#[command(flatten)]
+	#[serde(default)]
+	pub reconnect: ClientReconnect,
+
+	#[command(flatten)]
+	#[serde(default)]
+	pub limits: ClientLimits,
 }
 
 impl ClientConfig {
@@ -100,8 +365,11 @@ impl Default for ClientConfig {
 	fn default() -> Self {
 		Self {
 			bind: "[::]:0".parse().unwrap(),
+			quic: ClientQuic::default(),
 			tls: ClientTls::default(),
 			websocket: ClientWebSocket::default(),
+			reconnect: ClientReconnect::default(),
+			limits: ClientLimits::default(),
 		}
 	}
 }
@@ -114,7 +382,12 @@ pub struct Client {
 	pub quic: quinn::Endpoint,
 	pub tls: rustls::ClientConfig,
 	pub transport: Arc<quinn::TransportConfig>,
+	pub quic_attempt_delay: Option<time::Duration>,
 	pub websocket_delay: Option<time::Duration>,
+	pub reconnect_delay: Option<time::Duration>,
+	pub reconnect_max_delay: Option<time::Duration>,
+	pub reconnect_attempts: Option<u32>,
+	pub limits: moq_lite::Limits,
 	#[cfg(feature = "iroh")]
 	pub iroh: Option<iroh::Endpoint>,
 }
@@ -154,10 +427,17 @@ impl Client {
 		}
 
 		// Create the TLS configuration we'll use as a client (relay -> relay)
-		let mut tls = rustls::ClientConfig::builder_with_provider(provider.clone())
+		let tls = rustls::ClientConfig::builder_with_provider(provider.clone())
 			.with_protocol_versions(&[&rustls::version::TLS13])?
-			.with_root_certificates(roots)
-			.with_no_client_auth();
+			.with_root_certificates(roots);
+
+		// Present a client certificate if the server requires one.
+		let mut tls = match config.tls.identity()? {
+			Some((chain, key)) => tls
+				.with_client_auth_cert(chain, key)
+				.context("invalid client certificate")?,
+			None => tls.with_no_client_auth(),
+		};
 
 		// Allow disabling TLS verification altogether.
 		if config.tls.disable_verify.unwrap_or_default() {
@@ -169,13 +449,7 @@ impl Client {
 
 		let socket = std::net::UdpSocket::bind(config.bind).context("failed to bind UDP socket")?;
 
-		// TODO Validate the BBR implementation before enabling it
-		let mut transport = quinn::TransportConfig::default();
-		transport.max_idle_timeout(Some(time::Duration::from_secs(10).try_into().unwrap()));
-		transport.keep_alive_interval(Some(time::Duration::from_secs(4)));
-		//transport.congestion_controller_factory(Arc::new(quinn::congestion::BbrConfig::default()));
-		transport.mtu_discovery_config(None); // Disable MTU discovery
-		let transport = Arc::new(transport);
+		let transport = config.quic.transport()?;
 
 		// There's a bit more boilerplate to make a generic endpoint.
 		let runtime = quinn::default_runtime().context("no async runtime")?;
@@ -189,7 +463,12 @@ impl Client {
 			quic,
 			tls,
 			transport,
+			quic_attempt_delay: config.quic.attempt_delay,
 			websocket_delay: config.websocket.delay,
+			reconnect_delay: config.reconnect.delay,
+			reconnect_max_delay: config.reconnect.max_delay,
+			reconnect_attempts: config.reconnect.attempts,
+			limits: config.limits.limits(),
 			#[cfg(feature = "iroh")]
 			iroh: None,
 		})
@@ -202,6 +481,11 @@ impl Client {
 	}
 
 	/// Establish a WebTransport/QUIC connection followed by a MoQ handshake.
+	///
+	/// The URL scheme determines the protocol:
+	/// - `https`: WebTransport, or `http` to fetch the certificate fingerprint first (insecure).
+	/// - `moql` or `moqt`: moq-lite or IETF MoQ over raw QUIC.
+	/// - `moq`: offer all of the above and let the server choose; see [moq_lite::Session::protocol] and [moq_lite::Session::version].
 	pub async fn connect(
 		&self,
 		url: Url,
@@ -210,14 +494,45 @@ impl Client {
 	) -> anyhow::Result<moq_lite::Session> {
 		#[cfg(feature = "iroh")]
 		if crate::iroh::is_iroh_url(&url) {
-			let session = self.connect_iroh(url).await?;
-			let session = moq_lite::Session::connect(session, publish, subscribe).await?;
-			return Ok(session);
+			let session = self.connect_iroh(url.clone()).await?;
+			let connection = (*session).clone();
+			let session = self.handshake(session, &url, publish, subscribe).await?;
+			return Ok(session.with_transport_stats(move || crate::iroh::transport_stats(&connection)));
 		}
 
 		let session = self.connect_quic(url).await?;
-		let session = moq_lite::Session::connect(session, publish, subscribe).await?;
-		Ok(session)
+		let connection = (*session).clone();
+		let url = session.url().clone();
+		let session = self.handshake(session, &url, publish, subscribe).await?;
+		Ok(crate::quic::with_connection(session, connection))
+	}
+
+	// Perform the MoQ handshake, sending the path in the SETUP for raw QUIC since there's no URL.
+	// The configured limits are applied to the resulting session.
+	async fn handshake<S>(
+		&self,
+		session: S,
+		url: &Url,
+		publish: impl Into<Option<moq_lite::OriginConsumer>>,
+		subscribe: impl Into<Option<moq_lite::OriginProducer>>,
+	) -> anyhow::Result<moq_lite::Session>
+	where
+		S: web_transport_trait::Session,
+		S::SendStream: QuinnSendStream,
+	{
+		let session = Prioritized::new(session);
+		let session = match url.scheme() {
+			"moql" | "moqt" | "iroh" | "moql+iroh" | "moqt+iroh" => {
+				let path = match url.query() {
+					Some(query) => format!("{}?{}", url.path(), query),
+					None => url.path().to_string(),
+				};
+				moq_lite::Session::connect_with_path(session, &path, publish, subscribe).await?
+			}
+			_ => moq_lite::Session::connect(session, publish, subscribe).await?,
+		};
+
+		Ok(session.with_limits(self.limits.clone()))
 	}
 
 	/// Establish a WebTransport/QUIC connection or a WebSocket connection, whichever is available first.
@@ -231,9 +546,10 @@ impl Client {
 	) -> anyhow::Result<moq_lite::Session> {
 		#[cfg(feature = "iroh")]
 		if crate::iroh::is_iroh_url(&url) {
-			let session = self.connect_iroh(url).await?;
-			let session = moq_lite::Session::connect(session, publish, subscribe).await?;
-			return Ok(session);
+			let session = self.connect_iroh(url.clone()).await?;
+			let connection = (*session).clone();
+			let session = self.handshake(session, &url, publish, subscribe).await?;
+			return Ok(session.with_transport_stats(move || crate::iroh::transport_stats(&connection)));
 		}
 
 		// Create futures for both possible protocols
@@ -260,8 +576,13 @@ impl Client {
 
 		// Race the connection futures
 		Ok(tokio::select! {
-			Some(quic) = quic_handle => moq_lite::Session::connect(quic, publish, subscribe).await?,
-			Some(ws) = ws_handle => moq_lite::Session::connect(ws, publish, subscribe).await?,
+			Some(quic) = quic_handle => {
+				// The scheme reflects the negotiated protocol.
+				let connection = (*quic).clone();
+				let url = quic.url().clone();
+				crate::quic::with_connection(self.handshake(quic, &url, publish, subscribe).await?, connection)
+			}
+			Some(ws) = ws_handle => moq_lite::Session::connect(ws, publish, subscribe).await?.with_limits(self.limits.clone()),
 			// If both attempts fail, return an error
 			else => anyhow::bail!("failed to connect to server"),
 		})
@@ -273,70 +594,161 @@ impl Client {
 		let host = url.host().context("invalid DNS name")?.to_string();
 		let port = url.port().unwrap_or(443);
 
-		// Look up the DNS entry.
-		let ip = tokio::net::lookup_host((host.clone(), port))
-			.await
-			.context("failed DNS lookup")?
-			.next()
-			.context("no DNS entries")?;
-
 		if url.scheme() == "http" {
-			// Perform a HTTP request to fetch the certificate fingerprint.
-			let mut fingerprint = url.clone();
-			fingerprint.set_path("/certificate.sha256");
-			fingerprint.set_query(None);
-			fingerprint.set_fragment(None);
-
-			tracing::warn!(url = %fingerprint, "performing insecure HTTP request for certificate");
-
-			let resp = reqwest::get(fingerprint.as_str())
-				.await
-				.context("failed to fetch fingerprint")?
-				.error_for_status()
-				.context("fingerprint request failed")?;
-
-			let fingerprint = resp.text().await.context("failed to read fingerprint")?;
-			let fingerprint = hex::decode(fingerprint.trim()).context("invalid fingerprint")?;
+			// Perform a HTTP request to fetch the certificate fingerprints.
+			let fingerprints = Self::fetch_fingerprints(&url).await?;
 
-			let verifier = FingerprintVerifier::new(config.crypto_provider().clone(), fingerprint);
+			let verifier = FingerprintVerifier::new(config.crypto_provider().clone(), fingerprints);
 			config.dangerous().set_certificate_verifier(Arc::new(verifier));
 
 			url.set_scheme("https").expect("failed to set scheme");
 		}
 
-		let alpn = match url.scheme() {
-			"https" => web_transport_quinn::ALPN,
-			"moql" => moq_lite::lite::ALPN,
-			"moqt" => moq_lite::ietf::ALPN,
-			_ => anyhow::bail!("url scheme must be 'http', 'https', or 'moql'"),
+		let alpns = match url.scheme() {
+			"https" => vec![web_transport_quinn::ALPN],
+			"moql" => vec![moq_lite::lite::ALPN],
+			"moqt" => vec![moq_lite::ietf::ALPN],
+			// Offer every protocol and let the server choose.
+			"moq" => vec![web_transport_quinn::ALPN, moq_lite::lite::ALPN, moq_lite::ietf::ALPN],
+			_ => anyhow::bail!("url scheme must be 'http', 'https', 'moql', 'moqt', or 'moq'"),
 		};
 
-		// TODO support connecting to both ALPNs at the same time
-		config.alpn_protocols = vec![alpn.as_bytes().to_vec()];
+		config.alpn_protocols = alpns.iter().map(|alpn| alpn.as_bytes().to_vec()).collect();
 		config.key_log = Arc::new(rustls::KeyLogFile::new());
 
 		let config: quinn::crypto::rustls::QuicClientConfig = config.try_into()?;
 		let mut config = quinn::ClientConfig::new(Arc::new(config));
 		config.transport_config(self.transport.clone());
 
-		tracing::debug!(%url, %ip, %alpn, "connecting");
+		tracing::debug!(%url, ?alpns, "connecting");
 
-		let connection = self.quic.connect_with(config, ip, &host)?.await?;
+		let connection = self.connect_addrs(config, &host, port).await?;
 		tracing::Span::current().record("id", connection.stable_id());
 
-		let session = match alpn {
-			web_transport_quinn::ALPN => web_transport_quinn::Session::connect(connection, url).await?,
-			moq_lite::lite::ALPN | moq_lite::ietf::ALPN => web_transport_quinn::Session::raw(connection, url),
-			_ => unreachable!("ALPN was checked above"),
+		let alpn = crate::quic::alpn(&connection).context("missing ALPN")?;
+
+		tracing::debug!(%url, %alpn, "negotiated");
+
+		// Use the scheme of the negotiated protocol, so the URL reflects how we're connected.
+		let session = match alpn.as_str() {
+			web_transport_quinn::ALPN => {
+				web_transport_quinn::Session::connect(connection, url_set_scheme(url, "https")?).await?
+			}
+			moq_lite::lite::ALPN => web_transport_quinn::Session::raw(connection, url_set_scheme(url, "moql")?),
+			moq_lite::ietf::ALPN => web_transport_quinn::Session::raw(connection, url_set_scheme(url, "moqt")?),
+			_ => anyhow::bail!("unsupported ALPN: {alpn}"),
 		};
 
 		Ok(session)
 	}
 
+	// Fetch the SHA-256 fingerprints of every certificate the server may use (insecure).
+	async fn fetch_fingerprints(url: &Url) -> anyhow::Result<Vec<Vec<u8>>> {
+		let mut fingerprint = url.clone();
+		fingerprint.set_path("/certificate.json");
+		fingerprint.set_query(None);
+		fingerprint.set_fragment(None);
+
+		tracing::warn!(url = %fingerprint, "performing insecure HTTP request for certificate");
+
+		let resp = reqwest::get(fingerprint.as_str())
+			.await
+			.context("failed to fetch fingerprint")?;
+
+		if resp.status() != reqwest::StatusCode::NOT_FOUND {
+			let fingerprints: Vec<crate::Fingerprint> = resp
+				.error_for_status()
+				.context("fingerprint request failed")?
+				.json()
+				.await
+				.context("failed to read fingerprints")?;
+
+			return fingerprints
+				.iter()
+				.filter(|fingerprint| fingerprint.algorithm == "sha-256")
+				.map(|fingerprint| hex::decode(&fingerprint.value).context("invalid fingerprint"))
+				.collect();
+		}
+
+		// Older servers only serve a single fingerprint.
+		fingerprint.set_path("/certificate.sha256");
+
+		let resp = reqwest::get(fingerprint.as_str())
+			.await
+			.context("failed to fetch fingerprint")?
+			.error_for_status()
+			.context("fingerprint request failed")?;
+
+		let fingerprint = resp.text().await.context("failed to read fingerprint")?;
+		let fingerprint = hex::decode(fingerprint.trim()).context("invalid fingerprint")?;
+
+		Ok(vec![fingerprint])
+	}
+
+	// Race a connection to every resolved address, starting each attempt after a delay or when the previous one fails.
+	async fn connect_addrs(
+		&self,
+		config: quinn::ClientConfig,
+		host: &str,
+		port: u16,
+	) -> anyhow::Result<quinn::Connection> {
+		let key = (host.to_string(), port);
+
+		// An IPv4 socket can't reach IPv6 addresses, while an IPv6 socket can usually reach both.
+		let local = self.quic.local_addr()?;
+		let addrs = tokio::net::lookup_host((host, port))
+			.await
+			.context("failed DNS lookup")?
+			.filter(|addr| local.is_ipv6() || addr.is_ipv4())
+			.collect();
+
+		let prefer_ipv6 = FAMILY_WON.lock().unwrap().get(&key).copied();
+		let addrs = interleave(addrs, prefer_ipv6);
+		anyhow::ensure!(!addrs.is_empty(), "no DNS entries");
+
+		let delay = self.quic_attempt_delay.unwrap_or_default();
+		let mut remaining = addrs.into_iter();
+		let mut attempts = FuturesUnordered::new();
+		let mut error = None;
+
+		loop {
+			if let Some(addr) = remaining.next() {
+				tracing::debug!(%host, %addr, "connecting");
+
+				match self.quic.connect_with(config.clone(), addr, host) {
+					Ok(connecting) => attempts.push(async move { (addr, connecting.await) }),
+					Err(err) => {
+						tracing::debug!(%host, %addr, %err, "connection attempt failed");
+						error = Some(err.into());
+
+						// There's nothing to wait for, so try the next address immediately.
+						continue;
+					}
+				}
+			}
+
+			tokio::select! {
+				Some((addr, res)) = attempts.next() => match res {
+					Ok(connection) => {
+						// Any other attempts are abandoned when dropped.
+						FAMILY_WON.lock().unwrap().insert(key, addr.is_ipv6());
+						return Ok(connection);
+					}
+					Err(err) => {
+						tracing::debug!(%host, %addr, %err, "connection attempt failed");
+						error = Some(err.into());
+					}
+				},
+				_ = tokio::time::sleep(delay), if remaining.len() > 0 => {},
+				else => return Err(error.unwrap_or_else(|| anyhow::anyhow!("failed to connect"))),
+			}
+		}
+	}
+
 	async fn connect_websocket(&self, mut url: Url) -> anyhow::Result<web_transport_ws::Session> {
 		let host = url.host_str().context("missing hostname")?.to_string();
 		let port = url.port().unwrap_or_else(|| match url.scheme() {
-			"https" | "wss" | "moql" | "moqt" => 443,
+			"https" | "wss" | "moql" | "moqt" | "moq" => 443,
 			"http" | "ws" => 80,
 			_ => 443,
 		});
@@ -359,8 +771,8 @@ impl Client {
 				url.set_scheme("ws").expect("failed to set scheme");
 				false
 			}
-			"https" | "moql" | "moqt" => {
-				url.set_scheme("wss").expect("failed to set scheme");
+			"https" | "moql" | "moqt" | "moq" => {
+				url = url_set_scheme(url, "wss")?;
 				true
 			}
 			"ws" => false,
@@ -465,16 +877,16 @@ impl rustls::client::danger::ServerCertVerifier for NoCertificateVerification {
 	}
 }
 
-// Verify the certificate matches a provided fingerprint.
+// Verify the certificate matches any of the provided fingerprints.
 #[derive(Debug)]
 struct FingerprintVerifier {
 	provider: crypto::Provider,
-	fingerprint: Vec<u8>,
+	fingerprints: Vec<Vec<u8>>,
 }
 
 impl FingerprintVerifier {
-	pub fn new(provider: crypto::Provider, fingerprint: Vec<u8>) -> Self {
-		Self { provider, fingerprint }
+	pub fn new(provider: crypto::Provider, fingerprints: Vec<Vec<u8>>) -> Self {
+		Self { provider, fingerprints }
 	}
 }
 
@@ -488,7 +900,11 @@ impl rustls::client::danger::ServerCertVerifier for FingerprintVerifier {
 		_now: UnixTime,
 	) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
 		let fingerprint = crypto::sha256(&self.provider, end_entity);
-		if fingerprint.as_ref() == self.fingerprint.as_slice() {
+		if self
+			.fingerprints
+			.iter()
+			.any(|expected| expected.as_slice() == fingerprint.as_ref())
+		{
 			Ok(rustls::client::danger::ServerCertVerified::assertion())
 		} else {
 			Err(rustls::Error::General("fingerprint mismatch".into()))
@@ -518,13 +934,28 @@ impl rustls::client::danger::ServerCertVerifier for FingerprintVerifier {
 	}
 }
 
+// Alternate between address families, starting with the preferred family or whichever DNS returned first.
+fn interleave(addrs: Vec<net::SocketAddr>, prefer_ipv6: Option<bool>) -> Vec<net::SocketAddr> {
+	let prefer_ipv6 = prefer_ipv6.unwrap_or_else(|| addrs.first().is_some_and(|addr| addr.is_ipv6()));
+
+	let (mut preferred, mut other): (VecDeque<net::SocketAddr>, VecDeque<_>) =
+		addrs.iter().partition(|addr| addr.is_ipv6() == prefer_ipv6);
+
+	let mut interleaved = Vec::with_capacity(addrs.len());
+	while !preferred.is_empty() || !other.is_empty() {
+		interleaved.extend(preferred.pop_front());
+		interleaved.extend(other.pop_front());
+	}
+
+	interleaved
+}
+
 /// Returns a new URL with a changed scheme.
 ///
 /// [`Url::set_scheme`] returns an error if the scheme change is not valid according to
 /// [the URL specification's section on legal scheme state overrides](https://url.spec.whatwg.org/#scheme-state).
 ///
 /// This function allows all scheme changes, as long as the resulting URL is valid.
-#[cfg(feature = "iroh")]
 fn url_set_scheme(url: Url, scheme: &str) -> anyhow::Result<Url> {
 	let url = format!(
 		"{}:{}",
@@ -534,3 +965,30 @@ fn url_set_scheme(url: Url, scheme: &str) -> anyhow::Result<Url> {
 	.parse()?;
 	Ok(url)
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn interleave_families() {
+		let v4 = |i: u8| net::SocketAddr::from(([127, 0, 0, i], 443));
+		let v6 = |i: u16| net::SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, i], 443));
+
+		// Whichever family DNS returned first is preferred by default.
+		let addrs = vec![v6(1), v6(2), v6(3), v4(1)];
+		assert_eq!(interleave(addrs.clone(), None), [v6(1), v4(1), v6(2), v6(3)]);
+
+		// The family that won last time takes priority.
+		assert_eq!(interleave(addrs, Some(false)), [v4(1), v6(1), v6(2), v6(3)]);
+
+		// The order within each family is preserved.
+		let addrs = vec![v4(1), v4(2), v6(1), v6(2)];
+		assert_eq!(interleave(addrs.clone(), None), [v4(1), v6(1), v4(2), v6(2)]);
+		assert_eq!(interleave(addrs, Some(true)), [v6(1), v4(1), v6(2), v4(2)]);
+
+		// A single family is left untouched.
+		assert_eq!(interleave(vec![v4(2), v4(1)], Some(true)), [v4(2), v4(1)]);
+		assert!(interleave(Vec::new(), None).is_empty());
+	}
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use tokio::net::UdpSocket;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+/// Network conditions applied to packets flowing in one direction through an [ImpairedProxy].
+#[derive(Clone, Debug, Default)]
+pub struct Impairment {
+	/// A fixed delay added to every packet.
+	pub delay: Duration,
+
+	/// A random delay between zero and this value added to every packet, which may reorder packets.
+	pub jitter: Duration,
+
+	/// The probability (0.0 to 1.0) that a packet is dropped.
+	pub loss: f64,
+
+	/// The maximum throughput in bytes per second, or [None] for unlimited.
+	pub bandwidth: Option<u64>,
+
+	/// Drop packets that would wait longer than this behind the bandwidth limit.
+	///
+	/// [None] means the queue is unbounded, simulating bufferbloat.
+	pub queue: Option<Duration>,
+
+	/// The seed for the random number generator, so loss and jitter are reproducible.
+	pub seed: u64,
+}
+
+// Decides when (or if) each packet is delivered.
+struct Shaper {
+	impairment: Impairment,
+	rng: StdRng,
+
+	// When the link is next idle, used to queue packets behind the bandwidth limit.
+	idle: Instant,
+}
+
+impl Shaper {
+	fn new(impairment: Impairment) -> Self {
+		Self {
+			rng: StdRng::seed_from_u64(impairment.seed),
+			impairment,
+			idle: Instant::now(),
+		}
+	}
+
+	// Returns when the packet should be delivered, or None if it's dropped.
+	fn schedule(&mut self, size: usize) -> Option<Instant> {
+		let impairment = &self.impairment;
+
+		if impairment.loss > 0.0 && self.rng.random_bool(impairment.loss.min(1.0)) {
+			return None;
+		}
+
+		let now = Instant::now();
+		let start = now.max(self.idle);
+
+		if let Some(queue) = impairment.queue
+			&& start - now > queue
+		{
+			return None;
+		}
+
+		if let Some(bandwidth) = impairment.bandwidth {
+			self.idle = start + Duration::from_secs_f64(size as f64 / bandwidth.max(1) as f64);
+		}
+
+		let jitter = match impairment.jitter.is_zero() {
+			true => Duration::ZERO,
+			false => self.rng.random_range(Duration::ZERO..=impairment.jitter),
+		};
+
+		Some(self.idle.max(now) + impairment.delay + jitter)
+	}
+}
+
+/// A UDP proxy that forwards packets to a target while simulating a bad network.
+///
+/// Useful for testing how QUIC connections, and MoQ's prioritization, behave under congestion.
+/// Point the client at [Self::local_addr] instead of the server; each client address gets its own upstream socket.
+/// The proxy stops when dropped.
+pub struct ImpairedProxy {
+	addr: SocketAddr,
+	task: tokio::task::JoinHandle<()>,
+}
+
+impl ImpairedProxy {
+	/// Listen on the given address, forwarding to the target.
+	///
+	/// `upstream` applies to packets sent to the target, and `downstream` to packets sent back.
+	pub async fn bind(
+		bind: SocketAddr,
+		target: SocketAddr,
+		upstream: Impairment,
+		downstream: Impairment,
+	) -> anyhow::Result<Self> {
+		let socket = UdpSocket::bind(bind).await.context("failed to bind UDP socket")?;
+		let addr = socket.local_addr()?;
+
+		let proxy = Proxy {
+			socket: Arc::new(socket),
+			target,
+			upstream,
+			downstream,
+		};
+
+		let task = tokio::spawn(async move {
+			if let Err(err) = proxy.run().await {
+				tracing::warn!(%err, "proxy failed");
+			}
+		});
+
+		Ok(Self { addr, task })
+	}
+
+	/// Returns the address the proxy is listening on.
+	pub fn local_addr(&self) -> SocketAddr {
+		self.addr
+	}
+}
+
+impl Drop for ImpairedProxy {
+	fn drop(&mut self) {
+		self.task.abort();
+	}
+}
+
+struct Proxy {
+	socket: Arc<UdpSocket>,
+	target: SocketAddr,
+	upstream: Impairment,
+	downstream: Impairment,
+}
+
+impl Proxy {
+	async fn run(self) -> anyhow::Result<()> {
+		let mut clients: HashMap<SocketAddr, (Arc<UdpSocket>, Shaper)> = HashMap::new();
+
+		// Aborted when the proxy is dropped.
+		let mut tasks = JoinSet::new();
+
+		let mut buf = vec![0; u16::MAX as usize];
+
+		loop {
+			let (size, from) = self.socket.recv_from(&mut buf).await?;
+
+			let (upstream, shaper) = match clients.get_mut(&from) {
+				Some(client) => client,
+				None => {
+					let bind: SocketAddr = match self.target {
+						SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+						SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+					};
+
+					let upstream = UdpSocket::bind(bind).await?;
+					upstream.connect(self.target).await?;
+					let upstream = Arc::new(upstream);
+
+					tasks.spawn(Self::run_downstream(
+						self.socket.clone(),
+						upstream.clone(),
+						from,
+						Shaper::new(self.downstream.clone()),
+					));
+
+					clients
+						.entry(from)
+						.or_insert((upstream, Shaper::new(self.upstream.clone())))
+				}
+			};
+
+			if let Some(deliver) = shaper.schedule(size) {
+				let packet = buf[..size].to_vec();
+				let upstream = upstream.clone();
+
+				tasks.spawn(async move {
+					tokio::time::sleep_until(deliver).await;
+					upstream.send(&packet).await.ok();
+				});
+			}
+
+			// Reap any finished deliveries so the set doesn't grow forever.
+			while tasks.try_join_next().is_some() {}
+		}
+	}
+
+	async fn run_downstream(socket: Arc<UdpSocket>, upstream: Arc<UdpSocket>, client: SocketAddr, mut shaper: Shaper) {
+		let mut buf = vec![0; u16::MAX as usize];
+
+		// Each packet is delivered by its own task; they're detached but short-lived.
+		while let Ok(size) = upstream.recv(&mut buf).await {
+			if let Some(deliver) = shaper.schedule(size) {
+				let packet = buf[..size].to_vec();
+				let socket = socket.clone();
+
+				tokio::spawn(async move {
+					tokio::time::sleep_until(deliver).await;
+					socket.send_to(&packet, client).await.ok();
+				});
+			}
+		}
+	}
+}
@@ -1,5 +1,8 @@
 use std::{net, path::PathBuf, str::FromStr};
 
+use crate::priority::Prioritized;
+use crate::server::{SETUP_TIMEOUT, set_path};
+use anyhow::Context;
 use url::Url;
 use web_transport_iroh::{
 	http,
@@ -95,21 +98,75 @@ pub fn is_iroh_url(url: &Url) -> bool {
 }
 
 /// Raw QUIC-only iroh request (not using HTTP/3).
-pub struct IrohQuicRequest(iroh::endpoint::Connection);
+pub struct IrohQuicRequest {
+	// Boxed because the SETUP stream makes this much larger than the other requests.
+	request: Box<moq_lite::SessionRequest<Prioritized<web_transport_iroh::Session>>>,
+	connection: iroh::endpoint::Connection,
+	url: Url,
+}
 
 impl IrohQuicRequest {
-	/// Accept a new QUIC-only WebTransport session from a client.
-	pub fn accept(conn: iroh::endpoint::Connection) -> Self {
-		Self(conn)
+	/// Accept a new QUIC-only WebTransport session from a client, waiting for the MoQ SETUP message.
+	///
+	/// The URL is built from the ALPN, the remote endpoint ID, and the path provided in the SETUP.
+	pub async fn accept(connection: iroh::endpoint::Connection) -> anyhow::Result<Self> {
+		let scheme = match connection.alpn() {
+			alpn if alpn == moq_lite::ietf::ALPN.as_bytes() => "moqt+iroh",
+			_ => "moql+iroh",
+		};
+		let mut url: Url = format!("{scheme}://{}", connection.remote_id()).parse()?;
+
+		let session = Prioritized::new(web_transport_iroh::Session::raw(connection.clone()));
+		let request = tokio::time::timeout(SETUP_TIMEOUT, moq_lite::SessionRequest::accept(session))
+			.await
+			.context("timed out waiting for SETUP")??;
+		let request = Box::new(request);
+
+		if let Some(path) = request.path() {
+			set_path(&mut url, &path)?;
+		}
+
+		Ok(Self {
+			request,
+			connection,
+			url,
+		})
+	}
+
+	/// Accept the session, performing the rest of the MoQ handshake.
+	pub async fn ok(
+		self,
+		publish: impl Into<Option<moq_lite::OriginConsumer>>,
+		subscribe: impl Into<Option<moq_lite::OriginProducer>>,
+	) -> Result<moq_lite::Session, moq_lite::Error> {
+		let session = self.request.ok(publish, subscribe).await?;
+		let connection = self.connection;
+		Ok(session.with_transport_stats(move || transport_stats(&connection)))
 	}
 
-	/// Accept the session.
-	pub fn ok(self) -> web_transport_iroh::Session {
-		web_transport_iroh::Session::raw(self.0)
+	/// Returns the URL provided by the client.
+	pub fn url(&self) -> &Url {
+		&self.url
 	}
 
 	/// Reject the session.
 	pub fn close(self, status: http::StatusCode) {
-		self.0.close(status.as_u16().into(), status.as_str().as_bytes());
+		self.connection
+			.close(status.as_u16().into(), status.as_str().as_bytes());
 	}
 }
+
+/// Report the statistics of an iroh connection, see [moq_lite::Session::with_transport_stats].
+pub(crate) fn transport_stats(connection: &iroh::endpoint::Connection) -> moq_lite::TransportStats {
+	let stats = connection.stats();
+
+	let mut transport = moq_lite::TransportStats::default();
+	transport.rtt = stats.path.rtt;
+	transport.congestion_window = stats.path.cwnd;
+	transport.bytes_sent = stats.udp_tx.bytes;
+	transport.bytes_received = stats.udp_rx.bytes;
+	transport.packets_sent = stats.path.sent_packets;
+	transport.packets_received = stats.udp_rx.datagrams;
+	transport.packets_lost = stats.path.lost_packets;
+	transport
+}
@@ -7,15 +7,33 @@
 //! - Iroh P2P (requires `iroh` feature)
 //!
 //! See [`Client`] for connecting to relays and [`Server`] for accepting connections.
+//! [`ImpairedProxy`] simulates a bad network between them for testing.
+//! Use [`Client::connect_migrating`] to follow a GOAWAY to a new session,
+//! or [`Client::connect_reconnecting`] to also reconnect whenever the connection is lost.
 
+mod acme;
 mod client;
 mod crypto;
+mod impair;
 mod log;
+mod migrate;
+mod priority;
+mod quic;
+mod reconnect;
 mod server;
+mod watch;
+mod websocket;
 
+pub use acme::{AcmeChallenges, AcmeConfig};
 pub use client::*;
+pub use impair::*;
 pub use log::*;
+pub use migrate::*;
+pub use quic::CongestionControl;
+pub use reconnect::*;
 pub use server::*;
+pub use watch::*;
+pub use websocket::WebSocketRequest;
 
 // Re-export these crates.
 pub use moq_lite;
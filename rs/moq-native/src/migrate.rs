@@ -0,0 +1,230 @@
+use std::{collections::HashSet, time::Duration};
+
+use tokio::sync::watch;
+use url::Url;
+
+use crate::Client;
+
+/// A MoQ session that migrates to a new connection when the server sends a GOAWAY.
+///
+/// A new session is established using the same [moq_lite::OriginConsumer] and [moq_lite::OriginProducer].
+/// The old session is closed once the new one has reannounced its broadcasts (or a timeout expires),
+/// so consumers see a reannounce rather than a gap.
+/// If the new session can't be established, the migration is retried and then falls back to the current URL.
+///
+/// Created via [Client::connect_migrating].
+#[derive(Clone)]
+pub struct MigratingSession {
+	// The current session, replaced on each migration.
+	session: watch::Receiver<moq_lite::Session>,
+	pub(crate) url: watch::Receiver<Url>,
+}
+
+impl MigratingSession {
+	/// Returns the URL of the current session.
+	pub fn url(&self) -> Url {
+		self.url.borrow().clone()
+	}
+
+	/// Returns the current session.
+	pub fn session(&self) -> moq_lite::Session {
+		self.session.borrow().clone()
+	}
+
+	/// Close the current session, ending any migrations.
+	pub fn close(self, err: moq_lite::Error) {
+		self.session().close(err);
+	}
+
+	/// Block until the session is closed without migrating.
+	pub async fn closed(&self) -> Result<(), moq_lite::Error> {
+		let mut current = self.session.clone();
+
+		loop {
+			let session = current.borrow_and_update().clone();
+
+			tokio::select! {
+				// Prefer a migration over the old session closing.
+				biased;
+				res = current.changed() => match res {
+					Ok(()) => continue,
+					// No more migrations, so wait for the final session.
+					Err(_) => return session.closed().await,
+				},
+				res = session.closed() => return res,
+			}
+		}
+	}
+}
+
+impl Client {
+	/// Establish a MoQ session that migrates to a new connection when the server sends a GOAWAY.
+	///
+	/// See [Self::connect_with_fallback] for how the connection is established.
+	pub async fn connect_migrating(
+		&self,
+		url: Url,
+		publish: impl Into<Option<moq_lite::OriginConsumer>>,
+		subscribe: impl Into<Option<moq_lite::OriginProducer>>,
+	) -> anyhow::Result<MigratingSession> {
+		let publish = publish.into();
+		let subscribe = subscribe.into();
+
+		let session = self
+			.connect_with_fallback(url.clone(), publish.clone(), subscribe.clone())
+			.await?;
+
+		let session = watch::channel(session);
+		let url = watch::channel(url);
+
+		let migrate = Migrate {
+			client: self.clone(),
+			publish,
+			subscribe,
+			session: session.0,
+			url: url.0,
+		};
+		tokio::spawn(migrate.run());
+
+		Ok(MigratingSession {
+			session: session.1,
+			url: url.1,
+		})
+	}
+}
+
+// How many times to try establishing the new session, the last falling back to the current URL.
+const MIGRATE_ATTEMPTS: u32 = 3;
+
+// How long to wait for the new session to reannounce the old session's broadcasts before closing it anyway.
+const REANNOUNCE_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Migrate {
+	client: Client,
+	publish: Option<moq_lite::OriginConsumer>,
+	subscribe: Option<moq_lite::OriginProducer>,
+	session: watch::Sender<moq_lite::Session>,
+	url: watch::Sender<Url>,
+}
+
+impl Migrate {
+	async fn run(self) {
+		loop {
+			let old = self.session.borrow().clone();
+
+			let uri = tokio::select! {
+				uri = old.going_away() => uri,
+				// Stop migrating once every handle has been dropped.
+				_ = self.session.closed() => return,
+			};
+
+			// The session was closed without a GOAWAY.
+			let Some(uri) = uri else { return };
+
+			// An empty URI means we should reconnect to the same URL.
+			let current = self.url.borrow().clone();
+			let url = match current.join(&uri) {
+				Ok(url) => url,
+				Err(err) => {
+					tracing::warn!(%err, %uri, "invalid GOAWAY uri, reconnecting to the same URL");
+					current.clone()
+				}
+			};
+
+			// Snapshot the old session's broadcasts before the new session starts reannouncing them.
+			let reannounce = self.subscribe.as_ref().map(|origin| Reannounce::new(origin, &old));
+
+			let res = tokio::select! {
+				res = self.connect(&url, &current) => res,
+				// There's nothing left to migrate.
+				_ = old.closed() => return,
+			};
+
+			let Some((url, new)) = res else {
+				// Keep using the old session until the server closes it.
+				tracing::warn!(%url, "failed to migrate session");
+				return;
+			};
+
+			self.url.send_replace(url);
+			self.session.send_replace(new);
+
+			if let Some(reannounce) = reannounce {
+				reannounce.wait().await;
+			}
+
+			old.close(moq_lite::Error::Cancel);
+		}
+	}
+
+	// Establish the new session, retrying with backoff and falling back to the current URL on the last attempt.
+	async fn connect(&self, url: &Url, current: &Url) -> Option<(Url, moq_lite::Session)> {
+		let mut delay = self.client.reconnect_delay.unwrap_or(Duration::from_secs(1));
+
+		for attempt in 1..=MIGRATE_ATTEMPTS {
+			let url = match attempt {
+				MIGRATE_ATTEMPTS => current,
+				_ => url,
+			};
+
+			tracing::info!(%url, %attempt, "migrating session");
+
+			match self
+				.client
+				.connect_with_fallback(url.clone(), self.publish.clone(), self.subscribe.clone())
+				.await
+			{
+				Ok(session) => return Some((url.clone(), session)),
+				Err(err) => tracing::warn!(%err, %url, %attempt, "failed to connect"),
+			}
+
+			if attempt < MIGRATE_ATTEMPTS {
+				tokio::time::sleep(delay).await;
+				delay = delay.saturating_mul(2);
+			}
+		}
+
+		None
+	}
+}
+
+// The broadcasts announced by the old session, which should be reannounced by the new session.
+struct Reannounce {
+	origin: moq_lite::OriginConsumer,
+	pending: HashSet<moq_lite::PathOwned>,
+}
+
+impl Reannounce {
+	fn new(origin: &moq_lite::OriginProducer, session: &moq_lite::Session) -> Self {
+		let mut origin = origin.consume();
+
+		// Skip the current broadcasts, which are queued immediately.
+		while origin.try_announced().is_some() {}
+
+		// Broadcasts published locally or by other sessions won't be reannounced, so don't wait for them.
+		let pending = session.announced().into_iter().collect();
+
+		Self { origin, pending }
+	}
+
+	// Wait until every broadcast has been reannounced, or the timeout expires.
+	async fn wait(mut self) {
+		let res = tokio::time::timeout(REANNOUNCE_TIMEOUT, async {
+			while !self.pending.is_empty() {
+				match self.origin.announced().await {
+					Some((path, Some(_))) => self.pending.remove(&path),
+					Some((_, None)) => continue,
+					None => return,
+				};
+			}
+		})
+		.await;
+
+		if res.is_err() {
+			tracing::warn!(
+				missing = self.pending.len(),
+				"timed out waiting for broadcasts to be reannounced"
+			);
+		}
+	}
+}
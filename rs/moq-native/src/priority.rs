@@ -0,0 +1,133 @@
+use bytes::{Buf, Bytes};
+
+/// A send stream backed by quinn, which sends streams with a *higher* priority first.
+pub(crate) trait QuinnSendStream: web_transport_trait::SendStream {
+	fn set_quinn_priority(&self, priority: i32);
+}
+
+impl QuinnSendStream for web_transport_quinn::SendStream {
+	fn set_quinn_priority(&self, priority: i32) {
+		web_transport_quinn::SendStream::set_priority(self, priority).ok();
+	}
+}
+
+#[cfg(feature = "iroh")]
+impl QuinnSendStream for web_transport_iroh::SendStream {
+	fn set_quinn_priority(&self, priority: i32) {
+		web_transport_iroh::SendStream::set_priority(self, priority).ok();
+	}
+}
+
+/// Fixes the stream priority of a quinn-backed session.
+///
+/// [web_transport_trait::SendStream::set_priority] sends lower values first,
+/// but web-transport-quinn and web-transport-iroh pass the value straight to quinn, which sends higher values first.
+/// We negate the value instead so both the moq-lite and IETF publishers get the documented behavior.
+#[derive(Clone)]
+pub(crate) struct Prioritized<S>(S);
+
+impl<S> Prioritized<S> {
+	pub fn new(session: S) -> Self {
+		Self(session)
+	}
+}
+
+impl<S> web_transport_trait::Session for Prioritized<S>
+where
+	S: web_transport_trait::Session,
+	S::SendStream: QuinnSendStream,
+{
+	type SendStream = PrioritizedSend<S::SendStream>;
+	type RecvStream = S::RecvStream;
+	type Error = S::Error;
+
+	async fn accept_uni(&self) -> Result<Self::RecvStream, Self::Error> {
+		self.0.accept_uni().await
+	}
+
+	async fn accept_bi(&self) -> Result<(Self::SendStream, Self::RecvStream), Self::Error> {
+		let (send, recv) = self.0.accept_bi().await?;
+		Ok((PrioritizedSend(send), recv))
+	}
+
+	async fn open_bi(&self) -> Result<(Self::SendStream, Self::RecvStream), Self::Error> {
+		let (send, recv) = self.0.open_bi().await?;
+		Ok((PrioritizedSend(send), recv))
+	}
+
+	async fn open_uni(&self) -> Result<Self::SendStream, Self::Error> {
+		let send = self.0.open_uni().await?;
+		Ok(PrioritizedSend(send))
+	}
+
+	fn send_datagram(&self, payload: Bytes) -> Result<(), Self::Error> {
+		self.0.send_datagram(payload)
+	}
+
+	async fn recv_datagram(&self) -> Result<Bytes, Self::Error> {
+		self.0.recv_datagram().await
+	}
+
+	fn max_datagram_size(&self) -> usize {
+		self.0.max_datagram_size()
+	}
+
+	fn close(&self, code: u32, reason: &str) {
+		self.0.close(code, reason)
+	}
+
+	async fn closed(&self) -> Self::Error {
+		self.0.closed().await
+	}
+}
+
+pub(crate) struct PrioritizedSend<T>(T);
+
+impl<T: QuinnSendStream> web_transport_trait::SendStream for PrioritizedSend<T> {
+	type Error = T::Error;
+
+	async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+		self.0.write(buf).await
+	}
+
+	async fn write_buf<B: Buf + web_transport_trait::MaybeSend>(&mut self, buf: &mut B) -> Result<usize, Self::Error> {
+		self.0.write_buf(buf).await
+	}
+
+	async fn write_chunk(&mut self, chunk: Bytes) -> Result<(), Self::Error> {
+		self.0.write_chunk(chunk).await
+	}
+
+	fn set_priority(&mut self, order: u8) {
+		self.0.set_quinn_priority(quinn_priority(order))
+	}
+
+	fn finish(&mut self) -> Result<(), Self::Error> {
+		self.0.finish()
+	}
+
+	fn reset(&mut self, code: u32) {
+		self.0.reset(code)
+	}
+
+	async fn closed(&mut self) -> Result<(), Self::Error> {
+		self.0.closed().await
+	}
+}
+
+// Lower orders are sent first, so they need a higher quinn priority.
+fn quinn_priority(order: u8) -> i32 {
+	-i32::from(order)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn order() {
+		assert_eq!(quinn_priority(0), 0);
+		assert!(quinn_priority(0) > quinn_priority(1));
+		assert!(quinn_priority(1) > quinn_priority(u8::MAX));
+	}
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The congestion controller used for QUIC connections.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum CongestionControl {
+	#[default]
+	Cubic,
+	NewReno,
+	/// Experimental, the implementation has not been validated yet.
+	Bbr,
+}
+
+/// QUIC transport parameters shared by the client and server.
+///
+/// [None] means use the quinn default.
+#[derive(Default)]
+pub(crate) struct Transport {
+	/// [None] or zero disables the idle timeout.
+	pub idle_timeout: Option<Duration>,
+	/// [None] or zero disables keep-alives.
+	pub keep_alive: Option<Duration>,
+	pub mtu_discovery: bool,
+	pub stream_window: Option<u64>,
+	pub connection_window: Option<u64>,
+	pub max_uni_streams: Option<u64>,
+	pub congestion: CongestionControl,
+}
+
+impl Transport {
+	pub fn build(self) -> anyhow::Result<Arc<quinn::TransportConfig>> {
+		let mut transport = quinn::TransportConfig::default();
+
+		let idle_timeout = self.idle_timeout.filter(|timeout| !timeout.is_zero());
+		transport.max_idle_timeout(idle_timeout.map(TryInto::try_into).transpose()?);
+
+		let keep_alive = self.keep_alive.filter(|interval| !interval.is_zero());
+		transport.keep_alive_interval(keep_alive);
+
+		if !self.mtu_discovery {
+			transport.mtu_discovery_config(None);
+		}
+
+		if let Some(window) = self.stream_window {
+			transport.stream_receive_window(quinn::VarInt::from_u64(window)?);
+		}
+
+		if let Some(window) = self.connection_window {
+			transport.receive_window(quinn::VarInt::from_u64(window)?);
+		}
+
+		if let Some(streams) = self.max_uni_streams {
+			transport.max_concurrent_uni_streams(quinn::VarInt::from_u64(streams)?);
+		}
+
+		match self.congestion {
+			CongestionControl::Cubic => {
+				transport.congestion_controller_factory(Arc::new(quinn::congestion::CubicConfig::default()))
+			}
+			CongestionControl::NewReno => {
+				transport.congestion_controller_factory(Arc::new(quinn::congestion::NewRenoConfig::default()))
+			}
+			CongestionControl::Bbr => {
+				tracing::warn!("BBR congestion control is experimental");
+				transport.congestion_controller_factory(Arc::new(quinn::congestion::BbrConfig::default()))
+			}
+		};
+
+		Ok(Arc::new(transport))
+	}
+}
+
+/// Report the negotiated ALPN and statistics of the QUIC connection underlying the session.
+pub(crate) fn with_connection(session: moq_lite::Session, connection: quinn::Connection) -> moq_lite::Session {
+	let session = match alpn(&connection) {
+		Some(alpn) => session.with_protocol(alpn),
+		None => session,
+	};
+	session.with_transport_stats(move || transport_stats(&connection))
+}
+
+/// Returns the negotiated ALPN of a QUIC connection, see [moq_lite::Session::with_protocol].
+pub(crate) fn alpn(connection: &quinn::Connection) -> Option<String> {
+	let data = connection.handshake_data()?;
+	let data = data.downcast::<quinn::crypto::rustls::HandshakeData>().ok()?;
+	String::from_utf8(data.protocol?).ok()
+}
+
+/// Report the statistics of a QUIC connection, see [moq_lite::Session::with_transport_stats].
+pub(crate) fn transport_stats(connection: &quinn::Connection) -> moq_lite::TransportStats {
+	let stats = connection.stats();
+
+	let mut transport = moq_lite::TransportStats::default();
+	transport.rtt = stats.path.rtt;
+	transport.congestion_window = stats.path.cwnd;
+	transport.bytes_sent = stats.udp_tx.bytes;
+	transport.bytes_received = stats.udp_rx.bytes;
+	transport.packets_sent = stats.path.sent_packets;
+	transport.packets_received = stats.udp_rx.datagrams;
+	transport.packets_lost = stats.path.lost_packets;
+	transport
+}
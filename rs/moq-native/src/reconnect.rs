@@ -0,0 +1,207 @@
+use std::time::Duration;
+
+use tokio::sync::{oneshot, watch};
+use url::Url;
+
+use crate::{Client, MigratingSession};
+
+/// The connection state of a [ReconnectingSession].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectionState {
+	/// Establishing the connection.
+	Connecting,
+
+	/// Connected to the given URL, which may change if the server sends a GOAWAY.
+	Connected(Url),
+
+	/// The connection failed or was closed, waiting to try again.
+	Reconnecting {
+		/// The number of consecutive failed attempts, or zero if an established session was lost.
+		attempt: u32,
+		/// How long until the next attempt.
+		delay: Duration,
+	},
+
+	/// Gave up reconnecting, either because the URL is invalid or after too many failed attempts.
+	///
+	/// This is the final state; no more attempts are made.
+	Failed(String),
+}
+
+/// A MoQ session that reconnects with exponential backoff whenever the connection is lost.
+///
+/// Each connection uses the same [moq_lite::OriginConsumer] and [moq_lite::OriginProducer],
+/// so broadcasts are re-announced and re-subscribed without any work from the application.
+/// Consumers see the broadcasts unannounced while disconnected and reannounced afterwards.
+///
+/// Created via [Client::connect_reconnecting]. Dropping it closes the current session and stops reconnecting.
+pub struct ReconnectingSession {
+	state: watch::Receiver<ConnectionState>,
+
+	// Dropped to stop the background task.
+	_close: oneshot::Sender<()>,
+}
+
+impl ReconnectingSession {
+	/// Returns the current connection state.
+	pub fn state(&self) -> ConnectionState {
+		self.state.borrow().clone()
+	}
+
+	/// Block until the connection state changes, returning the new state.
+	///
+	/// Once [ConnectionState::Failed] is reached, it's returned immediately on every call.
+	pub async fn changed(&mut self) -> ConnectionState {
+		// The sender is dropped by the background task when it gives up.
+		let _ = self.state.changed().await;
+		self.state.borrow_and_update().clone()
+	}
+
+	/// Close the current session and stop reconnecting.
+	pub fn close(self) {}
+}
+
+impl Client {
+	/// Establish a MoQ session that reconnects whenever the connection is lost.
+	///
+	/// Unlike [Self::connect_migrating], this returns immediately;
+	/// use [ReconnectingSession::changed] to monitor the connection state.
+	/// The delay between attempts starts at `reconnect_delay` and doubles up to `reconnect_max_delay`.
+	/// It gives up with [ConnectionState::Failed] if the URL is invalid or after `reconnect_attempts` consecutive failures.
+	pub fn connect_reconnecting(
+		&self,
+		url: Url,
+		publish: impl Into<Option<moq_lite::OriginConsumer>>,
+		subscribe: impl Into<Option<moq_lite::OriginProducer>>,
+	) -> ReconnectingSession {
+		let state = watch::channel(ConnectionState::Connecting);
+		let close = oneshot::channel();
+
+		let reconnect = Reconnect {
+			client: self.clone(),
+			url,
+			publish: publish.into(),
+			subscribe: subscribe.into(),
+			state: state.0,
+		};
+		tokio::spawn(reconnect.run(close.1));
+
+		ReconnectingSession {
+			state: state.1,
+			_close: close.0,
+		}
+	}
+}
+
+struct Reconnect {
+	client: Client,
+	url: Url,
+	publish: Option<moq_lite::OriginConsumer>,
+	subscribe: Option<moq_lite::OriginProducer>,
+	state: watch::Sender<ConnectionState>,
+}
+
+impl Reconnect {
+	async fn run(self, mut close: oneshot::Receiver<()>) {
+		// Retrying won't fix an invalid URL.
+		if let Err(err) = check_url(&self.url) {
+			tracing::warn!(%err, url = %self.url, "invalid URL");
+			self.state.send_replace(ConnectionState::Failed(err.to_string()));
+			return;
+		}
+
+		// The number of consecutive failed attempts, reset once connected.
+		let mut attempt = 0;
+
+		loop {
+			self.state.send_replace(ConnectionState::Connecting);
+
+			let res = tokio::select! {
+				res = self.client.connect_migrating(self.url.clone(), self.publish.clone(), self.subscribe.clone()) => res,
+				_ = &mut close => return,
+			};
+
+			match res {
+				Ok(session) => {
+					attempt = 0;
+
+					if !self.serve(session, &mut close).await {
+						return;
+					}
+				}
+				Err(err) => {
+					attempt += 1;
+					tracing::warn!(%err, url = %self.url, %attempt, "failed to connect");
+
+					if self.client.reconnect_attempts.is_some_and(|max| attempt >= max) {
+						self.state.send_replace(ConnectionState::Failed(format!("{err:#}")));
+						return;
+					}
+				}
+			}
+
+			let delay = self.delay(attempt);
+			self.state
+				.send_replace(ConnectionState::Reconnecting { attempt, delay });
+
+			tracing::info!(url = %self.url, ?delay, %attempt, "reconnecting");
+
+			tokio::select! {
+				_ = tokio::time::sleep(delay) => {},
+				_ = &mut close => return,
+			}
+		}
+	}
+
+	// Run the session until it closes, returning false if we should stop reconnecting.
+	async fn serve(&self, session: MigratingSession, close: &mut oneshot::Receiver<()>) -> bool {
+		let mut url = session.url.clone();
+
+		loop {
+			self.state
+				.send_replace(ConnectionState::Connected(url.borrow_and_update().clone()));
+
+			tokio::select! {
+				// Report any migrations to a new URL.
+				Ok(()) = url.changed() => continue,
+				res = session.closed() => {
+					match res {
+						Ok(()) => tracing::info!(url = %session.url(), "session closed"),
+						Err(err) => tracing::warn!(%err, url = %session.url(), "session closed"),
+					}
+					return true;
+				}
+				_ = &mut *close => {
+					session.close(moq_lite::Error::Cancel);
+					return false;
+				}
+			}
+		}
+	}
+
+	// The initial delay after losing a session or the first failure, doubling after each further failure.
+	fn delay(&self, attempt: u32) -> Duration {
+		let initial = self.client.reconnect_delay.unwrap_or(Duration::from_secs(1));
+		let max = self.client.reconnect_max_delay.unwrap_or(Duration::from_secs(30));
+
+		initial.saturating_mul(1 << attempt.saturating_sub(1).min(16)).min(max)
+	}
+}
+
+// Check for a URL that we could never connect to, so we fail fast instead of retrying forever.
+fn check_url(url: &Url) -> anyhow::Result<()> {
+	#[cfg(feature = "iroh")]
+	if crate::iroh::is_iroh_url(url) {
+		return Ok(());
+	}
+
+	anyhow::ensure!(
+		matches!(url.scheme(), "http" | "https" | "moql" | "moqt" | "moq" | "ws" | "wss"),
+		"unsupported URL scheme: {}",
+		url.scheme()
+	);
+	anyhow::ensure!(url.host().is_some(), "missing host: {url}");
+
+	Ok(())
+}
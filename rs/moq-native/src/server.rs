@@ -1,18 +1,24 @@
+use std::net;
 use std::path::PathBuf;
-use std::{net, time::Duration};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::crypto;
+use crate::acme::Acme;
 #[cfg(feature = "iroh")]
 use crate::iroh::IrohQuicRequest;
+use crate::priority::Prioritized;
+use crate::websocket::WebSocketListener;
+use crate::{AcmeChallenges, AcmeConfig, CongestionControl, FileWatch, WebSocketRequest, crypto};
 use anyhow::Context;
 use moq_lite::Session;
 use rand::Rng;
 use rustls::pki_types::{CertificateDer, PrivatePkcs8KeyDer};
-use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::server::danger::ClientCertVerifier;
+use rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
 use rustls::sign::CertifiedKey;
 use std::fs;
 use std::io::{self, Cursor, Read};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::watch;
 use url::Url;
 #[cfg(feature = "iroh")]
 use web_transport_iroh::iroh;
@@ -24,7 +30,7 @@ use futures::stream::{FuturesUnordered, StreamExt};
 
 /// TLS configuration for the server.
 ///
-/// Certificate and keys must currently be files on disk.
+/// Certificate and keys can be files on disk or provisioned automatically via ACME.
 /// Alternatively, you can generate a self-signed certificate given a list of hostnames.
 #[derive(clap::Args, Clone, Default, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -42,6 +48,7 @@ pub struct ServerTlsConfig {
 
 	/// Or generate a new certificate and key with the given hostnames.
 	/// This won't be valid unless the client uses the fingerprint or disables verification.
+	/// The certificate is regenerated weekly, serving the previous one until it expires.
 	#[arg(
 		long = "tls-generate",
 		id = "tls-generate",
@@ -50,6 +57,291 @@ pub struct ServerTlsConfig {
 	)]
 	#[serde(default, skip_serializing_if = "Vec::is_empty")]
 	pub generate: Vec<String>,
+
+	/// Poll the certificate and key files at this interval, reloading them when changed.
+	/// The new certificates are validated before they replace the old ones.
+	#[arg(
+		long = "tls-watch",
+		id = "tls-watch",
+		env = "MOQ_SERVER_TLS_WATCH",
+		value_parser = humantime::parse_duration
+	)]
+	#[serde(default, with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+	pub watch: Option<Duration>,
+
+	/// Require clients to present a certificate signed by one of these CAs, encoded as PEM.
+	/// The verified certificate is available via [Request::peer_certificates].
+	#[arg(long = "tls-client-ca", id = "tls-client-ca", env = "MOQ_SERVER_TLS_CLIENT_CA")]
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub client_ca: Vec<PathBuf>,
+
+	/// Allow clients without a certificate when `client_ca` is configured, ex. browsers authenticating via JWT.
+	/// Any certificate that is presented must still be valid.
+	#[arg(
+		long = "tls-client-optional",
+		id = "tls-client-optional",
+		env = "MOQ_SERVER_TLS_CLIENT_OPTIONAL",
+		action = clap::ArgAction::SetTrue
+	)]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub client_optional: Option<bool>,
+
+	/// Or obtain a certificate automatically via ACME.
+	#[command(flatten)]
+	#[serde(default)]
+	pub acme: AcmeConfig,
+}
+
+impl ServerTlsConfig {
+	// Verify client certificates against the configured CAs, if any.
+	fn client_verifier(&self, provider: &crypto::Provider) -> anyhow::Result<Arc<dyn ClientCertVerifier>> {
+		if self.client_ca.is_empty() {
+			return Ok(WebPkiClientVerifier::no_client_auth());
+		}
+
+		let mut roots = rustls::RootCertStore::empty();
+		for path in &self.client_ca {
+			let file = fs::File::open(path).context("failed to open client CA file")?;
+			let mut file = io::BufReader::new(file);
+
+			for cert in rustls_pemfile::certs(&mut file) {
+				roots.add(cert.context("failed to read client CA")?)?;
+			}
+		}
+
+		anyhow::ensure!(!roots.is_empty(), "could not find client CA");
+
+		let mut verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone());
+		if self.client_optional.unwrap_or_default() {
+			verifier = verifier.allow_unauthenticated();
+		}
+
+		Ok(verifier.build()?)
+	}
+}
+
+/// Configuration for draining the server on shutdown.
+///
+/// Instead of closing immediately, the server stops accepting new sessions and sends a GOAWAY to existing sessions.
+/// Clients then have until the timeout to migrate before the server closes.
+#[derive(clap::Args, Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, default)]
+#[non_exhaustive]
+pub struct ServerDrainConfig {
+	/// How long to wait for sessions to migrate after sending a GOAWAY (default: 10s)
+	/// Set to 0 to close immediately.
+	#[arg(
+		id = "server-drain-timeout",
+		long = "server-drain-timeout",
+		env = "MOQ_SERVER_DRAIN_TIMEOUT",
+		default_value = "10s",
+		value_parser = humantime::parse_duration,
+	)]
+	#[serde(with = "humantime_serde")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub timeout: Option<Duration>,
+
+	/// Tell clients to reconnect to this URL instead of the current one.
+	#[arg(id = "server-drain-url", long = "server-drain-url", env = "MOQ_SERVER_DRAIN_URL")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub url: Option<Url>,
+}
+
+impl Default for ServerDrainConfig {
+	fn default() -> Self {
+		Self {
+			timeout: Some(Duration::from_secs(10)),
+			url: None,
+		}
+	}
+}
+
+/// Limits on the bytes buffered for each session's subscriptions, see [moq_lite::Session::with_limits].
+///
+/// Apply them to each accepted session via [Server::limits].
+#[derive(clap::Args, Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, default)]
+#[non_exhaustive]
+pub struct ServerLimitsConfig {
+	/// The maximum number of bytes buffered across all subscriptions of a session (default: unlimited)
+	#[arg(
+		id = "server-limits-session",
+		long = "server-limits-session",
+		env = "MOQ_SERVER_LIMITS_SESSION"
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub session: Option<u64>,
+
+	/// The maximum number of bytes buffered for each subscription (default: unlimited)
+	#[arg(
+		id = "server-limits-track",
+		long = "server-limits-track",
+		env = "MOQ_SERVER_LIMITS_TRACK"
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub track: Option<u64>,
+
+	/// The maximum number of bytes buffered for each group (default: unlimited)
+	#[arg(
+		id = "server-limits-group",
+		long = "server-limits-group",
+		env = "MOQ_SERVER_LIMITS_GROUP"
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub group: Option<u64>,
+}
+
+impl ServerLimitsConfig {
+	fn limits(&self) -> moq_lite::Limits {
+		moq_lite::Limits {
+			session: self.session,
+			track: self.track,
+			group: self.group,
+		}
+	}
+}
+
+/// QUIC transport configuration for the server.
+#[derive(clap::Args, Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, default)]
+#[non_exhaustive]
+pub struct ServerQuicConfig {
+	/// Close the connection after this much inactivity (default: 10s)
+	/// Set to 0 to disable the idle timeout.
+	#[arg(
+		id = "server-quic-idle-timeout",
+		long = "server-quic-idle-timeout",
+		env = "MOQ_SERVER_QUIC_IDLE_TIMEOUT",
+		default_value = "10s",
+		value_parser = humantime::parse_duration,
+	)]
+	#[serde(with = "humantime_serde")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub idle_timeout: Option<Duration>,
+
+	/// Send a keep-alive at this interval to prevent the idle timeout (default: 4s)
+	/// Set to 0 to disable keep-alives.
+	#[arg(
+		id = "server-quic-keep-alive",
+		long = "server-quic-keep-alive",
+		env = "MOQ_SERVER_QUIC_KEEP_ALIVE",
+		default_value = "4s",
+		value_parser = humantime::parse_duration,
+	)]
+	#[serde(with = "humantime_serde")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub keep_alive: Option<Duration>,
+
+	/// Enable path MTU discovery, which is disabled by default.
+	#[arg(
+		id = "server-quic-mtu-discovery",
+		long = "server-quic-mtu-discovery",
+		env = "MOQ_SERVER_QUIC_MTU_DISCOVERY",
+		action = clap::ArgAction::SetTrue
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub mtu_discovery: Option<bool>,
+
+	/// The maximum number of unacknowledged bytes per stream.
+	#[arg(
+		id = "server-quic-stream-window",
+		long = "server-quic-stream-window",
+		env = "MOQ_SERVER_QUIC_STREAM_WINDOW"
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub stream_window: Option<u64>,
+
+	/// The maximum number of unacknowledged bytes across all streams.
+	#[arg(
+		id = "server-quic-connection-window",
+		long = "server-quic-connection-window",
+		env = "MOQ_SERVER_QUIC_CONNECTION_WINDOW"
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub connection_window: Option<u64>,
+
+	/// The maximum number of concurrent unidirectional streams the peer may open.
+	#[arg(
+		id = "server-quic-max-uni-streams",
+		long = "server-quic-max-uni-streams",
+		env = "MOQ_SERVER_QUIC_MAX_UNI_STREAMS"
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub max_uni_streams: Option<u64>,
+
+	/// The congestion controller to use (default: cubic)
+	#[arg(
+		id = "server-quic-congestion",
+		long = "server-quic-congestion",
+		env = "MOQ_SERVER_QUIC_CONGESTION",
+		value_enum
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub congestion: Option<CongestionControl>,
+}
+
+impl Default for ServerQuicConfig {
+	fn default() -> Self {
+		Self {
+			idle_timeout: Some(Duration::from_secs(10)),
+			keep_alive: Some(Duration::from_secs(4)),
+			mtu_discovery: None,
+			stream_window: None,
+			connection_window: None,
+			max_uni_streams: None,
+			congestion: None,
+		}
+	}
+}
+
+impl ServerQuicConfig {
+	pub(crate) fn transport(&self) -> anyhow::Result<Arc<quinn::TransportConfig>> {
+		crate::quic::Transport {
+			idle_timeout: self.idle_timeout,
+			keep_alive: self.keep_alive,
+			mtu_discovery: self.mtu_discovery.unwrap_or_default(),
+			stream_window: self.stream_window,
+			connection_window: self.connection_window,
+			max_uni_streams: self.max_uni_streams,
+			congestion: self.congestion.unwrap_or_default(),
+		}
+		.build()
+	}
+}
+
+/// WebSocket configuration for the server.
+///
+/// Clients fall back to WebSocket when QUIC is blocked; each listener is disabled unless configured.
+#[derive(clap::Args, Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, default)]
+#[non_exhaustive]
+pub struct ServerWebSocketConfig {
+	/// Listen for unencrypted WebSocket connections on the given TCP address.
+	#[arg(
+		id = "server-websocket-bind",
+		long = "server-websocket-bind",
+		env = "MOQ_SERVER_WEBSOCKET_BIND"
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub bind: Option<net::SocketAddr>,
+
+	/// Listen for secure WebSocket connections on the given TCP address, using the same certificates as QUIC.
+	#[arg(
+		id = "server-websocket-tls-bind",
+		long = "server-websocket-tls-bind",
+		env = "MOQ_SERVER_WEBSOCKET_TLS_BIND"
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub tls_bind: Option<net::SocketAddr>,
+
+	/// Listen for unencrypted WebSocket connections on the given unix socket, ex. behind a reverse proxy.
+	#[arg(
+		id = "server-websocket-unix",
+		long = "server-websocket-unix",
+		env = "MOQ_SERVER_WEBSOCKET_UNIX"
+	)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub unix: Option<PathBuf>,
 }
 
 /// Configuration for the MoQ server.
@@ -80,9 +372,25 @@ pub struct ServerConfig {
 	#[serde(default, skip_serializing_if = "Option::is_none")]
 	pub quic_lb_nonce: Option<usize>,
 
+	#[command(flatten)]
+	#[serde(default)]
+	pub quic: ServerQuicConfig,
+
 	#[command(flatten)]
 	#[serde(default)]
 	pub tls: ServerTlsConfig,
+
+	#[command(flatten)]
+	#[serde(default)]
+	pub drain: ServerDrainConfig,
+
+	#[command(flatten)]
+	#[serde(default)]
+	pub websocket: ServerWebSocketConfig,
+
+	#[command(flatten)]
+	#[serde(default)]
+	pub limits: ServerLimitsConfig,
 }
 
 impl ServerConfig {
@@ -91,27 +399,25 @@ impl ServerConfig {
 	}
 }
 
-/// Server for accepting MoQ connections over QUIC.
+/// Server for accepting MoQ connections over QUIC, and optionally WebSocket.
 ///
 /// Create via [`ServerConfig::init`] or [`Server::new`].
 pub struct Server {
 	quic: quinn::Endpoint,
 	accept: FuturesUnordered<BoxFuture<'static, anyhow::Result<Request>>>,
 	certs: Arc<ServeCerts>,
+	acme: AcmeChallenges,
+	drain: watch::Sender<Option<Option<Url>>>,
+	drain_config: ServerDrainConfig,
+	websocket: Vec<WebSocketListener>,
+	limits: moq_lite::Limits,
 	#[cfg(feature = "iroh")]
 	iroh: Option<iroh::Endpoint>,
 }
 
 impl Server {
 	pub fn new(config: ServerConfig) -> anyhow::Result<Self> {
-		// Enable BBR congestion control
-		// TODO Validate the BBR implementation before enabling it
-		let mut transport = quinn::TransportConfig::default();
-		transport.max_idle_timeout(Some(Duration::from_secs(10).try_into().unwrap()));
-		transport.keep_alive_interval(Some(Duration::from_secs(4)));
-		//transport.congestion_controller_factory(Arc::new(quinn::congestion::BbrConfig::default()));
-		transport.mtu_discovery_config(None); // Disable MTU discovery
-		let transport = Arc::new(transport);
+		let transport = config.quic.transport()?;
 
 		let provider = crypto::provider();
 
@@ -121,12 +427,31 @@ impl Server {
 
 		let certs = Arc::new(certs);
 
+		if !config.tls.generate.is_empty() {
+			certs.rotate(&config.tls.generate)?;
+			tokio::spawn(Self::regenerate_certs(certs.clone(), config.tls.generate.clone()));
+		}
+
 		#[cfg(unix)]
 		tokio::spawn(Self::reload_certs(certs.clone(), config.tls.clone()));
 
+		if let Some(interval) = config.tls.watch {
+			tokio::spawn(Self::watch_certs(certs.clone(), config.tls.clone(), interval));
+		}
+
+		let acme = AcmeChallenges::default();
+		if !config.tls.acme.domain.is_empty() {
+			let certs = certs.clone();
+			let acme = Acme::new(config.tls.acme.clone(), certs.provider.clone(), acme.clone());
+			tokio::spawn(acme.run(move |cert| certs.set_acme(cert)));
+		}
+
+		let client_verifier = config.tls.client_verifier(&provider)?;
+		let websocket = Self::bind_websocket(&config.websocket, &provider, &certs, &client_verifier)?;
+
 		let mut tls = rustls::ServerConfig::builder_with_provider(provider)
 			.with_protocol_versions(&[&rustls::version::TLS13])?
-			.with_no_client_auth()
+			.with_client_cert_verifier(client_verifier)
 			.with_cert_resolver(certs.clone());
 
 		tls.alpn_protocols = vec![
@@ -171,11 +496,48 @@ impl Server {
 			quic: quic.clone(),
 			accept: Default::default(),
 			certs,
+			acme,
+			drain: Default::default(),
+			drain_config: config.drain,
+			websocket,
+			limits: config.limits.limits(),
 			#[cfg(feature = "iroh")]
 			iroh: None,
 		})
 	}
 
+	fn bind_websocket(
+		config: &ServerWebSocketConfig,
+		provider: &crypto::Provider,
+		certs: &Arc<ServeCerts>,
+		client_verifier: &Arc<dyn ClientCertVerifier>,
+	) -> anyhow::Result<Vec<WebSocketListener>> {
+		let mut listeners = Vec::new();
+
+		if let Some(bind) = config.bind {
+			listeners.push(WebSocketListener::bind(bind)?);
+			tracing::info!(%bind, "listening for WebSocket");
+		}
+
+		if let Some(bind) = config.tls_bind {
+			let mut tls = rustls::ServerConfig::builder_with_provider(provider.clone())
+				.with_safe_default_protocol_versions()?
+				.with_client_cert_verifier(client_verifier.clone())
+				.with_cert_resolver(certs.clone());
+			tls.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+			listeners.push(WebSocketListener::bind_tls(bind, tls)?);
+			tracing::info!(%bind, "listening for secure WebSocket");
+		}
+
+		if let Some(path) = &config.unix {
+			listeners.push(WebSocketListener::bind_unix(path.clone())?);
+			tracing::info!(path = %path.display(), "listening for WebSocket");
+		}
+
+		Ok(listeners)
+	}
+
 	#[cfg(feature = "iroh")]
 	pub fn with_iroh(&mut self, iroh: Option<iroh::Endpoint>) -> &mut Self {
 		self.iroh = iroh;
@@ -198,17 +560,55 @@ impl Server {
 		}
 	}
 
-	// Return the SHA256 fingerprints of all our certificates.
+	async fn watch_certs(certs: Arc<ServeCerts>, tls_config: ServerTlsConfig, interval: Duration) {
+		let paths = tls_config.cert.iter().chain(tls_config.key.iter()).cloned().collect();
+		let mut watch = FileWatch::new(paths, interval);
+
+		loop {
+			watch.changed().await;
+			tracing::info!("server certificates changed, reloading");
+
+			// The old certificates are kept if the new ones fail to load, ex. the cert was written before the key.
+			if let Err(err) = certs.load_certs(&tls_config) {
+				tracing::warn!(%err, "failed to reload server certificates");
+			}
+		}
+	}
+
+	async fn regenerate_certs(certs: Arc<ServeCerts>, hostnames: Vec<String>) {
+		loop {
+			tokio::time::sleep(GENERATE_INTERVAL).await;
+			tracing::info!("regenerating self-signed certificate");
+
+			if let Err(err) = certs.rotate(&hostnames) {
+				tracing::warn!(%err, "failed to regenerate self-signed certificate");
+			}
+		}
+	}
+
+	/// Returns the pending ACME HTTP-01 challenges, which must be served over HTTP.
+	///
+	/// This is empty unless [AcmeConfig::domain] is configured.
+	pub fn acme_challenges(&self) -> AcmeChallenges {
+		self.acme.clone()
+	}
+
+	/// Returns the configured limits, which should be applied to each session via [moq_lite::Session::with_limits].
+	pub fn limits(&self) -> moq_lite::Limits {
+		self.limits.clone()
+	}
+
+	/// Returns the fingerprints of all our certificates, updated as they're reloaded or regenerated.
 	pub fn tls_info(&self) -> Arc<RwLock<ServerTlsInfo>> {
 		self.certs.info.clone()
 	}
 
-	/// Returns the next partially established QUIC or WebTransport session.
+	/// Returns the next partially established QUIC, WebTransport, or WebSocket session.
 	///
 	/// This returns a [Request] instead of a [web_transport_quinn::Session]
 	/// so the connection can be rejected early on an invalid path or missing auth.
 	///
-	/// The [Request] is either a WebTransport or a raw QUIC request.
+	/// The [Request] is either a WebTransport, a raw QUIC, or a WebSocket request.
 	/// Call [Request::accept] or [Request::reject] to complete the handshake.
 	pub async fn accept(&mut self) -> Option<Request> {
 		loop {
@@ -226,6 +626,17 @@ impl Server {
 				std::future::pending::<()>().await
 			};
 
+			// Wait for the first WebSocket listener to accept a connection, if any are configured.
+			let websocket = &self.websocket;
+			let websocket_accept_fut = async {
+				if websocket.is_empty() {
+					return std::future::pending().await;
+				}
+
+				let accepts = websocket.iter().map(|listener| listener.accept().boxed());
+				futures::future::select_all(accepts).await.0
+			};
+
 			tokio::select! {
 				res = self.quic.accept() => {
 					let conn = res?;
@@ -240,6 +651,12 @@ impl Server {
 					#[cfg(not(feature = "iroh"))]
 					let _: () = res;
 				}
+				res = websocket_accept_fut => {
+					match res {
+						Ok(handshake) => self.accept.push(handshake),
+						Err(err) => tracing::warn!(%err, "failed to accept WebSocket"),
+					}
+				}
 				Some(res) = self.accept.next() => {
 					match res {
 						Ok(session) => return Some(session),
@@ -247,10 +664,7 @@ impl Server {
 					}
 				}
 				_ = tokio::signal::ctrl_c() => {
-					self.close();
-					// Give it a chance to close.
-					tokio::time::sleep(Duration::from_millis(100)).await;
-
+					self.drain().await;
 					return None;
 				}
 			}
@@ -282,12 +696,16 @@ impl Server {
 		match alpn.as_str() {
 			web_transport_quinn::ALPN => {
 				// Wait for the CONNECT request.
-				let request = web_transport_quinn::Request::accept(conn)
+				let request = WebTransportRequest::accept(conn)
 					.await
 					.context("failed to receive WebTransport request")?;
 				Ok(Request::WebTransport(request))
 			}
-			moq_lite::lite::ALPN | moq_lite::ietf::ALPN => Ok(Request::Quic(QuicRequest::accept(conn))),
+			moq_lite::lite::ALPN | moq_lite::ietf::ALPN => {
+				// Wait for the SETUP message, which contains the path.
+				let request = QuicRequest::accept(conn).await.context("failed to receive MoQ setup")?;
+				Ok(Request::Quic(request))
+			}
 			_ => anyhow::bail!("unsupported ALPN: {alpn}"),
 		}
 	}
@@ -306,7 +724,10 @@ impl Server {
 				Ok(Request::IrohWebTransport(request))
 			}
 			moq_lite::lite::ALPN | moq_lite::ietf::ALPN => {
-				let request = IrohQuicRequest::accept(conn);
+				// Wait for the SETUP message, which contains the path.
+				let request = IrohQuicRequest::accept(conn)
+					.await
+					.context("failed to receive MoQ setup")?;
 				Ok(Request::IrohQuic(request))
 			}
 			_ => Err(anyhow::anyhow!("unsupported ALPN: {alpn}")),
@@ -323,18 +744,101 @@ impl Server {
 	}
 
 	pub fn close(&mut self) {
+		self.websocket.clear();
 		self.quic.close(quinn::VarInt::from_u32(0), b"server shutdown");
 	}
+
+	/// Returns a handle that resolves when the server starts draining.
+	///
+	/// Hold this for the lifetime of each session and send a GOAWAY when it resolves.
+	/// [Self::drain] will finish early once every handle has been dropped.
+	pub fn draining(&self) -> Drain {
+		Drain(self.drain.subscribe())
+	}
+
+	/// Returns a handle that creates [Drain] handles, for sessions accepted outside of this server.
+	///
+	/// For example, a WebSocket served by a separate HTTP server should still be drained.
+	/// Unlike a [Drain], holding this doesn't delay [Self::drain].
+	pub fn drainer(&self) -> Drainer {
+		Drainer(self.drain.clone())
+	}
+
+	/// Gracefully shut down the server, performed automatically on ctrl-c.
+	///
+	/// New sessions are refused and every [Drain] handle is notified, giving clients a chance to migrate.
+	/// The endpoint is closed once all handles are dropped or the configured timeout expires.
+	pub async fn drain(&mut self) {
+		// Refuse any new connections and drop any pending handshakes.
+		self.quic.set_server_config(None);
+		self.websocket.clear();
+		self.accept.clear();
+
+		let timeout = self.drain_config.timeout.unwrap_or_default();
+		tracing::info!(sessions = self.drain.receiver_count(), ?timeout, "draining server");
+
+		self.drain.send_replace(Some(self.drain_config.url.clone()));
+
+		tokio::select! {
+			_ = self.drain.closed() => tracing::info!("all sessions drained"),
+			_ = tokio::time::sleep(timeout) => tracing::warn!(sessions = self.drain.receiver_count(), "drain timeout expired"),
+		}
+
+		self.close();
+
+		// Give it a chance to close.
+		tokio::time::sleep(Duration::from_millis(100)).await;
+	}
+}
+
+/// Notifies a session when the [Server] starts draining.
+///
+/// Created via [Server::draining].
+#[derive(Clone)]
+pub struct Drain(watch::Receiver<Option<Option<Url>>>);
+
+impl Drain {
+	/// Block until the server starts draining, returning the URL clients should migrate to.
+	///
+	/// Returns [None] if clients should reconnect to the same URL.
+	pub async fn wait(&mut self) -> Option<Url> {
+		let drain = self.0.wait_for(Option::is_some).await.map(|drain| drain.clone());
+		let Ok(Some(url)) = drain else {
+			// The server was dropped, so it will never drain.
+			return std::future::pending().await;
+		};
+
+		url
+	}
+}
+
+/// Creates [Drain] handles on demand.
+///
+/// Created via [Server::drainer].
+#[derive(Clone)]
+pub struct Drainer(watch::Sender<Option<Option<Url>>>);
+
+impl Drainer {
+	/// Returns a handle that resolves when the server starts draining, like [Server::draining].
+	pub fn draining(&self) -> Drain {
+		Drain(self.0.subscribe())
+	}
+
+	/// Returns true once the server has started draining, in which case new sessions should be refused.
+	pub fn is_draining(&self) -> bool {
+		self.0.borrow().is_some()
+	}
 }
 
 /// An incoming connection that can be accepted or rejected.
 pub enum Request {
-	WebTransport(web_transport_quinn::Request),
+	WebTransport(WebTransportRequest),
 	Quic(QuicRequest),
 	#[cfg(feature = "iroh")]
 	IrohWebTransport(web_transport_iroh::H3Request),
 	#[cfg(feature = "iroh")]
 	IrohQuic(IrohQuicRequest),
+	WebSocket(WebSocketRequest),
 }
 
 impl Request {
@@ -347,6 +851,7 @@ impl Request {
 			Request::IrohWebTransport(request) => request.close(status).await?,
 			#[cfg(feature = "iroh")]
 			Request::IrohQuic(request) => request.close(status),
+			Request::WebSocket(request) => request.close(status),
 		}
 		Ok(())
 	}
@@ -358,47 +863,187 @@ impl Request {
 		subscribe: impl Into<Option<moq_lite::OriginProducer>>,
 	) -> anyhow::Result<Session> {
 		let session = match self {
-			Request::WebTransport(request) => Session::accept(request.ok().await?, publish, subscribe).await?,
-			Request::Quic(request) => Session::accept(request.ok(), publish, subscribe).await?,
+			Request::WebTransport(request) => request.ok(publish, subscribe).await?,
+			Request::Quic(request) => request.ok(publish, subscribe).await?,
 			#[cfg(feature = "iroh")]
-			Request::IrohWebTransport(request) => Session::accept(request.ok().await?, publish, subscribe).await?,
+			Request::IrohWebTransport(request) => {
+				let session = request.ok().await?;
+				let connection = (*session).clone();
+				Session::accept(Prioritized::new(session), publish, subscribe)
+					.await?
+					.with_transport_stats(move || crate::iroh::transport_stats(&connection))
+			}
 			#[cfg(feature = "iroh")]
-			Request::IrohQuic(request) => Session::accept(request.ok(), publish, subscribe).await?,
+			Request::IrohQuic(request) => request.ok(publish, subscribe).await?,
+			Request::WebSocket(request) => request.ok(publish, subscribe).await?,
 		};
 		Ok(session)
 	}
 
 	/// Returns the URL provided by the client.
+	///
+	/// For raw QUIC, the path and query are provided in the SETUP message.
 	pub fn url(&self) -> Option<&Url> {
 		match self {
 			Request::WebTransport(request) => Some(request.url()),
+			Request::Quic(request) => Some(request.url()),
 			#[cfg(feature = "iroh")]
 			Request::IrohWebTransport(request) => Some(request.url()),
-			_ => None,
+			#[cfg(feature = "iroh")]
+			Request::IrohQuic(request) => Some(request.url()),
+			Request::WebSocket(request) => Some(request.url()),
+		}
+	}
+
+	/// Returns the certificate chain presented by the client, starting with the leaf.
+	///
+	/// This is empty unless [ServerTlsConfig::client_ca] is configured, in which case the chain has been verified.
+	/// Iroh connections are authenticated by their endpoint ID instead.
+	pub fn peer_certificates(&self) -> &[CertificateDer<'static>] {
+		match self {
+			Request::WebTransport(request) => request.peer_certificates(),
+			Request::Quic(request) => request.peer_certificates(),
+			#[cfg(feature = "iroh")]
+			Request::IrohWebTransport(_) | Request::IrohQuic(_) => &[],
+			Request::WebSocket(request) => request.peer_certificates(),
 		}
 	}
 }
 
+// Returns the certificate chain presented by the client, if any.
+fn peer_certificates(connection: &quinn::Connection) -> Vec<CertificateDer<'static>> {
+	connection
+		.peer_identity()
+		.and_then(|identity| identity.downcast::<Vec<CertificateDer<'static>>>().ok())
+		.map(|certs| *certs)
+		.unwrap_or_default()
+}
+
+/// A WebTransport connection request, awaiting the HTTP response.
+pub struct WebTransportRequest {
+	request: web_transport_quinn::Request,
+	certificates: Vec<CertificateDer<'static>>,
+}
+
+impl WebTransportRequest {
+	/// Accept a new WebTransport session from a client, waiting for the CONNECT request.
+	pub async fn accept(connection: quinn::Connection) -> anyhow::Result<Self> {
+		let certificates = peer_certificates(&connection);
+		let request = web_transport_quinn::Request::accept(connection).await?;
+
+		Ok(Self { request, certificates })
+	}
+
+	/// Accept the session, responding with a 200 OK and performing the MoQ handshake.
+	pub async fn ok(
+		self,
+		publish: impl Into<Option<moq_lite::OriginConsumer>>,
+		subscribe: impl Into<Option<moq_lite::OriginProducer>>,
+	) -> anyhow::Result<Session> {
+		let session = self.request.ok().await?;
+		let connection = (*session).clone();
+		let session = Session::accept(Prioritized::new(session), publish, subscribe).await?;
+		Ok(crate::quic::with_connection(session, connection))
+	}
+
+	/// Returns the URL provided by the client.
+	pub fn url(&self) -> &Url {
+		self.request.url()
+	}
+
+	/// Returns the certificate chain presented by the client, see [Request::peer_certificates].
+	pub fn peer_certificates(&self) -> &[CertificateDer<'static>] {
+		&self.certificates
+	}
+
+	/// Reject the session, returning your favorite HTTP status code.
+	pub async fn close(self, status: http::StatusCode) -> anyhow::Result<()> {
+		self.request.close(status).await?;
+		Ok(())
+	}
+}
+
+/// How long a raw QUIC client has to send the SETUP message with its path.
+pub(crate) const SETUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Set the path and query provided by the client in the SETUP message.
+///
+/// Unlike [Url::join], the client can't change the scheme or host with an absolute URL or a `//host` path.
+pub(crate) fn set_path(url: &mut Url, path: &str) -> anyhow::Result<()> {
+	let (path, query) = match path.split_once('?') {
+		Some((path, query)) => (path, Some(query)),
+		None => (path, None),
+	};
+
+	// A URL without a path, like `moql://host:port`, has an empty path.
+	let path = if path.is_empty() { "/" } else { path };
+	anyhow::ensure!(path.starts_with('/') && !path.starts_with("//"), "invalid path: {path}");
+
+	url.set_path(path);
+	url.set_query(query);
+
+	Ok(())
+}
+
 /// A raw QUIC connection request without WebTransport framing.
 ///
 /// Used to accept/reject QUIC connections.
 pub struct QuicRequest {
+	// Boxed because the SETUP stream makes this much larger than the other requests.
+	request: Box<moq_lite::SessionRequest<Prioritized<web_transport_quinn::Session>>>,
 	connection: quinn::Connection,
 	url: Url,
+	certificates: Vec<CertificateDer<'static>>,
 }
 
 impl QuicRequest {
-	/// Accept a new QUIC session from a client.
-	pub fn accept(connection: quinn::Connection) -> Self {
-		let url: Url = format!("moql://{}", connection.remote_address())
-			.parse()
-			.expect("URL is valid");
-		Self { connection, url }
+	/// Accept a new QUIC session from a client, waiting for the MoQ SETUP message.
+	///
+	/// The URL is built from the ALPN, the SNI (or remote address), and the path provided in the SETUP.
+	pub async fn accept(connection: quinn::Connection) -> anyhow::Result<Self> {
+		let handshake = connection
+			.handshake_data()
+			.and_then(|data| data.downcast::<quinn::crypto::rustls::HandshakeData>().ok());
+
+		let scheme = match handshake.as_ref().and_then(|data| data.protocol.as_deref()) {
+			Some(alpn) if alpn == moq_lite::ietf::ALPN.as_bytes() => "moqt",
+			_ => "moql",
+		};
+		let host = match handshake.and_then(|data| data.server_name) {
+			Some(host) => host,
+			None => connection.remote_address().to_string(),
+		};
+
+		let mut url: Url = format!("{scheme}://{host}").parse().context("invalid host")?;
+
+		let session = web_transport_quinn::Session::raw(connection.clone(), url.clone());
+		let session = Prioritized::new(session);
+		let request = tokio::time::timeout(SETUP_TIMEOUT, moq_lite::SessionRequest::accept(session))
+			.await
+			.context("timed out waiting for SETUP")??;
+		let request = Box::new(request);
+
+		if let Some(path) = request.path() {
+			set_path(&mut url, &path)?;
+		}
+
+		Ok(Self {
+			request,
+			certificates: peer_certificates(&connection),
+			connection,
+			url,
+		})
 	}
 
-	/// Accept the session, returning a 200 OK if using WebTransport.
-	pub fn ok(self) -> web_transport_quinn::Session {
-		web_transport_quinn::Session::raw(self.connection, self.url)
+	/// Accept the session, performing the rest of the MoQ handshake.
+	pub async fn ok(
+		self,
+		publish: impl Into<Option<moq_lite::OriginConsumer>>,
+		subscribe: impl Into<Option<moq_lite::OriginProducer>>,
+	) -> Result<Session, moq_lite::Error> {
+		let session = self.request.ok(publish, subscribe).await?;
+		let connection = self.connection;
+		Ok(crate::quic::with_connection(session, connection))
 	}
 
 	/// Returns the URL provided by the client.
@@ -406,6 +1051,11 @@ impl QuicRequest {
 		&self.url
 	}
 
+	/// Returns the certificate chain presented by the client, see [Request::peer_certificates].
+	pub fn peer_certificates(&self) -> &[CertificateDer<'static>] {
+		&self.certificates
+	}
+
 	/// Reject the session with a status code.
 	///
 	/// The status code number will be used as the error code.
@@ -419,13 +1069,43 @@ impl QuicRequest {
 #[derive(Debug)]
 pub struct ServerTlsInfo {
 	pub(crate) certs: Vec<Arc<CertifiedKey>>,
+	/// The hex-encoded SHA-256 fingerprint of each certificate, starting with the preferred one.
 	pub fingerprints: Vec<String>,
+	/// The same fingerprints including the algorithm and expiration, like the WebTransport `serverCertificateHashes` option.
+	pub hashes: Vec<Fingerprint>,
 }
 
+/// The fingerprint of a certificate, used to trust a self-signed certificate without a CA.
+///
+/// Serialized in the same form as the WebTransport `serverCertificateHashes` option, plus the expiration.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[non_exhaustive]
+pub struct Fingerprint {
+	/// The hash algorithm, currently always `sha-256`.
+	pub algorithm: String,
+
+	/// The hex-encoded hash of the DER certificate.
+	pub value: String,
+
+	/// When the certificate expires, if known.
+	#[serde(default, with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+	pub expires: Option<SystemTime>,
+}
+
+// WebTransport requires self-signed certificates to be valid for at most 14 days,
+// so regenerate them weekly, giving clients a week to pick up the new fingerprint.
+const GENERATE_INTERVAL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
 #[derive(Debug)]
 struct ServeCerts {
 	info: Arc<RwLock<ServerTlsInfo>>,
 	provider: crypto::Provider,
+	// Certificates loaded from disk, replaced on reload.
+	loaded: Mutex<Vec<Arc<CertifiedKey>>>,
+	// Self-signed certificates, newest first, kept until they expire.
+	generated: Mutex<Vec<Arc<CertifiedKey>>>,
+	// The latest certificate obtained via ACME.
+	acme: Mutex<Option<Arc<CertifiedKey>>>,
 }
 
 impl ServeCerts {
@@ -434,8 +1114,12 @@ impl ServeCerts {
 			info: Arc::new(RwLock::new(ServerTlsInfo {
 				certs: Vec::new(),
 				fingerprints: Vec::new(),
+				hashes: Vec::new(),
 			})),
 			provider,
+			loaded: Default::default(),
+			generated: Default::default(),
+			acme: Default::default(),
 		}
 	}
 
@@ -449,15 +1133,41 @@ impl ServeCerts {
 			certs.push(Arc::new(self.load(cert, key)?));
 		}
 
-		// Generate a new certificate if requested.
-		if !config.generate.is_empty() {
-			certs.push(Arc::new(self.generate(&config.generate)?));
-		}
+		*self.loaded.lock().unwrap() = certs;
+		self.update();
+
+		Ok(())
+	}
+
+	// Replace the ACME certificate, keeping any loaded certificates.
+	pub fn set_acme(&self, cert: Arc<CertifiedKey>) {
+		*self.acme.lock().unwrap() = Some(cert);
+		self.update();
+	}
+
+	// Generate a new self-signed certificate, keeping the previous ones until they expire.
+	pub fn rotate(&self, hostnames: &[String]) -> anyhow::Result<()> {
+		let cert = Arc::new(self.generate(hostnames)?);
+		let now = SystemTime::now();
+
+		let mut generated = self.generated.lock().unwrap();
+		generated.retain(|cert| expires(&cert.cert[0]).is_none_or(|expires| expires > now));
+		generated.insert(0, cert);
+		drop(generated);
+
+		self.update();
 
-		self.set_certs(certs);
 		Ok(())
 	}
 
+	// Combine the ACME, loaded, and generated certificates, preferring the ACME certificate.
+	fn update(&self) {
+		let acme = self.acme.lock().unwrap().clone();
+		let loaded = self.loaded.lock().unwrap().clone();
+		let generated = self.generated.lock().unwrap().clone();
+		self.set_certs(acme.into_iter().chain(loaded).chain(generated).collect());
+	}
+
 	// Load a certificate and corresponding key from a file, but don't add it to the certs
 	fn load(&self, chain_path: &PathBuf, key_path: &PathBuf) -> anyhow::Result<CertifiedKey> {
 		let chain = fs::File::open(chain_path).context("failed to open cert file")?;
@@ -514,17 +1224,19 @@ impl ServeCerts {
 
 	// Replace the certificates
 	pub fn set_certs(&self, certs: Vec<Arc<CertifiedKey>>) {
-		let fingerprints = certs
+		let hashes: Vec<_> = certs
 			.iter()
-			.map(|ck| {
-				let fingerprint = crate::crypto::sha256(&self.provider, ck.cert[0].as_ref());
-				hex::encode(fingerprint)
+			.map(|ck| Fingerprint {
+				algorithm: "sha-256".to_string(),
+				value: hex::encode(crate::crypto::sha256(&self.provider, ck.cert[0].as_ref())),
+				expires: expires(&ck.cert[0]),
 			})
 			.collect();
 
 		let mut info = self.info.write().expect("info write lock poisoned");
 		info.certs = certs;
-		info.fingerprints = fingerprints;
+		info.fingerprints = hashes.iter().map(|hash| hash.value.clone()).collect();
+		info.hashes = hashes;
 	}
 
 	// Return the best certificate for the given ClientHello.
@@ -548,6 +1260,13 @@ impl ServeCerts {
 	}
 }
 
+// Returns when the certificate expires, or None if it can't be parsed.
+fn expires(cert: &CertificateDer) -> Option<SystemTime> {
+	let (_, parsed) = x509_parser::parse_x509_certificate(cert).ok()?;
+	let expires = parsed.validity().not_after.timestamp();
+	Some(UNIX_EPOCH + Duration::from_secs(expires.max(0) as u64))
+}
+
 impl ResolvesServerCert for ServeCerts {
 	fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
 		if let Some(cert) = self.best_certificate(&client_hello) {
@@ -633,3 +1352,27 @@ impl quinn::ConnectionIdGenerator for ServerIdGenerator {
 		None
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn setup_path() {
+		let base: Url = "moql://relay.example".parse().unwrap();
+
+		let mut url = base.clone();
+		set_path(&mut url, "/demo/room?jwt=abc").unwrap();
+		assert_eq!(url.as_str(), "moql://relay.example/demo/room?jwt=abc");
+
+		let mut url = base.clone();
+		set_path(&mut url, "?jwt=abc").unwrap();
+		assert_eq!(url.as_str(), "moql://relay.example/?jwt=abc");
+
+		// The client can't redirect the URL to another host or scheme.
+		for path in ["//evil.host/x", "https://other/x", "demo"] {
+			let mut url = base.clone();
+			assert!(set_path(&mut url, path).is_err(), "{path}");
+		}
+	}
+}
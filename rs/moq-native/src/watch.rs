@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// Polls a set of files, resolving when any of them are modified.
+///
+/// Symlinks are followed, so this also detects Kubernetes-style volume updates that swap a symlink.
+/// Files are polled instead of using inotify/kqueue so it works the same on every platform and filesystem.
+pub struct FileWatch {
+	paths: Vec<PathBuf>,
+	interval: Duration,
+	modified: Vec<Option<SystemTime>>,
+}
+
+impl FileWatch {
+	/// Watch the given files, checking for changes at the given interval.
+	pub fn new(paths: Vec<PathBuf>, interval: Duration) -> Self {
+		let modified = paths.iter().map(Self::modified).collect();
+
+		Self {
+			paths,
+			interval,
+			modified,
+		}
+	}
+
+	fn modified(path: &PathBuf) -> Option<SystemTime> {
+		std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+	}
+
+	/// Block until any of the files has been modified, created, or removed since the last call.
+	pub async fn changed(&mut self) {
+		loop {
+			tokio::time::sleep(self.interval).await;
+
+			let modified: Vec<_> = self.paths.iter().map(Self::modified).collect();
+			if modified != self.modified {
+				self.modified = modified;
+				return;
+			}
+		}
+	}
+}
@@ -0,0 +1,195 @@
+use std::{io, net, path::PathBuf, sync::Arc};
+
+use rustls::pki_types::CertificateDer;
+
+use anyhow::Context;
+use futures::FutureExt;
+use futures::future::BoxFuture;
+use tokio::io::{AsyncRead, AsyncWrite};
+use url::Url;
+use web_transport_quinn::http;
+use web_transport_trait::Session as _;
+use web_transport_ws::{tokio_tungstenite, tungstenite};
+
+use crate::Request;
+
+/// A listener for WebSocket connections, used as a fallback when QUIC is blocked.
+pub(crate) enum WebSocketListener {
+	Tcp(tokio::net::TcpListener),
+	Tls(tokio::net::TcpListener, tokio_rustls::TlsAcceptor),
+	#[cfg(unix)]
+	Unix(tokio::net::UnixListener, PathBuf),
+}
+
+impl WebSocketListener {
+	pub fn bind(addr: net::SocketAddr) -> anyhow::Result<Self> {
+		Ok(Self::Tcp(Self::bind_tcp(addr)?))
+	}
+
+	pub fn bind_tls(addr: net::SocketAddr, tls: rustls::ServerConfig) -> anyhow::Result<Self> {
+		let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(tls));
+		Ok(Self::Tls(Self::bind_tcp(addr)?, acceptor))
+	}
+
+	fn bind_tcp(addr: net::SocketAddr) -> anyhow::Result<tokio::net::TcpListener> {
+		let listener = net::TcpListener::bind(addr).context("failed to bind TCP socket")?;
+		listener.set_nonblocking(true)?;
+		Ok(tokio::net::TcpListener::from_std(listener)?)
+	}
+
+	#[cfg(unix)]
+	pub fn bind_unix(path: PathBuf) -> anyhow::Result<Self> {
+		use std::os::unix::fs::FileTypeExt;
+
+		// Remove a stale socket left behind by a previous run, but never a regular file.
+		if let Ok(metadata) = std::fs::symlink_metadata(&path)
+			&& metadata.file_type().is_socket()
+		{
+			std::fs::remove_file(&path).context("failed to remove stale unix socket")?;
+		}
+
+		let listener = std::os::unix::net::UnixListener::bind(&path).context("failed to bind unix socket")?;
+		listener.set_nonblocking(true)?;
+		Ok(Self::Unix(tokio::net::UnixListener::from_std(listener)?, path))
+	}
+
+	#[cfg(not(unix))]
+	pub fn bind_unix(_path: PathBuf) -> anyhow::Result<Self> {
+		anyhow::bail!("unix sockets are not supported on this platform")
+	}
+
+	/// Wait for the next connection, returning a future that performs the handshake.
+	pub async fn accept(&self) -> io::Result<BoxFuture<'static, anyhow::Result<Request>>> {
+		match self {
+			Self::Tcp(listener) => {
+				let (stream, addr) = listener.accept().await?;
+				tracing::debug!(ip = %addr, "accepting WebSocket");
+				Ok(handshake(stream, "ws", Vec::new()).boxed())
+			}
+			Self::Tls(listener, acceptor) => {
+				let (stream, addr) = listener.accept().await?;
+				tracing::debug!(ip = %addr, "accepting secure WebSocket");
+
+				let acceptor = acceptor.clone();
+				Ok(async move {
+					let stream = acceptor.accept(stream).await.context("failed to accept TLS")?;
+					let certificates = stream.get_ref().1.peer_certificates().unwrap_or_default().to_vec();
+					handshake(stream, "wss", certificates).await
+				}
+				.boxed())
+			}
+			#[cfg(unix)]
+			Self::Unix(listener, path) => {
+				let (stream, _) = listener.accept().await?;
+				tracing::debug!(path = %path.display(), "accepting WebSocket");
+				Ok(handshake(stream, "ws", Vec::new()).boxed())
+			}
+		}
+	}
+}
+
+#[cfg(unix)]
+impl Drop for WebSocketListener {
+	fn drop(&mut self) {
+		if let Self::Unix(_, path) = self {
+			std::fs::remove_file(path).ok();
+		}
+	}
+}
+
+// Perform the HTTP upgrade, recording the URL requested by the client.
+async fn handshake<T>(stream: T, scheme: &str, certificates: Vec<CertificateDer<'static>>) -> anyhow::Result<Request>
+where
+	T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+	let mut url = None;
+
+	// The error type is dictated by tungstenite.
+	#[allow(clippy::result_large_err)]
+	let callback = |request: &tungstenite::handshake::server::Request,
+	             mut response: tungstenite::handshake::server::Response| {
+		// Unix sockets don't necessarily have a host, so default to localhost.
+		let host = request
+			.headers()
+			.get(http::header::HOST)
+			.and_then(|host| host.to_str().ok())
+			.unwrap_or("localhost");
+		url = Some(format!("{scheme}://{host}{}", request.uri()));
+
+		// Browsers request the WebTransport polyfill protocol, but our client doesn't.
+		let protocols = request
+			.headers()
+			.get(http::header::SEC_WEBSOCKET_PROTOCOL)
+			.and_then(|protocols| protocols.to_str().ok())
+			.unwrap_or_default();
+
+		if protocols.split(',').any(|p| p.trim() == web_transport_ws::ALPN) {
+			response.headers_mut().insert(
+				http::header::SEC_WEBSOCKET_PROTOCOL,
+				http::HeaderValue::from_static(web_transport_ws::ALPN),
+			);
+		}
+
+		Ok(response)
+	};
+
+	let config = tungstenite::protocol::WebSocketConfig {
+		max_message_size: Some(64 << 20), // 64 MB
+		max_frame_size: Some(16 << 20),   // 16 MB
+		accept_unmasked_frames: false,
+		..Default::default()
+	};
+
+	let ws = tokio_tungstenite::accept_hdr_async_with_config(stream, callback, Some(config))
+		.await
+		.context("failed to accept WebSocket")?;
+
+	let url = url.context("missing URL")?.parse().context("invalid URL")?;
+	tracing::debug!(%url, "accepted WebSocket");
+
+	// Wrap the WebSocket in a WebTransport compatibility layer.
+	let session = web_transport_ws::Session::new(ws, true);
+
+	Ok(Request::WebSocket(WebSocketRequest {
+		session,
+		url,
+		certificates,
+	}))
+}
+
+/// A WebSocket connection request, using the WebTransport polyfill.
+///
+/// The WebSocket handshake has already completed, so rejecting the request closes the session with the status code.
+pub struct WebSocketRequest {
+	session: web_transport_ws::Session,
+	url: Url,
+	certificates: Vec<CertificateDer<'static>>,
+}
+
+impl WebSocketRequest {
+	/// Accept the session, performing the MoQ handshake.
+	pub async fn ok(
+		self,
+		publish: impl Into<Option<moq_lite::OriginConsumer>>,
+		subscribe: impl Into<Option<moq_lite::OriginProducer>>,
+	) -> Result<moq_lite::Session, moq_lite::Error> {
+		moq_lite::Session::accept(self.session, publish, subscribe).await
+	}
+
+	/// Returns the URL provided by the client.
+	pub fn url(&self) -> &Url {
+		&self.url
+	}
+
+	/// Returns the certificate chain presented by the client, see [Request::peer_certificates].
+	pub fn peer_certificates(&self) -> &[CertificateDer<'static>] {
+		&self.certificates
+	}
+
+	/// Reject the session with a status code.
+	///
+	/// The status code number will be used as the error code.
+	pub fn close(self, status: http::StatusCode) {
+		self.session.close(status.as_u16().into(), status.as_str());
+	}
+}
@@ -0,0 +1,72 @@
+//! Connect to a [moq_native::Server] over QUIC using a hostname.
+
+use anyhow::Context;
+
+fn server() -> anyhow::Result<(moq_native::Server, u16)> {
+	let mut config = moq_native::ServerConfig::default();
+	config.bind = Some("127.0.0.1:0".parse()?);
+	config.tls.generate = vec!["localhost".to_string()];
+	let server = config.init()?;
+	let port = server.local_addr()?.port();
+	Ok((server, port))
+}
+
+#[tokio::test]
+async fn hostname() -> anyhow::Result<()> {
+	let (mut server, port) = server()?;
+
+	// The default dual-stack socket races every address that localhost resolves to.
+	let mut config = moq_native::ClientConfig::default();
+	config.tls.disable_verify = Some(true);
+	let client = config.init()?;
+
+	let url = format!("moql://localhost:{port}/demo?jwt=token").parse()?;
+	let (client, (server, url)) = tokio::try_join!(client.connect(url, None, None), async {
+		let request = server.accept().await.context("server closed")?;
+		let url = request.url().context("missing url")?.clone();
+		let session = request.accept(None, None).await?;
+		anyhow::Ok((session, url))
+	})?;
+
+	// The path and query are sent in the SETUP message.
+	assert_eq!(url.as_str(), "moql://localhost/demo?jwt=token");
+
+	// Both sides report the QUIC protocol and statistics.
+	assert_eq!(client.protocol(), Some(moq_native::moq_lite::lite::ALPN));
+	assert_eq!(server.protocol(), Some(moq_native::moq_lite::lite::ALPN));
+
+	for stats in [client.stats(), server.stats()] {
+		assert_eq!(stats.version, client.version());
+		let transport = stats.transport.context("missing transport stats")?;
+		assert!(transport.packets_sent > 0);
+		assert!(transport.bytes_received > 0);
+	}
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn negotiate() -> anyhow::Result<()> {
+	let (mut server, port) = server()?;
+
+	let mut config = moq_native::ClientConfig::default();
+	config.bind = "127.0.0.1:0".parse()?;
+	config.tls.disable_verify = Some(true);
+	let client = config.init()?;
+
+	// The "moq" scheme offers every protocol and the server picks its favorite: WebTransport.
+	let url = format!("moq://localhost:{port}/demo").parse()?;
+	let (client, (server, url)) = tokio::try_join!(client.connect(url, None, None), async {
+		let request = server.accept().await.context("server closed")?;
+		let url = request.url().context("missing url")?.clone();
+		let session = request.accept(None, None).await?;
+		anyhow::Ok((session, url))
+	})?;
+
+	assert_eq!(client.protocol(), Some(moq_native::web_transport_quinn::ALPN));
+	assert_eq!(server.protocol(), Some(moq_native::web_transport_quinn::ALPN));
+	assert_eq!(url.scheme(), "https");
+	assert_eq!(url.path(), "/demo");
+
+	Ok(())
+}
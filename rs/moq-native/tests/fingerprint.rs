@@ -0,0 +1,84 @@
+//! Connect to a self-signed [moq_native::Server] by fetching its certificate fingerprints over HTTP.
+
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+// A minimal HTTP server that serves the fingerprints, or only the legacy endpoint if `legacy` is set.
+async fn serve_http(listener: tokio::net::TcpListener, fingerprints: Vec<moq_native::Fingerprint>, legacy: bool) {
+	while let Ok((mut stream, _)) = listener.accept().await {
+		let mut request = Vec::new();
+		let mut buf = [0u8; 1024];
+		while !request.ends_with(b"\r\n\r\n") {
+			match stream.read(&mut buf).await {
+				Ok(0) | Err(_) => break,
+				Ok(size) => request.extend_from_slice(&buf[..size]),
+			}
+		}
+
+		let request = String::from_utf8_lossy(&request);
+		let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+		let (status, body) = match path {
+			"/certificate.json" if !legacy => ("200 OK", serde_json::to_string(&fingerprints).unwrap()),
+			"/certificate.sha256" => ("200 OK", fingerprints[0].value.clone()),
+			_ => ("404 Not Found", String::new()),
+		};
+
+		let response = format!(
+			"HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+			body.len()
+		);
+		stream.write_all(response.as_bytes()).await.ok();
+	}
+}
+
+async fn connect(legacy: bool) -> anyhow::Result<()> {
+	let mut config = moq_native::ServerConfig::default();
+	config.bind = Some("127.0.0.1:0".parse()?);
+	config.tls.generate = vec!["localhost".to_string()];
+	let mut server = config.init()?;
+
+	let fingerprints = server.tls_info().read().unwrap().hashes.clone();
+	assert_eq!(fingerprints.len(), 1);
+	assert_eq!(fingerprints[0].algorithm, "sha-256");
+
+	// The plain hex strings are still available.
+	assert_eq!(
+		server.tls_info().read().unwrap().fingerprints,
+		[fingerprints[0].value.clone()]
+	);
+
+	// WebTransport requires self-signed certificates to expire within two weeks.
+	let expires = fingerprints[0].expires.context("missing expiration")?;
+	assert!(expires > SystemTime::now());
+	assert!(expires < SystemTime::now() + Duration::from_secs(14 * 24 * 60 * 60));
+
+	// Serve HTTP over TCP on the same port number as QUIC.
+	let port = server.local_addr()?.port();
+	let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+	tokio::spawn(serve_http(listener, fingerprints, legacy));
+
+	let mut config = moq_native::ClientConfig::default();
+	config.bind = "127.0.0.1:0".parse()?;
+	let client = config.init()?;
+
+	let url = format!("http://localhost:{port}/demo").parse()?;
+	tokio::try_join!(client.connect(url, None, None), async {
+		let request = server.accept().await.context("server closed")?;
+		request.accept(None, None).await
+	})?;
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn json() -> anyhow::Result<()> {
+	connect(false).await
+}
+
+#[tokio::test]
+async fn legacy() -> anyhow::Result<()> {
+	connect(true).await
+}
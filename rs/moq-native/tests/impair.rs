@@ -0,0 +1,313 @@
+//! Scenario tests that relay a session through an [ImpairedProxy] to see how MoQ behaves under congestion.
+//!
+//! These use real sockets and the real clock, so paused time isn't an option: quinn's timers would fire immediately.
+//! Instead the bounds are deliberately wide, several times the expected value, so a slow CI machine doesn't flake.
+
+use std::time::Duration;
+
+use anyhow::Context;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use moq_native::{ImpairedProxy, Impairment, moq_lite};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+// How long each scenario produces media.
+const DURATION: Duration = Duration::from_secs(3);
+
+// A publisher and subscriber session connected through the proxy.
+struct Harness {
+	_publisher: moq_lite::Session,
+	_subscriber: moq_lite::Session,
+	_proxy: ImpairedProxy,
+	_server: moq_native::Server,
+}
+
+async fn connect(
+	impairment: Impairment,
+	publish: moq_lite::OriginConsumer,
+	subscribe: moq_lite::OriginProducer,
+) -> anyhow::Result<Harness> {
+	let mut config = moq_native::ServerConfig::default();
+	config.bind = Some("127.0.0.1:0".parse()?);
+	config.tls.generate = vec!["localhost".to_string()];
+	let mut server = config.init()?;
+
+	let target = server.local_addr()?;
+	let proxy = ImpairedProxy::bind("127.0.0.1:0".parse()?, target, impairment.clone(), impairment).await?;
+
+	let mut config = moq_native::ClientConfig::default();
+	config.bind = "127.0.0.1:0".parse()?;
+	config.tls.disable_verify = Some(true);
+	let client = config.init()?;
+
+	let url = format!("moql://127.0.0.1:{}", proxy.local_addr().port()).parse()?;
+
+	let (publisher, subscriber) = tokio::try_join!(client.connect(url, publish, None), async {
+		let request = server.accept().await.context("server closed")?;
+		request.accept(None, subscribe).await
+	})?;
+
+	Ok(Harness {
+		_publisher: publisher,
+		_subscriber: subscriber,
+		_proxy: proxy,
+		_server: server,
+	})
+}
+
+// Wait for the broadcast to be announced by the publisher.
+async fn announced(mut origin: moq_lite::OriginConsumer) -> anyhow::Result<moq_lite::BroadcastConsumer> {
+	loop {
+		if let (_, Some(broadcast)) = origin.announced().await.context("origin closed")? {
+			return Ok(broadcast);
+		}
+	}
+}
+
+// A frame of the given size that starts with the time it was created, relative to `start`.
+fn frame(start: Instant, size: usize) -> Bytes {
+	let mut frame = BytesMut::with_capacity(size.max(8));
+	frame.put_u64(start.elapsed().as_micros() as u64);
+	frame.resize(size.max(8), 0);
+	frame.freeze()
+}
+
+// How long ago the frame was created.
+fn latency(start: Instant, mut frame: Bytes) -> Duration {
+	start.elapsed() - Duration::from_micros(frame.get_u64())
+}
+
+// Reads every frame of a track in the background, recording the group sequence and latency.
+struct Consumer {
+	task: tokio::task::JoinHandle<()>,
+	received: mpsc::UnboundedReceiver<(u64, Duration)>,
+}
+
+impl Consumer {
+	fn new(mut track: moq_lite::TrackConsumer, start: Instant) -> Self {
+		let (tx, received) = mpsc::unbounded_channel();
+
+		let task = tokio::spawn(async move {
+			while let Ok(Some(mut group)) = track.next_group().await {
+				let sequence = group.info.sequence;
+
+				// Groups that were cut short by the publisher return an error.
+				while let Ok(Some(frame)) = group.read_frame().await {
+					tx.send((sequence, latency(start, frame))).ok();
+				}
+			}
+		});
+
+		Self { task, received }
+	}
+
+	// Stop reading and return everything received so far.
+	fn finish(mut self) -> Vec<(u64, Duration)> {
+		self.task.abort();
+
+		let mut received = Vec::new();
+		while let Ok(frame) = self.received.try_recv() {
+			received.push(frame);
+		}
+		received
+	}
+}
+
+// ~1 Mbit/s with a shallow queue, far less than the video bitrate.
+fn congested() -> Impairment {
+	Impairment {
+		delay: Duration::from_millis(20),
+		bandwidth: Some(125_000),
+		queue: Some(Duration::from_millis(50)),
+		..Default::default()
+	}
+}
+
+#[tokio::test]
+async fn high_priority_keeps_flowing() -> anyhow::Result<()> {
+	let publisher = moq_lite::Origin::produce();
+	let subscriber = moq_lite::Origin::produce();
+
+	let audio = moq_lite::Track {
+		name: "audio".to_string(),
+		priority: 2,
+	};
+	let video = moq_lite::Track {
+		name: "video".to_string(),
+		priority: 1,
+	};
+
+	let mut broadcast = moq_lite::Broadcast::produce();
+	let mut audio_producer = broadcast.producer.create_track(audio.clone());
+	let mut video_producer = broadcast.producer.create_track(video.clone());
+	publisher.producer.publish_broadcast("test", broadcast.consumer);
+
+	let _harness = connect(congested(), publisher.consumer, subscriber.producer).await?;
+	let broadcast = announced(subscriber.consumer).await?;
+
+	let start = Instant::now();
+	let audio_consumer = Consumer::new(broadcast.subscribe_track(&audio), start);
+	let video_consumer = Consumer::new(broadcast.subscribe_track(&video), start);
+
+	// Audio is a small frame every 20ms in a group per second, while video is a 50KB group every 100ms (4Mbit/s).
+	let mut audio_group = audio_producer.append_group();
+	let mut audio_count = 0;
+	let mut video_count = 0;
+	while start.elapsed() < DURATION {
+		if audio_count % 50 == 49 {
+			audio_group.close();
+			audio_group = audio_producer.append_group();
+		}
+
+		audio_group.write_frame(frame(start, 200));
+		audio_count += 1;
+
+		if audio_count % 5 == 1 {
+			video_producer.write_frame(frame(start, 50_000));
+			video_count += 1;
+		}
+
+		tokio::time::sleep(Duration::from_millis(20)).await;
+	}
+
+	// Give the last groups a chance to arrive.
+	tokio::time::sleep(Duration::from_millis(500)).await;
+
+	let audio = audio_consumer.finish();
+	let video = video_consumer.finish();
+
+	// The video can't possibly fit, so some of it must have been dropped.
+	assert!(video.len() < video_count, "video was not congested: {}", video.len());
+
+	// But the audio is higher priority, so most of it arrives promptly.
+	// The path adds ~70ms of delay and queuing, so 500ms leaves plenty of room for scheduling hiccups.
+	let prompt = audio
+		.iter()
+		.filter(|(_, latency)| *latency < Duration::from_millis(500))
+		.count();
+	assert!(
+		prompt * 10 >= audio_count * 8,
+		"only {prompt}/{audio_count} audio frames arrived promptly"
+	);
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn old_groups_are_dropped() -> anyhow::Result<()> {
+	let publisher = moq_lite::Origin::produce();
+	let subscriber = moq_lite::Origin::produce();
+
+	let video = moq_lite::Track::new("video");
+
+	let mut broadcast = moq_lite::Broadcast::produce();
+	let mut producer = broadcast.producer.create_track(video.clone());
+	publisher.producer.publish_broadcast("test", broadcast.consumer);
+
+	let _harness = connect(congested(), publisher.consumer, subscriber.producer).await?;
+	let broadcast = announced(subscriber.consumer).await?;
+
+	let start = Instant::now();
+	let consumer = Consumer::new(broadcast.subscribe_track(&video), start);
+
+	// A 20KB group every 100ms is more than the available bandwidth.
+	let mut count = 0;
+	while start.elapsed() < DURATION {
+		producer.write_frame(frame(start, 20_000));
+		count += 1;
+
+		tokio::time::sleep(Duration::from_millis(100)).await;
+	}
+
+	tokio::time::sleep(Duration::from_millis(500)).await;
+
+	let received = consumer.finish();
+
+	// Groups are skipped rather than queued...
+	assert!(
+		received.len() < count,
+		"no groups were dropped: {}/{count}",
+		received.len()
+	);
+
+	// ...so the subscriber stays close to the live edge.
+	// Each group takes ~160ms to send, so even a slow machine should be within a second (10 groups).
+	let (last, _) = received.last().context("no groups received")?;
+	assert!(*last + 10 >= count as u64, "fell behind: {last}/{count}");
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn hang_skips_late_groups() -> anyhow::Result<()> {
+	let publisher = moq_lite::Origin::produce();
+	let subscriber = moq_lite::Origin::produce();
+
+	let video = moq_lite::Track::new("video");
+
+	let mut broadcast = moq_lite::Broadcast::produce();
+	let mut producer = hang::TrackProducer::new(broadcast.producer.create_track(video.clone()));
+	publisher.producer.publish_broadcast("test", broadcast.consumer);
+
+	let _harness = connect(congested(), publisher.consumer, subscriber.producer).await?;
+	let broadcast = announced(subscriber.consumer).await?;
+
+	let max_latency = Duration::from_millis(300);
+	let mut consumer = hang::TrackConsumer::new(broadcast.subscribe_track(&video), max_latency);
+
+	// The last group is never finished, so collect the timestamps as they're read.
+	let (tx, mut rx) = mpsc::unbounded_channel();
+	let consumer = tokio::spawn(async move {
+		while let Ok(Some(frame)) = consumer.read_frame().await {
+			tx.send(Duration::from_micros(frame.timestamp.as_micros() as u64)).ok();
+		}
+	});
+
+	// A 30KB keyframe every second followed by 10KB delta frames every 40ms, roughly 2Mbit/s.
+	// The group must last longer than the latency target, otherwise the newest group starves the older ones
+	// before it's far enough ahead to skip them.
+	let start = Instant::now();
+	let mut index = 0u64;
+	while start.elapsed() < DURATION {
+		let keyframe = index.is_multiple_of(25);
+		let size = if keyframe { 30_000 } else { 10_000 };
+
+		producer.write(hang::Frame {
+			timestamp: hang::Timestamp::from_millis(index * 40)?,
+			keyframe,
+			payload: vec![0u8; size].into(),
+		})?;
+
+		index += 1;
+		tokio::time::sleep(Duration::from_millis(40)).await;
+	}
+
+	tokio::time::sleep(Duration::from_millis(500)).await;
+	consumer.abort();
+
+	let mut timestamps = Vec::new();
+	while let Ok(timestamp) = rx.try_recv() {
+		timestamps.push(timestamp);
+	}
+	let last = *timestamps.last().context("no frames received")?;
+
+	// Frames are never returned out of order, even when switching groups.
+	assert!(
+		timestamps.windows(2).all(|pair| pair[0] < pair[1]),
+		"frames went backwards"
+	);
+
+	// The consumer skips the tail of a late group once the next group is buffered past the latency target,
+	// so at least once it jumped to a keyframe (every second) before reading the previous group's last frame.
+	let skipped = timestamps
+		.windows(2)
+		.any(|pair| pair[1].as_millis().is_multiple_of(1000) && pair[1] - pair[0] > Duration::from_millis(40));
+	assert!(skipped, "never skipped ahead to a newer group");
+
+	// And it ended up near the live edge.
+	// The latency target is 300ms, so a second leaves room for a slow machine.
+	let end = Duration::from_millis((index - 1) * 40);
+	assert!(end - last < Duration::from_secs(1), "fell behind: {last:?}/{end:?}");
+
+	Ok(())
+}
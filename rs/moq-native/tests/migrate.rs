@@ -0,0 +1,144 @@
+//! Migrate a [moq_native::MigratingSession] to a new server when the old one sends a GOAWAY.
+
+use std::time::Duration;
+
+use anyhow::Context;
+
+fn server() -> anyhow::Result<(moq_native::Server, url::Url)> {
+	let mut config = moq_native::ServerConfig::default();
+	config.bind = Some("127.0.0.1:0".parse()?);
+	config.tls.generate = vec!["localhost".to_string()];
+	let server = config.init()?;
+	let url = format!("moql://127.0.0.1:{}", server.local_addr()?.port()).parse()?;
+	Ok((server, url))
+}
+
+fn client() -> anyhow::Result<moq_native::Client> {
+	let mut config = moq_native::ClientConfig::default();
+	config.bind = "127.0.0.1:0".parse()?;
+	config.tls.disable_verify = Some(true);
+	config.init()
+}
+
+// Accept the next session, publishing the origin's broadcasts.
+async fn accept(
+	server: &mut moq_native::Server,
+	origin: &moq_lite::OriginProducer,
+) -> anyhow::Result<moq_lite::Session> {
+	let request = server.accept().await.context("server closed")?;
+	request.accept(origin.consume(), None).await
+}
+
+#[tokio::test]
+async fn migrate() -> anyhow::Result<()> {
+	let (mut old_server, old_url) = server()?;
+	let (mut new_server, new_url) = server()?;
+
+	let publisher = moq_lite::Origin::produce();
+	let _broadcast = publisher.producer.create_broadcast("demo").context("duplicate")?;
+	let mut subscriber = moq_lite::Origin::produce();
+
+	let client = client()?;
+	let (session, old) = tokio::try_join!(
+		client.connect_migrating(old_url.clone(), None, subscriber.producer),
+		accept(&mut old_server, &publisher.producer),
+	)?;
+
+	let (path, broadcast) = subscriber.consumer.announced().await.context("origin closed")?;
+	assert_eq!(path.as_str(), "demo");
+	assert!(broadcast.is_some());
+
+	let new = tokio::spawn(async move {
+		accept(&mut new_server, &publisher.producer)
+			.await
+			.map(|new| (new, new_server))
+	});
+
+	old.goaway(Some(new_url.as_str()));
+
+	// The old session is closed once the new one has reannounced the broadcast.
+	assert!(old.closed().await.is_err());
+	let (_new, _new_server) = new.await??;
+	assert_eq!(session.url(), new_url);
+
+	// Consumers see a reannounce rather than a gap.
+	let (path, broadcast) = subscriber.consumer.announced().await.context("origin closed")?;
+	assert_eq!(path.as_str(), "demo");
+	assert!(broadcast.is_none());
+
+	let (path, broadcast) = subscriber.consumer.announced().await.context("origin closed")?;
+	assert_eq!(path.as_str(), "demo");
+	assert!(broadcast.is_some());
+
+	tokio::time::sleep(Duration::from_millis(100)).await;
+	assert!(subscriber.consumer.try_announced().is_none());
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn unrelated() -> anyhow::Result<()> {
+	let (mut old_server, old_url) = server()?;
+	let (mut new_server, new_url) = server()?;
+
+	let publisher = moq_lite::Origin::produce();
+	let _broadcast = publisher.producer.create_broadcast("demo").context("duplicate")?;
+
+	// A broadcast published locally to the same origin, which no session will reannounce.
+	let mut subscriber = moq_lite::Origin::produce();
+	let _local = subscriber.producer.create_broadcast("local").context("duplicate")?;
+
+	let client = client()?;
+	let (_session, old) = tokio::try_join!(
+		client.connect_migrating(old_url.clone(), None, subscriber.producer),
+		accept(&mut old_server, &publisher.producer),
+	)?;
+
+	while subscriber
+		.consumer
+		.announced()
+		.await
+		.context("origin closed")?
+		.0
+		.as_str()
+		!= "demo"
+	{}
+
+	let new = tokio::spawn(async move {
+		accept(&mut new_server, &publisher.producer)
+			.await
+			.map(|new| (new, new_server))
+	});
+
+	old.goaway(Some(new_url.as_str()));
+
+	// Only the old session's broadcasts are waited for, so this doesn't take the full reannounce timeout.
+	let closed = tokio::time::timeout(Duration::from_secs(2), old.closed()).await;
+	assert!(closed.context("migration waited for the local broadcast")?.is_err());
+	let (_new, _new_server) = new.await??;
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn invalid_uri() -> anyhow::Result<()> {
+	let (mut server, url) = server()?;
+
+	let publisher = moq_lite::Origin::produce();
+	let subscriber = moq_lite::Origin::produce();
+
+	let client = client()?;
+	let (session, old) = tokio::try_join!(
+		client.connect_migrating(url.clone(), None, subscriber.producer),
+		accept(&mut server, &publisher.producer),
+	)?;
+
+	// The client falls back to reconnecting to the same URL.
+	old.goaway(Some("http://[invalid"));
+	let _new = accept(&mut server, &publisher.producer).await?;
+
+	assert!(old.closed().await.is_err());
+	assert_eq!(session.url(), url);
+
+	Ok(())
+}
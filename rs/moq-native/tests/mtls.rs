@@ -0,0 +1,101 @@
+//! Authenticate clients with a certificate signed by a trusted CA.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+// A CA and a client certificate signed by it, written to a temporary directory as PEM.
+struct Certs {
+	dir: PathBuf,
+	client: Vec<u8>,
+}
+
+impl Certs {
+	fn generate(name: &str) -> anyhow::Result<Self> {
+		let dir = std::env::temp_dir().join(format!("moq-native-{name}-{}", std::process::id()));
+		std::fs::create_dir_all(&dir)?;
+
+		let mut params = rcgen::CertificateParams::new(Vec::new())?;
+		params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+		let ca = rcgen::CertifiedIssuer::self_signed(params, rcgen::KeyPair::generate()?)?;
+		std::fs::write(dir.join("ca.pem"), ca.pem())?;
+
+		let key = rcgen::KeyPair::generate()?;
+		let mut params = rcgen::CertificateParams::new(vec!["device".to_string()])?;
+		params.extended_key_usages = vec![rcgen::ExtendedKeyUsagePurpose::ClientAuth];
+		let client = params.signed_by(&key, &ca)?;
+		std::fs::write(dir.join("client.pem"), client.pem())?;
+		std::fs::write(dir.join("client.key"), key.serialize_pem())?;
+
+		Ok(Self {
+			dir,
+			client: client.der().to_vec(),
+		})
+	}
+
+	fn path(&self, name: &str) -> PathBuf {
+		self.dir.join(name)
+	}
+}
+
+impl Drop for Certs {
+	fn drop(&mut self) {
+		std::fs::remove_dir_all(&self.dir).ok();
+	}
+}
+
+fn server(ca: &Path) -> anyhow::Result<moq_native::Server> {
+	let mut config = moq_native::ServerConfig::default();
+	config.bind = Some("127.0.0.1:0".parse()?);
+	config.tls.generate = vec!["localhost".to_string()];
+	config.tls.client_ca = vec![ca.to_path_buf()];
+	config.init()
+}
+
+fn client(identity: Option<(PathBuf, PathBuf)>) -> anyhow::Result<moq_native::Client> {
+	let mut config = moq_native::ClientConfig::default();
+	config.bind = "127.0.0.1:0".parse()?;
+	config.tls.disable_verify = Some(true);
+	if let Some((cert, key)) = identity {
+		config.tls.cert = Some(cert);
+		config.tls.key = Some(key);
+	}
+	config.init()
+}
+
+#[tokio::test]
+async fn verified() -> anyhow::Result<()> {
+	let certs = Certs::generate("mtls-verified")?;
+	let mut server = server(&certs.path("ca.pem"))?;
+	let client = client(Some((certs.path("client.pem"), certs.path("client.key"))))?;
+
+	let url = format!("moql://localhost:{}", server.local_addr()?.port()).parse()?;
+	let (_client, peer) = tokio::try_join!(client.connect(url, None, None), async {
+		let request = server.accept().await.context("server closed")?;
+		let peer = request.peer_certificates().to_vec();
+		request.accept(None, None).await?;
+		anyhow::Ok(peer)
+	})?;
+
+	assert_eq!(peer.len(), 1);
+	assert_eq!(peer[0].as_ref(), certs.client.as_slice());
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn missing() -> anyhow::Result<()> {
+	let certs = Certs::generate("mtls-missing")?;
+	let mut server = server(&certs.path("ca.pem"))?;
+	let client = client(None)?;
+
+	let url = format!("moql://localhost:{}", server.local_addr()?.port()).parse()?;
+
+	// The server rejects the handshake, so it never produces a request.
+	tokio::select! {
+		res = client.connect(url, None, None) => assert!(res.is_err(), "connected without a certificate"),
+		_ = server.accept() => anyhow::bail!("accepted without a certificate"),
+	}
+
+	Ok(())
+}
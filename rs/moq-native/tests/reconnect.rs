@@ -0,0 +1,128 @@
+//! Reconnect a [moq_native::ReconnectingSession] when the connection is lost, giving up when it can't succeed.
+
+use std::time::Duration;
+
+use anyhow::Context;
+use moq_native::ConnectionState;
+
+fn server() -> anyhow::Result<(moq_native::Server, url::Url)> {
+	let mut config = moq_native::ServerConfig::default();
+	config.bind = Some("127.0.0.1:0".parse()?);
+	config.tls.generate = vec!["localhost".to_string()];
+	let server = config.init()?;
+	let url = format!("moql://127.0.0.1:{}", server.local_addr()?.port()).parse()?;
+	Ok((server, url))
+}
+
+fn client(attempts: Option<u32>) -> anyhow::Result<moq_native::Client> {
+	let mut config = moq_native::ClientConfig::default();
+	config.bind = "127.0.0.1:0".parse()?;
+	config.tls.disable_verify = Some(true);
+	config.reconnect.delay = Some(Duration::from_millis(10));
+	config.reconnect.attempts = attempts;
+	config.init()
+}
+
+// Wait until the session reaches a matching state, as intermediate states may be skipped.
+async fn wait_for(
+	session: &mut moq_native::ReconnectingSession,
+	f: impl Fn(&ConnectionState) -> bool,
+) -> anyhow::Result<ConnectionState> {
+	tokio::time::timeout(Duration::from_secs(10), async {
+		loop {
+			let state = session.changed().await;
+			if f(&state) {
+				return state;
+			}
+		}
+	})
+	.await
+	.context("timed out waiting for state")
+}
+
+#[tokio::test]
+async fn reconnect() -> anyhow::Result<()> {
+	let (mut server, url) = server()?;
+	let origin = moq_lite::Origin::produce();
+
+	let client = client(None)?;
+	let mut session = client.connect_reconnecting(url.clone(), None, origin.producer);
+
+	let request = server.accept().await.context("server closed")?;
+	let first = request.accept(None, None).await?;
+	let state = wait_for(&mut session, |state| matches!(state, ConnectionState::Connected(_))).await?;
+	assert_eq!(state, ConnectionState::Connected(url.clone()));
+
+	// Losing an established session isn't a failed attempt.
+	first.close(moq_lite::Error::Cancel);
+	let state = wait_for(&mut session, |state| {
+		matches!(state, ConnectionState::Reconnecting { .. })
+	})
+	.await?;
+	assert_eq!(
+		state,
+		ConnectionState::Reconnecting {
+			attempt: 0,
+			delay: Duration::from_millis(10)
+		}
+	);
+
+	let request = server.accept().await.context("server closed")?;
+	let _second = request.accept(None, None).await?;
+	let state = wait_for(&mut session, |state| matches!(state, ConnectionState::Connected(_))).await?;
+	assert_eq!(state, ConnectionState::Connected(url));
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn invalid_url() -> anyhow::Result<()> {
+	let origin = moq_lite::Origin::produce();
+
+	let client = client(None)?;
+	let mut session = client.connect_reconnecting("ftp://localhost".parse()?, None, origin.producer);
+
+	// Retrying won't help, so give up immediately.
+	let state = wait_for(&mut session, |state| matches!(state, ConnectionState::Failed(_))).await?;
+	assert!(matches!(state, ConnectionState::Failed(err) if err.contains("ftp")));
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn attempts() -> anyhow::Result<()> {
+	let (mut server, url) = server()?;
+	let origin = moq_lite::Origin::produce();
+
+	// Reject every session.
+	tokio::spawn(async move {
+		while let Some(request) = server.accept().await {
+			let _ = request
+				.reject(moq_native::web_transport_quinn::http::StatusCode::FORBIDDEN)
+				.await;
+		}
+	});
+
+	let client = client(Some(2))?;
+	let mut session = client.connect_reconnecting(url, None, origin.producer);
+
+	let state = wait_for(&mut session, |state| {
+		matches!(state, ConnectionState::Reconnecting { .. })
+	})
+	.await?;
+	assert_eq!(
+		state,
+		ConnectionState::Reconnecting {
+			attempt: 1,
+			delay: Duration::from_millis(10)
+		}
+	);
+
+	let state = wait_for(&mut session, |state| matches!(state, ConnectionState::Failed(_))).await?;
+	assert!(matches!(state, ConnectionState::Failed(_)));
+
+	// The final state sticks.
+	assert!(matches!(session.changed().await, ConnectionState::Failed(_)));
+
+	Ok(())
+}
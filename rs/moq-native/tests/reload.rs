@@ -0,0 +1,83 @@
+//! Reload a [moq_native::Server]'s certificate when the files change on disk.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// A unique directory per test, since tests run in parallel.
+fn tempdir(name: &str) -> anyhow::Result<PathBuf> {
+	let dir = std::env::temp_dir().join(format!("moq-native-{name}-{}", std::process::id()));
+	std::fs::create_dir_all(&dir)?;
+	Ok(dir)
+}
+
+fn generate() -> anyhow::Result<rcgen::CertifiedKey<rcgen::KeyPair>> {
+	Ok(rcgen::generate_simple_self_signed(vec!["localhost".to_string()])?)
+}
+
+// The clock is paused, so each write sets an explicit modification time in case the filesystem's resolution is coarse.
+fn write(dir: &Path, cert: &rcgen::Certificate, key: &rcgen::KeyPair, version: u64) -> anyhow::Result<()> {
+	let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000 + version);
+
+	for (name, contents) in [("cert.pem", cert.pem()), ("key.pem", key.serialize_pem())] {
+		let path = dir.join(name);
+		std::fs::write(&path, contents)?;
+		std::fs::File::options()
+			.write(true)
+			.open(&path)?
+			.set_modified(modified)?;
+	}
+
+	Ok(())
+}
+
+fn fingerprint(server: &moq_native::Server) -> String {
+	server.tls_info().read().unwrap().fingerprints[0].clone()
+}
+
+#[tokio::test(start_paused = true)]
+async fn watch() -> anyhow::Result<()> {
+	let dir = tempdir("watch")?;
+
+	let first = generate()?;
+	write(&dir, &first.cert, &first.signing_key, 0)?;
+
+	let mut config = moq_native::ServerConfig::default();
+	config.bind = Some("127.0.0.1:0".parse()?);
+	config.tls.cert = vec![dir.join("cert.pem")];
+	config.tls.key = vec![dir.join("key.pem")];
+	config.tls.watch = Some(Duration::from_secs(1));
+	let server = config.init()?;
+
+	let original = fingerprint(&server);
+
+	// Nothing changes until the files do.
+	tokio::time::sleep(Duration::from_secs(5)).await;
+	assert_eq!(fingerprint(&server), original);
+
+	// A new certificate and key are picked up on the next poll.
+	let second = generate()?;
+	write(&dir, &second.cert, &second.signing_key, 1)?;
+
+	tokio::time::sleep(Duration::from_secs(2)).await;
+	let reloaded = fingerprint(&server);
+	assert_ne!(reloaded, original);
+
+	// A certificate that doesn't match the key is rejected, keeping the previous one.
+	let third = generate()?;
+	write(&dir, &third.cert, &first.signing_key, 2)?;
+
+	tokio::time::sleep(Duration::from_secs(2)).await;
+	assert_eq!(fingerprint(&server), reloaded);
+
+	// Writing the matching key fixes it.
+	write(&dir, &third.cert, &third.signing_key, 3)?;
+
+	tokio::time::sleep(Duration::from_secs(2)).await;
+	let fixed = fingerprint(&server);
+	assert_ne!(fixed, reloaded);
+	assert_ne!(fixed, original);
+
+	std::fs::remove_dir_all(&dir)?;
+
+	Ok(())
+}
@@ -0,0 +1,94 @@
+//! Connect to a [moq_native::Server] over its WebSocket listeners instead of QUIC.
+
+use anyhow::Context;
+use moq_native::moq_lite;
+
+// Find a free TCP port, since the server doesn't expose the address of its WebSocket listener.
+fn free_port() -> anyhow::Result<u16> {
+	let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+	Ok(listener.local_addr()?.port())
+}
+
+fn server(websocket: moq_native::ServerWebSocketConfig) -> anyhow::Result<moq_native::Server> {
+	let mut config = moq_native::ServerConfig::default();
+	config.bind = Some("127.0.0.1:0".parse()?);
+	config.tls.generate = vec!["localhost".to_string()];
+	config.websocket = websocket;
+	config.init()
+}
+
+// Publish a broadcast from the client and wait for the server to see it.
+async fn announce(
+	client: impl Future<Output = anyhow::Result<moq_lite::Session>>,
+	server: &mut moq_native::Server,
+	publisher: moq_lite::OriginProducer,
+) -> anyhow::Result<String> {
+	let subscriber = moq_lite::Origin::produce();
+
+	let broadcast = moq_lite::Broadcast::produce();
+	publisher.publish_broadcast("test", broadcast.consumer);
+
+	let (_client, (url, _server)) = tokio::try_join!(client, async {
+		let request = server.accept().await.context("server closed")?;
+		let url = request.url().context("missing url")?.to_string();
+		let session = request.accept(None, subscriber.producer).await?;
+		anyhow::Ok((url, session))
+	})?;
+
+	let mut announced = subscriber.consumer;
+	let (path, broadcast) = announced.announced().await.context("origin closed")?;
+	assert_eq!(path.as_str(), "test");
+	assert!(broadcast.is_some());
+
+	Ok(url)
+}
+
+#[tokio::test]
+async fn tcp() -> anyhow::Result<()> {
+	let port = free_port()?;
+
+	let mut websocket = moq_native::ServerWebSocketConfig::default();
+	websocket.bind = Some(format!("127.0.0.1:{port}").parse()?);
+	let mut server = server(websocket)?;
+
+	let client = moq_native::ClientConfig::default().init()?;
+	let publisher = moq_lite::Origin::produce();
+
+	let url = format!("ws://127.0.0.1:{port}/demo?jwt=abc").parse()?;
+	let connect = async { client.connect_with_fallback(url, publisher.consumer, None).await };
+
+	let url = announce(connect, &mut server, publisher.producer).await?;
+	assert_eq!(url, format!("ws://127.0.0.1:{port}/demo?jwt=abc"));
+
+	Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn unix() -> anyhow::Result<()> {
+	use web_transport_ws::tokio_tungstenite;
+
+	let path = std::env::temp_dir().join(format!("moq-native-{}.sock", std::process::id()));
+
+	let mut websocket = moq_native::ServerWebSocketConfig::default();
+	websocket.unix = Some(path.clone());
+	let mut server = server(websocket)?;
+
+	let publisher = moq_lite::Origin::produce();
+
+	let connect = async {
+		let stream = tokio::net::UnixStream::connect(&path).await?;
+		let (ws, _) = tokio_tungstenite::client_async("ws://localhost/demo", stream).await?;
+		let session = web_transport_ws::Session::new(ws, false);
+		Ok(moq_lite::Session::connect(session, publisher.consumer, None).await?)
+	};
+
+	let url = announce(connect, &mut server, publisher.producer).await?;
+	assert_eq!(url, "ws://localhost/demo");
+
+	// The socket is removed when the server is dropped.
+	drop(server);
+	assert!(!path.exists());
+
+	Ok(())
+}
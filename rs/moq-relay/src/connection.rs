@@ -1,12 +1,14 @@
 use crate::{Auth, Cluster};
 
-use moq_native::Request;
+use moq_native::{Drain, Request};
 
 pub struct Connection {
 	pub id: u64,
 	pub request: Request,
 	pub cluster: Cluster,
 	pub auth: Auth,
+	pub drain: Drain,
+	pub limits: moq_lite::Limits,
 }
 
 impl Connection {
@@ -50,9 +52,23 @@ impl Connection {
 		// NOTE: subscribe and publish seem backwards because of how relays work.
 		// We publish the tracks the client is allowed to subscribe to.
 		// We subscribe to the tracks the client is allowed to publish.
-		let session = self.request.accept(subscribe, publish).await?;
+		let session = self.request.accept(subscribe, publish).await?.with_limits(self.limits);
+		let mut drain = self.drain;
 
-		// Wait until the session is closed.
-		session.closed().await.map_err(Into::into)
+		// Wait until the session is closed, asking the client to migrate if we're shutting down.
+		let res = tokio::select! {
+			res = session.closed() => res,
+			url = drain.wait() => {
+				tracing::info!(url = ?url.as_ref().map(|url| url.as_str()), "sending GOAWAY");
+				session.goaway(url.as_ref().map(|url| url.as_str()));
+
+				// Give the client a chance to migrate, until the server closes.
+				session.closed().await
+			}
+		};
+
+		tracing::info!(stats = ?session.stats(), "session closed");
+
+		res.map_err(Into::into)
 	}
 }
@@ -55,6 +55,9 @@ async fn main() -> anyhow::Result<()> {
 			auth: auth.clone(),
 			cluster: cluster.clone(),
 			tls_info: server.tls_info(),
+			acme: server.acme_challenges(),
+			limits: server.limits(),
+			drainer: server.drainer(),
 			conn_id: Default::default(),
 		},
 		config.web,
@@ -78,6 +81,8 @@ async fn main() -> anyhow::Result<()> {
 			request,
 			cluster: cluster.clone(),
 			auth: auth.clone(),
+			drain: server.draining(),
+			limits: server.limits(),
 		};
 
 		conn_id += 1;
@@ -8,11 +8,12 @@ use std::{
 		atomic::{AtomicU64, Ordering},
 	},
 	task::{Context, Poll, ready},
+	time::Duration,
 };
 use web_transport_ws::tungstenite;
 
 use axum::{
-	Router,
+	Json, Router,
 	body::Body,
 	extract::{Path, Query, State, WebSocketUpgrade},
 	http::{Method, StatusCode},
@@ -31,6 +32,15 @@ use crate::{Auth, Cluster};
 #[derive(Debug, Deserialize)]
 struct Params {
 	jwt: Option<String>,
+
+	// Set to "json" to include metadata in the response, used by /announced.
+	format: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Announced {
+	path: String,
+	metadata: moq_lite::Metadata,
 }
 
 #[derive(Parser, Clone, Debug, Deserialize, Serialize, Default)]
@@ -70,12 +80,25 @@ pub struct HttpsConfig {
 	/// Load the given key from disk.
 	#[arg(long = "web-https-key", id = "web-https-key", env = "MOQ_WEB_HTTPS_KEY")]
 	pub key: Option<PathBuf>,
+
+	/// Poll the certificate and key files at this interval, reloading them when changed.
+	#[arg(
+		long = "web-https-watch",
+		id = "web-https-watch",
+		env = "MOQ_WEB_HTTPS_WATCH",
+		value_parser = humantime::parse_duration
+	)]
+	#[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+	pub watch: Option<Duration>,
 }
 
 pub struct WebState {
 	pub auth: Auth,
 	pub cluster: Cluster,
 	pub tls_info: Arc<std::sync::RwLock<moq_native::ServerTlsInfo>>,
+	pub acme: moq_native::AcmeChallenges,
+	pub limits: moq_lite::Limits,
+	pub drainer: moq_native::Drainer,
 	pub conn_id: AtomicU64,
 }
 
@@ -91,20 +114,7 @@ impl Web {
 	}
 
 	pub async fn run(self) -> anyhow::Result<()> {
-		let app = Router::new()
-			.route("/certificate.sha256", get(serve_fingerprint))
-			.route("/announced", get(serve_announced))
-			.route("/announced/{*prefix}", get(serve_announced))
-			.route("/fetch/{*path}", get(serve_fetch));
-
-		// If WebSocket is enabled, add the WebSocket route.
-		let app = match self.config.ws {
-			true => app.route("/{*path}", any(serve_ws)),
-			false => app,
-		}
-		.layer(CorsLayer::new().allow_origin(Any).allow_methods([Method::GET]))
-		.with_state(Arc::new(self.state))
-		.into_make_service();
+		let app = router(self.state, self.config.ws).into_make_service();
 
 		let http = if let Some(listen) = self.config.http.listen {
 			let server = axum_server::bind(listen);
@@ -119,7 +129,11 @@ impl Web {
 			let config = axum_server::tls_rustls::RustlsConfig::from_pem_file(cert.clone(), key.clone()).await?;
 
 			#[cfg(unix)]
-			tokio::spawn(reload_certs(config.clone(), cert, key));
+			tokio::spawn(reload_certs(config.clone(), cert.clone(), key.clone()));
+
+			if let Some(interval) = self.config.https.watch {
+				tokio::spawn(watch_certs(config.clone(), cert, key, interval));
+			}
 
 			let server = axum_server::bind_rustls(listen, config);
 			Some(server.serve(app))
@@ -137,6 +151,24 @@ impl Web {
 	}
 }
 
+fn router(state: WebState, ws: bool) -> Router {
+	let app = Router::new()
+		.route("/certificate.sha256", get(serve_fingerprint))
+		.route("/certificate.json", get(serve_fingerprints))
+		.route("/.well-known/acme-challenge/{token}", get(serve_acme_challenge))
+		.route("/announced", get(serve_announced))
+		.route("/announced/{*prefix}", get(serve_announced))
+		.route("/fetch/{*path}", get(serve_fetch));
+
+	// If WebSocket is enabled, add the WebSocket route.
+	match ws {
+		true => app.route("/{*path}", any(serve_ws)),
+		false => app,
+	}
+	.layer(CorsLayer::new().allow_origin(Any).allow_methods([Method::GET]))
+	.with_state(Arc::new(state))
+}
+
 #[cfg(unix)]
 async fn reload_certs(config: axum_server::tls_rustls::RustlsConfig, cert: PathBuf, key: PathBuf) {
 	use tokio::signal::unix::{SignalKind, signal};
@@ -153,9 +185,22 @@ async fn reload_certs(config: axum_server::tls_rustls::RustlsConfig, cert: PathB
 	}
 }
 
+async fn watch_certs(config: axum_server::tls_rustls::RustlsConfig, cert: PathBuf, key: PathBuf, interval: Duration) {
+	let mut watch = moq_native::FileWatch::new(vec![cert.clone(), key.clone()], interval);
+
+	loop {
+		watch.changed().await;
+		tracing::info!("web certificate changed, reloading");
+
+		// The old certificate is kept if the new pair fails to load.
+		if let Err(err) = config.reload_from_pem_file(cert.clone(), key.clone()).await {
+			tracing::warn!(%err, "failed to reload web certificate");
+		}
+	}
+}
+
 async fn serve_fingerprint(State(state): State<Arc<WebState>>) -> String {
-	// Get the first certificate's fingerprint.
-	// TODO serve all of them so we can support multiple signature algorithms.
+	// Get the preferred certificate's fingerprint, for clients that only support one.
 	state
 		.tls_info
 		.read()
@@ -166,12 +211,28 @@ async fn serve_fingerprint(State(state): State<Arc<WebState>>) -> String {
 		.clone()
 }
 
+async fn serve_fingerprints(State(state): State<Arc<WebState>>) -> Json<Vec<moq_native::Fingerprint>> {
+	Json(state.tls_info.read().expect("tls_info lock poisoned").hashes.clone())
+}
+
+async fn serve_acme_challenge(
+	Path(token): Path<String>,
+	State(state): State<Arc<WebState>>,
+) -> axum::response::Result<String> {
+	state.acme.get(&token).ok_or_else(|| StatusCode::NOT_FOUND.into())
+}
+
 async fn serve_ws(
 	ws: WebSocketUpgrade,
 	Path(path): Path<String>,
 	Query(params): Query<Params>,
 	State(state): State<Arc<WebState>>,
 ) -> axum::response::Result<Response> {
+	// Refuse new sessions while draining, just like the QUIC listener.
+	if state.drainer.is_draining() {
+		return Err(StatusCode::SERVICE_UNAVAILABLE.into());
+	}
+
 	let ws = ws.protocols(["webtransport"]);
 
 	let token = state.auth.verify(&path, params.jwt.as_deref())?;
@@ -196,7 +257,8 @@ async fn serve_ws(
 				tungstenite::Error::ConnectionClosed
 			})
 			.with(tungstenite_to_axum);
-		let _ = handle_socket(id, socket, publish, subscribe).await;
+		let drain = state.drainer.draining();
+		let _ = handle_socket(id, socket, publish, subscribe, state.limits.clone(), drain).await;
 	}))
 }
 
@@ -206,6 +268,8 @@ async fn handle_socket<T>(
 	socket: T,
 	publish: Option<OriginProducer>,
 	subscribe: Option<OriginConsumer>,
+	limits: moq_lite::Limits,
+	mut drain: moq_native::Drain,
 ) -> anyhow::Result<()>
 where
 	T: futures::Stream<Item = Result<tungstenite::Message, tungstenite::Error>>
@@ -216,16 +280,29 @@ where
 {
 	// Wrap the WebSocket in a WebTransport compatibility layer.
 	let ws = web_transport_ws::Session::new(socket, true);
-	let session = moq_lite::Session::accept(ws, subscribe, publish).await?;
-	session.closed().await.map_err(Into::into)
+	let session = moq_lite::Session::accept(ws, subscribe, publish)
+		.await?
+		.with_limits(limits);
+
+	// Wait until the session is closed, asking the client to migrate if we're shutting down.
+	let res = tokio::select! {
+		res = session.closed() => res,
+		url = drain.wait() => {
+			tracing::info!(url = ?url.as_ref().map(|url| url.as_str()), "sending GOAWAY");
+			session.goaway(url.as_ref().map(|url| url.as_str()));
+			session.closed().await
+		}
+	};
+
+	res.map_err(Into::into)
 }
 
-/// Serve the announced broadcasts for a given prefix.
+/// Serve the announced broadcasts for a given prefix, as plain text or JSON with metadata.
 async fn serve_announced(
 	path: Option<Path<String>>,
 	Query(params): Query<Params>,
 	State(state): State<Arc<WebState>>,
-) -> axum::response::Result<String> {
+) -> axum::response::Result<Response> {
 	let prefix = match path {
 		Some(Path(prefix)) => prefix,
 		None => String::new(),
@@ -239,12 +316,24 @@ async fn serve_announced(
 	let mut broadcasts = Vec::new();
 
 	while let Some((suffix, active)) = origin.try_announced() {
-		if active.is_some() {
-			broadcasts.push(suffix);
+		if let Some(active) = active {
+			broadcasts.push(Announced {
+				path: suffix.to_string(),
+				metadata: active.metadata().clone(),
+			});
 		}
 	}
 
-	Ok(broadcasts.iter().map(|p| p.to_string()).collect::<Vec<_>>().join("\n"))
+	match params.format.as_deref() {
+		None | Some("text") => Ok(broadcasts
+			.iter()
+			.map(|broadcast| broadcast.path.as_str())
+			.collect::<Vec<_>>()
+			.join("\n")
+			.into_response()),
+		Some("json") => Ok(Json(broadcasts).into_response()),
+		Some(_) => Err(StatusCode::BAD_REQUEST.into()),
+	}
 }
 
 /// Serve the latest group for a given track
@@ -404,3 +493,71 @@ fn tungstenite_to_axum(
 fn default_true() -> bool {
 	true
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+	use crate::{AuthConfig, ClusterConfig};
+
+	// Send a WebSocket upgrade request, returning the status line of the response.
+	async fn upgrade(addr: net::SocketAddr) -> anyhow::Result<String> {
+		let mut stream = tokio::net::TcpStream::connect(addr).await?;
+		let request = format!(
+			"GET /anon HTTP/1.1\r\n\
+			Host: {addr}\r\n\
+			Connection: Upgrade\r\n\
+			Upgrade: websocket\r\n\
+			Sec-WebSocket-Version: 13\r\n\
+			Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+			Sec-WebSocket-Protocol: webtransport\r\n\r\n"
+		);
+		stream.write_all(request.as_bytes()).await?;
+
+		let mut buf = vec![0; 1024];
+		let size = stream.read(&mut buf).await?;
+		let response = String::from_utf8_lossy(&buf[..size]);
+		Ok(response.lines().next().unwrap_or_default().to_string())
+	}
+
+	#[tokio::test]
+	async fn ws_drain() -> anyhow::Result<()> {
+		let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+
+		let mut config = moq_native::ServerConfig::default();
+		config.bind = Some("127.0.0.1:0".parse()?);
+		config.tls.generate = vec!["localhost".to_string()];
+		let mut server = config.init()?;
+
+		let auth = Auth::new(AuthConfig {
+			key: None,
+			public: Some("anon".to_string()),
+		})?;
+		let client = moq_native::ClientConfig::default().init()?;
+
+		let state = WebState {
+			auth,
+			cluster: Cluster::new(ClusterConfig::default(), client),
+			tls_info: server.tls_info(),
+			acme: server.acme_challenges(),
+			limits: server.limits(),
+			drainer: server.drainer(),
+			conn_id: Default::default(),
+		};
+
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+		let addr = listener.local_addr()?;
+		let app = router(state, true).into_make_service();
+		tokio::spawn(async move { axum::serve(listener, app).await });
+
+		assert_eq!(upgrade(addr).await?, "HTTP/1.1 101 Switching Protocols");
+
+		// New WebSocket sessions are refused once the server starts draining.
+		server.drain().await;
+		assert_eq!(upgrade(addr).await?, "HTTP/1.1 503 Service Unavailable");
+
+		Ok(())
+	}
+}